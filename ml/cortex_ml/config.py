@@ -5,6 +5,10 @@ class Settings(BaseSettings):
     grpc_port: int = 50051
     embedding_model: str = "all-MiniLM-L6-v2"
     reranker_model: str = "cross-encoder/ms-marco-MiniLM-L-12-v2"
+    # Models a Rerank request may ask for besides reranker_model, as a JSON list
+    reranker_allowed_models: list[str] = []
+    # Rerankers kept loaded; the least recently used is evicted past this
+    reranker_cache_size: int = 2
 
     # LLM provider API keys
     anthropic_api_key: str = ""
@@ -0,0 +1,25 @@
+from collections import OrderedDict
+
+from ..config import settings
+from .base import Reranker
+from .cross_encoder_reranker import CrossEncoderReranker
+
+_rerankers: OrderedDict[str, Reranker] = OrderedDict()
+
+
+def is_allowed_reranker(model_name: str) -> bool:
+    return model_name == settings.reranker_model or model_name in settings.reranker_allowed_models
+
+
+def get_reranker(model_name: str, device: str = "cpu") -> Reranker:
+    if not is_allowed_reranker(model_name):
+        raise ValueError(f"Reranker model not allowed: {model_name}")
+
+    if model_name in _rerankers:
+        _rerankers.move_to_end(model_name)
+        return _rerankers[model_name]
+
+    _rerankers[model_name] = CrossEncoderReranker(model_name, device)
+    while len(_rerankers) > max(settings.reranker_cache_size, 1):
+        _rerankers.popitem(last=False)
+    return _rerankers[model_name]
@@ -21,7 +21,7 @@ from .embeddings.registry import get_embedding_provider
 from .llm.registry import get_llm_provider
 from .llm.base import GenerationConfig
 from .reranker.base import RankedDocument
-from .reranker.cross_encoder_reranker import CrossEncoderReranker
+from .reranker.registry import get_reranker
 
 logging.basicConfig(
     level=logging.INFO,
@@ -38,7 +38,7 @@ class MlServiceServicer(ml_service_pb2_grpc.MlServiceServicer):
     def __init__(self):
         logger.info("Initializing ML service...")
         self.embedder = get_embedding_provider(settings.embedding_model, settings.device)
-        self.reranker = CrossEncoderReranker(settings.reranker_model, settings.device)
+        self.reranker = get_reranker(settings.reranker_model, settings.device)
         logger.info("ML service initialized")
 
     async def EmbedBatch(self, request, context):
@@ -59,6 +59,7 @@ class MlServiceServicer(ml_service_pb2_grpc.MlServiceServicer):
     async def Rerank(self, request, context):
         query = request.query
         top_k = request.top_k or 10
+        model = request.model or settings.reranker_model
 
         documents = [
             RankedDocument(
@@ -70,8 +71,14 @@ class MlServiceServicer(ml_service_pb2_grpc.MlServiceServicer):
             for doc in request.documents
         ]
 
-        logger.info(f"Rerank: query='{query[:50]}...', {len(documents)} docs, top_k={top_k}")
-        reranked = self.reranker.rerank(query, documents, top_k)
+        logger.info(
+            f"Rerank: query='{query[:50]}...', {len(documents)} docs, top_k={top_k}, model={model}"
+        )
+        try:
+            reranker = get_reranker(model, settings.device)
+        except ValueError as e:
+            await context.abort(grpc.StatusCode.INVALID_ARGUMENT, str(e))
+        reranked = reranker.rerank(query, documents, top_k)
 
         response_docs = [
             ml_service_pb2.RerankDocument(
@@ -28,3 +28,11 @@ def test_reranker_reorders_by_relevance():
     assert len(reranked) == 2
     # The ML-related document should be ranked first
     assert reranked[0].id == "2"
+
+
+def test_reranker_rejects_models_not_allowed():
+    """Test that only the configured reranker models can be loaded."""
+    from cortex_ml.reranker.registry import get_reranker
+
+    with pytest.raises(ValueError):
+        get_reranker("someone/untrusted-model")
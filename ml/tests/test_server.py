@@ -0,0 +1,42 @@
+"""Tests for the gRPC servicer."""
+import grpc
+import pytest
+
+
+class AbortError(Exception):
+    pass
+
+
+class FakeContext:
+    """Records an abort the way grpc.aio does: awaited, then raising."""
+
+    def __init__(self):
+        self.code = None
+        self.details = None
+
+    async def abort(self, code, details):
+        self.code = code
+        self.details = details
+        raise AbortError(details)
+
+
+@pytest.mark.asyncio
+async def test_rerank_rejects_models_not_allowed():
+    """Test that a disallowed reranker model aborts with INVALID_ARGUMENT."""
+    from cortex_ml.proto import ml_service_pb2
+    from cortex_ml.server import MlServiceServicer
+
+    # Skip __init__, which loads the default models
+    servicer = MlServiceServicer.__new__(MlServiceServicer)
+    request = ml_service_pb2.RerankRequest(
+        query="What is machine learning?",
+        documents=[ml_service_pb2.RerankDocument(id="1", text="Machine learning", score=0.5)],
+        top_k=1,
+        model="someone/untrusted-model",
+    )
+    context = FakeContext()
+
+    with pytest.raises(AbortError):
+        await servicer.Rerank(request, context)
+
+    assert context.code == grpc.StatusCode.INVALID_ARGUMENT
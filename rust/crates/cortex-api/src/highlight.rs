@@ -0,0 +1,178 @@
+//! Query term highlighting and query-biased snippets for search results.
+//!
+//! Offsets count characters (Unicode scalar values), not bytes, so clients
+//! can slice the text without knowing its encoding.
+
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Target snippet length in characters.
+const SNIPPET_CHARS: usize = 200;
+
+/// Context kept before the first match of a snippet.
+const SNIPPET_LEAD_CHARS: usize = 40;
+
+/// Words too common to be worth highlighting, like the keyword index's
+/// stopwords.
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is", "it",
+    "no", "not", "of", "on", "or", "such", "that", "the", "their", "then", "there", "these",
+    "they", "this", "to", "was", "will", "with",
+];
+
+/// A query term occurrence, `start..end` in characters.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Highlight {
+    pub start: usize,
+    pub end: usize,
+    /// The query term it matched, lowercased.
+    pub term: String,
+}
+
+/// The part of a text that best matches the query.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snippet {
+    pub text: String,
+    /// Where the snippet sits in the full text, in characters.
+    pub start: usize,
+    pub end: usize,
+    /// Matches within the snippet, relative to its start.
+    pub highlights: Vec<Highlight>,
+}
+
+/// The query's terms, tokenized like keyword search.
+pub struct QueryTerms(HashSet<String>);
+
+impl QueryTerms {
+    pub fn new(query: &str) -> Self {
+        Self(
+            cortex_store::hybrid::tokenize(query)
+                .into_iter()
+                .filter(|term| !STOPWORDS.contains(&term.as_str()))
+                .collect(),
+        )
+    }
+
+    /// Every occurrence of a query term in `text`.
+    pub fn highlights(&self, text: &str) -> Vec<Highlight> {
+        words(text)
+            .into_iter()
+            .filter(|word| self.0.contains(&word.term))
+            .collect()
+    }
+
+    /// The window of about `SNIPPET_CHARS` characters covering the most
+    /// distinct query terms, then the most matches, starting a little before
+    /// its first match. Without matches it is the start of the text.
+    pub fn snippet(&self, text: &str) -> Snippet {
+        let words = words(text);
+        let matches: Vec<&Highlight> = words.iter().filter(|w| self.0.contains(&w.term)).collect();
+
+        let mut best = (0, 0);
+        let mut start = 0;
+        for (i, anchor) in matches.iter().enumerate() {
+            let in_window: Vec<&&Highlight> = matches[i..]
+                .iter()
+                .take_while(|m| m.end <= anchor.start + SNIPPET_CHARS - SNIPPET_LEAD_CHARS)
+                .collect();
+            let distinct: HashSet<&str> = in_window.iter().map(|m| m.term.as_str()).collect();
+            let score = (distinct.len(), in_window.len());
+            if score > best {
+                best = score;
+                start = anchor.start.saturating_sub(SNIPPET_LEAD_CHARS);
+            }
+        }
+
+        // Snap to whole words: start at the first word that begins in the
+        // window and end after the last word that fits
+        let start = if start == 0 {
+            0
+        } else {
+            words
+                .iter()
+                .find(|w| w.start >= start)
+                .map_or(start, |w| w.start)
+        };
+        let limit = start + SNIPPET_CHARS;
+        let end = words
+            .iter()
+            .take_while(|w| w.end <= limit)
+            .last()
+            .map_or(limit, |w| w.end)
+            .min(text.chars().count());
+        let end = end.max(start);
+
+        let highlights = matches
+            .into_iter()
+            .filter(|m| m.start >= start && m.end <= end)
+            .map(|m| Highlight {
+                start: m.start - start,
+                end: m.end - start,
+                term: m.term.clone(),
+            })
+            .collect();
+        Snippet {
+            text: text.chars().skip(start).take(end - start).collect(),
+            start,
+            end,
+            highlights,
+        }
+    }
+}
+
+/// Alphanumeric words of `text` with their character offsets, lowercased.
+fn words(text: &str) -> Vec<Highlight> {
+    let mut words = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    for (position, c) in text.chars().enumerate() {
+        if c.is_alphanumeric() {
+            current
+                .get_or_insert_with(|| (position, String::new()))
+                .1
+                .extend(c.to_lowercase());
+        } else if let Some((start, term)) = current.take() {
+            words.push(Highlight {
+                start,
+                end: position,
+                term,
+            });
+        }
+    }
+    if let Some((start, term)) = current {
+        words.push(Highlight {
+            start,
+            end: text.chars().count(),
+            term,
+        });
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippet_picks_best_matching_window() {
+        let filler = "Lorem ipsum dolor sit amet. ".repeat(20);
+        let text = format!(
+            "{filler}Refunds are issued within 14 days of the return. {filler}Refunds only."
+        );
+        let terms = QueryTerms::new("the refund days");
+
+        let highlights = terms.highlights("The Refunds take 14 days");
+        assert_eq!(highlights.len(), 1);
+        assert_eq!((highlights[0].start, highlights[0].end), (20, 24));
+
+        let terms = QueryTerms::new("refunds days");
+        let snippet = terms.snippet(&text);
+        assert!(snippet.text.contains("Refunds are issued within 14 days"));
+        assert!(snippet.text.chars().count() <= SNIPPET_CHARS);
+        let spans: Vec<&str> = snippet
+            .highlights
+            .iter()
+            .map(|h| &snippet.text[h.start..h.end])
+            .collect();
+        assert_eq!(spans, ["Refunds", "days"]);
+    }
+}
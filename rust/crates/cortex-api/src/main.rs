@@ -1,6 +1,9 @@
 use cortex_common::{config::AppConfig, telemetry};
 
 mod error;
+mod highlight;
+mod retrieval;
+mod rewrite;
 mod routes;
 mod state;
 
@@ -13,10 +16,10 @@ async fn main() -> anyhow::Result<()> {
 
     let app_state = state::AppState::new(&config).await?;
 
-    // Ensure Weaviate schema exists
-    app_state.weaviate.ensure_schema().await.map_err(|e| {
-        tracing::error!("Failed to create Weaviate schema: {e}");
-        anyhow::anyhow!("Weaviate schema error: {e}")
+    // Ensure the default vector index exists
+    app_state.vector_store.ensure_index().await.map_err(|e| {
+        tracing::error!("Failed to create vector index: {e}");
+        anyhow::anyhow!("vector index error: {e}")
     })?;
 
     let app = routes::create_router(app_state);
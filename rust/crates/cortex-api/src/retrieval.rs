@@ -0,0 +1,804 @@
+use cortex_common::types::*;
+use cortex_ml_client::{MlClient, MlClientError};
+use cortex_store::hybrid::{self, Contributions, FusionMethod};
+use cortex_store::models::{ChunkRecord, ParentChunk, SearchFilters, SearchResult};
+use cortex_store::postgres::PostgresStore;
+use cortex_store::vector::{VectorStore, VectorStoreError};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+/// Most neighbors a hit may be expanded by on each side.
+pub const MAX_CONTEXT_WINDOW: usize = 5;
+
+/// Most results one request may ask for.
+pub const MAX_TOP_K: usize = 100;
+
+/// Most candidates one request may retrieve and rerank.
+pub const MAX_CANDIDATES: usize = 1000;
+
+/// A search hit, with the cross-encoder's score once it has been reranked.
+/// `result.score` stays the retrieval score.
+#[derive(Debug, Clone)]
+pub struct RankedHit {
+    pub result: SearchResult,
+    pub rerank_score: Option<f32>,
+    /// What each retriever added, when they were fused here rather than by
+    /// the store.
+    pub contributions: Option<Contributions>,
+    /// The chunks joined into `result.text`, in document order, once the
+    /// hit has been expanded to its context window.
+    pub window: Option<Vec<ChunkId>>,
+}
+
+/// One retrieval from the vector store.
+pub struct SearchParams<'a> {
+    pub query: &'a str,
+    pub vector: &'a [f32],
+    pub user_id: UserId,
+    pub filters: &'a SearchFilters,
+    pub limit: usize,
+    /// Weight of vector search against keyword search, 1.0 being pure vector.
+    pub alpha: f32,
+    /// Fuse keyword and vector results here instead of using the store's
+    /// hybrid search.
+    pub fusion: Option<FusionMethod>,
+}
+
+impl RankedHit {
+    /// The score the hit is ordered by.
+    pub fn score(&self) -> f32 {
+        self.rerank_score.unwrap_or(self.result.score)
+    }
+}
+
+impl From<SearchResult> for RankedHit {
+    fn from(result: SearchResult) -> Self {
+        Self {
+            result,
+            rerank_score: None,
+            contributions: None,
+            window: None,
+        }
+    }
+}
+
+/// Hybrid search, either by the store or by running each retriever on its
+/// own and fusing the results with `params.fusion`.
+pub async fn search(
+    store: &dyn VectorStore,
+    params: &SearchParams<'_>,
+) -> Result<Vec<RankedHit>, VectorStoreError> {
+    let SearchParams {
+        query,
+        vector,
+        user_id,
+        filters,
+        limit,
+        alpha,
+        fusion,
+    } = *params;
+
+    let Some(method) = fusion else {
+        let results = store
+            .hybrid_search(query, vector, user_id, filters, limit, alpha)
+            .await?;
+        return Ok(results.into_iter().map(RankedHit::from).collect());
+    };
+
+    // A retriever with no weight can't change the ranking
+    let keyword = async {
+        if alpha < 1.0 {
+            store.keyword_search(query, user_id, filters, limit).await
+        } else {
+            Ok(Vec::new())
+        }
+    };
+    let semantic = async {
+        if alpha > 0.0 {
+            store.vector_search(vector, user_id, filters, limit).await
+        } else {
+            Ok(Vec::new())
+        }
+    };
+    let (keyword, semantic) = futures::try_join!(keyword, semantic)?;
+    Ok(fuse(keyword, semantic, method, alpha, limit))
+}
+
+/// Search once per params, as for rewritten queries, and fuse the result
+/// lists with reciprocal rank fusion.
+pub async fn search_many(
+    store: &dyn VectorStore,
+    params: &[SearchParams<'_>],
+) -> Result<Vec<RankedHit>, VectorStoreError> {
+    let lists =
+        futures::future::try_join_all(params.iter().map(|params| search(store, params))).await?;
+    let limit = params.first().map_or(0, |params| params.limit);
+    Ok(fuse_queries(lists, limit))
+}
+
+/// The `limit` best hits across the searches of several queries. A single
+/// search's results are returned as they are; fused hits are scored by RRF
+/// and lose their per-retriever contributions.
+fn fuse_queries(mut lists: Vec<Vec<RankedHit>>, limit: usize) -> Vec<RankedHit> {
+    if lists.len() <= 1 {
+        return lists.pop().unwrap_or_default();
+    }
+
+    let ranked: Vec<Vec<(ChunkId, f32)>> = lists
+        .iter()
+        .map(|hits| {
+            hits.iter()
+                .map(|hit| (hit.result.chunk_id, hit.score()))
+                .collect()
+        })
+        .collect();
+    let ranked: Vec<&[(ChunkId, f32)]> = ranked.iter().map(Vec::as_slice).collect();
+    let mut hits: HashMap<ChunkId, RankedHit> = HashMap::new();
+    for hit in lists.into_iter().flatten() {
+        hits.entry(hit.result.chunk_id).or_insert(hit);
+    }
+    hybrid::fuse_many(&ranked, FusionMethod::Rrf)
+        .into_iter()
+        .take(limit)
+        .filter_map(|(id, score)| {
+            let mut hit = hits.remove(&id)?;
+            hit.result.score = score;
+            hit.contributions = None;
+            Some(hit)
+        })
+        .collect()
+}
+
+fn fuse(
+    keyword: Vec<SearchResult>,
+    semantic: Vec<SearchResult>,
+    method: FusionMethod,
+    alpha: f32,
+    limit: usize,
+) -> Vec<RankedHit> {
+    let mut results: HashMap<ChunkId, SearchResult> = HashMap::new();
+    let mut scores = |hits: Vec<SearchResult>| -> Vec<(ChunkId, f32)> {
+        hits.into_iter()
+            .map(|result| {
+                let scored = (result.chunk_id, result.score);
+                results.insert(result.chunk_id, result);
+                scored
+            })
+            .collect()
+    };
+    let keyword = scores(keyword);
+    let semantic = scores(semantic);
+
+    hybrid::fuse(&keyword, &semantic, method, alpha)
+        .into_iter()
+        .take(limit)
+        .filter_map(|fused| {
+            let mut result = results.remove(&fused.key)?;
+            result.score = fused.score;
+            Some(RankedHit {
+                contributions: Some(fused.contributions),
+                ..RankedHit::from(result)
+            })
+        })
+        .collect()
+}
+
+/// Pick `top_k` of the ranked hits, best first. With `mmr_lambda`, hits are
+/// re-selected by Maximal Marginal Relevance over their stored embeddings,
+/// trading relevance (1.0) against similarity to hits already picked (0.0).
+/// `max_per_document` caps how many hits a single document may fill.
+pub async fn diversify(
+    store: &dyn VectorStore,
+    hits: Vec<RankedHit>,
+    top_k: usize,
+    mmr_lambda: Option<f32>,
+    max_per_document: Option<usize>,
+) -> Result<Vec<RankedHit>, VectorStoreError> {
+    let vectors = match mmr_lambda {
+        Some(_) => {
+            let ids: Vec<ChunkId> = hits.iter().map(|hit| hit.result.chunk_id).collect();
+            store.chunk_vectors(&ids).await?
+        }
+        None => HashMap::new(),
+    };
+    Ok(select_diverse(
+        hits,
+        &vectors,
+        top_k,
+        mmr_lambda.unwrap_or(1.0),
+        max_per_document,
+    ))
+}
+
+/// Reject diversity options `diversify` can't honor.
+pub fn check_diversity(
+    mmr_lambda: Option<f32>,
+    max_per_document: Option<usize>,
+) -> Result<(), String> {
+    if mmr_lambda.is_some_and(|lambda| !(0.0..=1.0).contains(&lambda)) {
+        return Err("mmr_lambda must be between 0 and 1".to_string());
+    }
+    if max_per_document == Some(0) {
+        return Err("max_chunks_per_document must be at least 1".to_string());
+    }
+    Ok(())
+}
+
+fn select_diverse(
+    hits: Vec<RankedHit>,
+    vectors: &HashMap<ChunkId, Vec<f32>>,
+    top_k: usize,
+    lambda: f32,
+    max_per_document: Option<usize>,
+) -> Vec<RankedHit> {
+    // Rerank scores are unbounded, so scale relevance to [0, 1] to be
+    // comparable with cosine similarity
+    let scores: Vec<f32> = hits.iter().map(RankedHit::score).collect();
+    let min = scores.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let relevance = |i: usize| {
+        if max > min {
+            (scores[i] - min) / (max - min)
+        } else {
+            1.0
+        }
+    };
+    let vector = |i: usize| vectors.get(&hits[i].result.chunk_id);
+
+    let mut remaining: Vec<usize> = (0..hits.len()).collect();
+    let mut selected: Vec<usize> = Vec::with_capacity(top_k);
+    let mut per_document: HashMap<DocumentId, usize> = HashMap::new();
+    while selected.len() < top_k {
+        remaining.retain(|&i| {
+            let count = per_document.get(&hits[i].result.document_id);
+            max_per_document.is_none_or(|cap| count.copied().unwrap_or(0) < cap)
+        });
+
+        let best = remaining
+            .iter()
+            .enumerate()
+            .map(|(position, &i)| {
+                let redundancy = selected
+                    .iter()
+                    .filter_map(|&j| Some(hybrid::cosine_similarity(vector(i)?, vector(j)?)))
+                    .fold(0.0, f32::max);
+                let score = lambda * relevance(i) - (1.0 - lambda) * redundancy;
+                (position, score)
+            })
+            // The first of equal scores wins, keeping the original order
+            .min_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        let Some((position, _)) = best else {
+            break;
+        };
+
+        let i = remaining.remove(position);
+        *per_document.entry(hits[i].result.document_id).or_default() += 1;
+        selected.push(i);
+    }
+
+    let mut hits: Vec<Option<RankedHit>> = hits.into_iter().map(Some).collect();
+    selected
+        .into_iter()
+        .filter_map(|i| hits[i].take())
+        .collect()
+}
+
+/// Replace child-chunk hits with the text of their parent sections.
+///
+/// Hits are expected in descending score order. Several children of the same
+/// parent collapse into a single result that keeps the best child's score.
+pub async fn expand_to_parents(
+    postgres: &PostgresStore,
+    results: Vec<RankedHit>,
+) -> Result<Vec<RankedHit>, sqlx::Error> {
+    let parent_ids: Vec<_> = results
+        .iter()
+        .filter_map(|hit| hit.result.parent_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    if parent_ids.is_empty() {
+        return Ok(results);
+    }
+
+    let parents = postgres.get_parent_chunks(&parent_ids).await?;
+    Ok(merge_parents(results, parents))
+}
+
+pub fn check_limits(top_k: usize, rerank_top_n: Option<usize>) -> Result<(), String> {
+    if top_k == 0 || top_k > MAX_TOP_K {
+        return Err(format!("top_k must be between 1 and {MAX_TOP_K}"));
+    }
+    if rerank_top_n.is_some_and(|n| n > MAX_CANDIDATES) {
+        return Err(format!("rerank_top_n can be at most {MAX_CANDIDATES}"));
+    }
+    Ok(())
+}
+
+pub fn check_context_window(window: usize) -> Result<(), String> {
+    if window > MAX_CONTEXT_WINDOW {
+        return Err(format!(
+            "context_window can be at most {MAX_CONTEXT_WINDOW}"
+        ));
+    }
+    Ok(())
+}
+
+/// Replace each hit's text with the passage of `window` chunks before and
+/// after it in its document. Windows that overlap or touch are merged into
+/// one passage, kept at the position of its best hit.
+pub async fn expand_to_windows(
+    postgres: &PostgresStore,
+    hits: Vec<RankedHit>,
+    window: usize,
+) -> Result<Vec<RankedHit>, sqlx::Error> {
+    if hits.is_empty() || window == 0 {
+        return Ok(hits);
+    }
+
+    let ids: Vec<ChunkId> = hits.iter().map(|hit| hit.result.chunk_id).collect();
+    let positions: HashMap<ChunkId, i32> = postgres
+        .get_chunks(&ids)
+        .await?
+        .into_iter()
+        .map(|chunk| (chunk.id, chunk.chunk_index))
+        .collect();
+
+    let passages = plan_windows(hits, &positions, window as i32);
+    let ranges: Vec<(DocumentId, i32, i32)> = passages
+        .iter()
+        .filter_map(|(hit, range)| {
+            let (first, last) = (*range)?;
+            Some((hit.result.document_id, first, last))
+        })
+        .collect();
+    let chunks = postgres.get_chunk_ranges(&ranges).await?;
+    Ok(fill_windows(passages, chunks))
+}
+
+/// Group hits into passages: each hit's `[index - window, index + window]`
+/// range, merged with the ranges of better hits in the same document that it
+/// overlaps or touches. Hits without a known index keep their own text.
+fn plan_windows(
+    hits: Vec<RankedHit>,
+    positions: &HashMap<ChunkId, i32>,
+    window: i32,
+) -> Vec<(RankedHit, Option<(i32, i32)>)> {
+    let mut passages: Vec<(RankedHit, Option<(i32, i32)>)> = Vec::with_capacity(hits.len());
+    for hit in hits {
+        let Some(&index) = positions.get(&hit.result.chunk_id) else {
+            passages.push((hit, None));
+            continue;
+        };
+        let (mut first, mut last) = ((index - window).max(0), index + window);
+
+        // Fold every passage this range reaches into the best of them
+        let document_id = hit.result.document_id;
+        let mut reached = passages
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (other, range))| {
+                let (lo, hi) = (*range)?;
+                let touches =
+                    other.result.document_id == document_id && lo <= last + 1 && first <= hi + 1;
+                touches.then_some(i)
+            });
+        let Some(target) = reached.next() else {
+            passages.push((hit, Some((first, last))));
+            continue;
+        };
+        let absorbed: Vec<usize> = reached.collect();
+        for &i in absorbed.iter().rev() {
+            let (_, range) = passages.remove(i);
+            if let Some((lo, hi)) = range {
+                (first, last) = (first.min(lo), last.max(hi));
+            }
+        }
+        if let Some((lo, hi)) = &mut passages[target].1 {
+            (*lo, *hi) = (first.min(*lo), last.max(*hi));
+        }
+    }
+    passages
+}
+
+/// Join each passage's chunks into its hit's text.
+fn fill_windows(
+    passages: Vec<(RankedHit, Option<(i32, i32)>)>,
+    chunks: Vec<ChunkRecord>,
+) -> Vec<RankedHit> {
+    let mut by_document: HashMap<DocumentId, Vec<ChunkRecord>> = HashMap::new();
+    for chunk in chunks {
+        by_document
+            .entry(chunk.document_id)
+            .or_default()
+            .push(chunk);
+    }
+
+    passages
+        .into_iter()
+        .map(|(mut hit, range)| {
+            let Some((first, last)) = range else {
+                return hit;
+            };
+            let Some(chunks) = by_document.get(&hit.result.document_id) else {
+                return hit;
+            };
+            let mut in_window: Vec<&ChunkRecord> = chunks
+                .iter()
+                .filter(|c| (first..=last).contains(&c.chunk_index))
+                .collect();
+            in_window.sort_by_key(|c| c.chunk_index);
+            if in_window.is_empty() {
+                return hit;
+            }
+
+            hit.result.text = join_chunks(&in_window);
+            hit.window = Some(in_window.iter().map(|c| c.id).collect());
+            hit
+        })
+        .collect()
+}
+
+/// Concatenate consecutive chunks, dropping the text a chunk repeats from
+/// the one before when their offsets in the document show an overlap.
+fn join_chunks(chunks: &[&ChunkRecord]) -> String {
+    let mut text = String::new();
+    let mut previous: Option<&ChunkRecord> = None;
+    for chunk in chunks {
+        let offsets =
+            previous.and_then(|p| Some((p.end_offset?, chunk.start_offset?, chunk.end_offset?)));
+        match offsets {
+            // Entirely inside the previous chunk
+            Some((end, _, next_end)) if next_end <= end => continue,
+            Some((end, start, _)) if start < end => {
+                let overlap = (end - start) as usize;
+                match chunk.text.get(overlap..) {
+                    Some(rest) => text.push_str(rest),
+                    None => {
+                        text.push_str("\n\n");
+                        text.push_str(&chunk.text);
+                    }
+                }
+            }
+            Some((end, start, _)) => {
+                text.push_str(if start == end { "" } else { " " });
+                text.push_str(&chunk.text);
+            }
+            None if previous.is_some() => {
+                text.push_str("\n\n");
+                text.push_str(&chunk.text);
+            }
+            None => text.push_str(&chunk.text),
+        }
+        previous = Some(chunk);
+    }
+    text
+}
+
+/// How a document's chunk scores add up to its score.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupScore {
+    /// Its best chunk's score.
+    #[default]
+    Max,
+    /// The total of its chunks' scores, favoring documents that match in
+    /// many places. Best with retrieval scores, as rerank scores can be
+    /// negative.
+    Sum,
+}
+
+/// A document's hits, best first.
+#[derive(Debug)]
+pub struct DocumentGroup {
+    pub score: f32,
+    pub hits: Vec<RankedHit>,
+}
+
+/// Group hits by document, best documents first. Documents with equal
+/// scores keep the order of their best hits.
+pub fn group_by_document(hits: Vec<RankedHit>, method: GroupScore) -> Vec<DocumentGroup> {
+    let mut groups: Vec<DocumentGroup> = Vec::new();
+    let mut positions: HashMap<DocumentId, usize> = HashMap::new();
+    for hit in hits {
+        let i = *positions.entry(hit.result.document_id).or_insert_with(|| {
+            groups.push(DocumentGroup {
+                score: 0.0,
+                hits: Vec::new(),
+            });
+            groups.len() - 1
+        });
+        groups[i].hits.push(hit);
+    }
+
+    for group in &mut groups {
+        group.hits.sort_by(|a, b| b.score().total_cmp(&a.score()));
+        let scores = group.hits.iter().map(RankedHit::score);
+        group.score = match method {
+            GroupScore::Max => scores.fold(f32::NEG_INFINITY, f32::max),
+            GroupScore::Sum => scores.sum(),
+        };
+    }
+    groups.sort_by(|a, b| b.score.total_cmp(&a.score));
+    groups
+}
+
+/// Rerank hits with the cross-encoder and keep the best `top_k`. `model`
+/// overrides the ML service's default reranker.
+pub async fn rerank(
+    ml_client: &MlClient,
+    query: &str,
+    results: Vec<RankedHit>,
+    top_k: usize,
+    model: Option<&str>,
+) -> Result<Vec<RankedHit>, MlClientError> {
+    if results.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let documents = results
+        .iter()
+        .map(|hit| {
+            let r = &hit.result;
+            (r.chunk_id.0.to_string(), r.text.clone(), r.score)
+        })
+        .collect();
+    let reranked = ml_client
+        .rerank(query, documents, top_k as i32, model)
+        .await?;
+    Ok(apply_rerank(results, reranked))
+}
+
+/// Order hits as the reranker returned them, dropping any it left out.
+fn apply_rerank(results: Vec<RankedHit>, reranked: Vec<(String, String, f32)>) -> Vec<RankedHit> {
+    let mut by_id: HashMap<String, RankedHit> = results
+        .into_iter()
+        .map(|hit| (hit.result.chunk_id.0.to_string(), hit))
+        .collect();
+
+    reranked
+        .into_iter()
+        .filter_map(|(id, _, score)| {
+            let mut hit = by_id.remove(&id)?;
+            hit.rerank_score = Some(score);
+            Some(hit)
+        })
+        .collect()
+}
+
+fn merge_parents(results: Vec<RankedHit>, parents: Vec<ParentChunk>) -> Vec<RankedHit> {
+    let parents: HashMap<_, _> = parents.into_iter().map(|p| (p.id, p)).collect();
+    let mut seen = HashSet::new();
+
+    results
+        .into_iter()
+        .filter_map(|mut hit| {
+            let result = &mut hit.result;
+            // Hits without a parent (or whose parent is gone) pass through as-is.
+            let Some(parent) = result.parent_id.and_then(|id| parents.get(&id)) else {
+                return Some(hit);
+            };
+
+            if !seen.insert(parent.id) {
+                return None;
+            }
+
+            result.text = parent.text.clone();
+            result.section_title = parent.section_title.clone();
+            Some(hit)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(score: f32, parent_id: Option<ChunkId>) -> RankedHit {
+        RankedHit::from(SearchResult {
+            chunk_id: ChunkId::new(),
+            document_id: DocumentId::new(),
+            text: "child".to_string(),
+            score,
+            document_title: "Doc".to_string(),
+            source_type: SourceType::PdfUpload,
+            source_url: None,
+            section_title: None,
+            kind: ChunkKind::Text,
+            parent_id,
+        })
+    }
+
+    #[test]
+    fn test_children_collapse_into_parent() {
+        let parent = ParentChunk {
+            id: ChunkId::new(),
+            document_id: DocumentId::new(),
+            user_id: UserId::new(),
+            chunk_index: 0,
+            text: "the whole parent section".to_string(),
+            section_title: Some("Intro".to_string()),
+        };
+
+        let results = vec![
+            hit(0.9, Some(parent.id)),
+            hit(0.8, None),
+            hit(0.7, Some(parent.id)),
+        ];
+        let best_child = results[0].result.chunk_id;
+
+        let merged = merge_parents(results, vec![parent]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].result.chunk_id, best_child);
+        assert_eq!(merged[0].result.text, "the whole parent section");
+        assert_eq!(merged[0].result.section_title.as_deref(), Some("Intro"));
+        assert_eq!(merged[1].result.text, "child");
+    }
+
+    #[test]
+    fn test_mmr_skips_near_duplicates_and_caps_documents() {
+        let mut hits = vec![
+            hit(0.9, None),
+            hit(0.85, None),
+            hit(0.5, None),
+            hit(0.4, None),
+        ];
+        let shared_document = hits[0].result.document_id;
+        hits[3].result.document_id = shared_document;
+        let vectors: HashMap<ChunkId, Vec<f32>> = hits
+            .iter()
+            .zip([[1.0, 0.0], [0.99, 0.1], [0.0, 1.0], [0.7, 0.7]])
+            .map(|(hit, v)| (hit.result.chunk_id, v.to_vec()))
+            .collect();
+        let ids: Vec<ChunkId> = hits.iter().map(|hit| hit.result.chunk_id).collect();
+
+        let relevance_only = select_diverse(hits.clone(), &vectors, 2, 1.0, None);
+        let picked: Vec<ChunkId> = relevance_only.iter().map(|h| h.result.chunk_id).collect();
+        assert_eq!(picked, [ids[0], ids[1]]);
+
+        // The second hit nearly repeats the first, so a diverse pick skips it
+        let diverse = select_diverse(hits.clone(), &vectors, 2, 0.5, None);
+        let picked: Vec<ChunkId> = diverse.iter().map(|h| h.result.chunk_id).collect();
+        assert_eq!(picked, [ids[0], ids[2]]);
+
+        let capped = select_diverse(hits, &vectors, 4, 1.0, Some(1));
+        let picked: Vec<ChunkId> = capped.iter().map(|h| h.result.chunk_id).collect();
+        assert_eq!(picked, [ids[0], ids[1], ids[2]]);
+    }
+
+    fn record(document_id: DocumentId, chunk_index: i32, text: &str, start: i32) -> ChunkRecord {
+        ChunkRecord {
+            id: ChunkId::new(),
+            document_id,
+            parent_id: None,
+            chunk_index,
+            text: text.to_string(),
+            section_title: None,
+            kind: ChunkKind::Text,
+            context_header: None,
+            start_offset: Some(start),
+            end_offset: Some(start + text.len() as i32),
+        }
+    }
+
+    #[test]
+    fn test_overlapping_windows_merge_into_one_passage() {
+        let document_id = DocumentId::new();
+        // "Refunds take 14 days. Contact support. Shipping is free." with
+        // chunks overlapping by a few bytes
+        let chunks = vec![
+            record(document_id, 0, "Refunds take 14 days.", 0),
+            record(document_id, 1, "days. Contact support.", 16),
+            record(document_id, 2, "Shipping is free.", 39),
+            record(document_id, 3, "Returns need a receipt.", 57),
+        ];
+        let mut hits = vec![hit(0.9, None), hit(0.8, None), hit(0.7, None)];
+        for (hit, chunk) in hits.iter_mut().zip([&chunks[1], &chunks[3], &chunks[0]]) {
+            hit.result.chunk_id = chunk.id;
+            hit.result.document_id = document_id;
+        }
+        let positions = chunks.iter().map(|c| (c.id, c.chunk_index)).collect();
+
+        let passages = plan_windows(hits, &positions, 1);
+        assert_eq!(passages.len(), 1);
+        assert_eq!(passages[0].1, Some((0, 4)));
+        assert_eq!(passages[0].0.result.score, 0.9);
+
+        let filled = fill_windows(passages, chunks.clone());
+        assert_eq!(
+            filled[0].result.text,
+            "Refunds take 14 days. Contact support. Shipping is free. Returns need a receipt."
+        );
+        assert_eq!(filled[0].window.as_ref().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_group_by_document_scores_documents() {
+        let mut hits = vec![
+            hit(0.9, None),
+            hit(0.6, None),
+            hit(0.5, None),
+            hit(0.4, None),
+        ];
+        let (first, second) = (hits[0].result.document_id, hits[1].result.document_id);
+        hits[2].result.document_id = second;
+        hits[3].result.document_id = second;
+
+        let by_max = group_by_document(hits.clone(), GroupScore::Max);
+        let order: Vec<DocumentId> = by_max
+            .iter()
+            .map(|g| g.hits[0].result.document_id)
+            .collect();
+        assert_eq!(order, [first, second]);
+        assert_eq!(by_max[1].score, 0.6);
+        assert_eq!(by_max[1].hits.len(), 3);
+
+        let by_sum = group_by_document(hits, GroupScore::Sum);
+        let order: Vec<DocumentId> = by_sum
+            .iter()
+            .map(|g| g.hits[0].result.document_id)
+            .collect();
+        assert_eq!(order, [second, first]);
+        assert!((by_sum[0].score - 1.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_grouped_pages_do_not_overlap() {
+        // Each query's top hits tie once fused
+        let queries = vec![
+            vec![hit(0.9, None), hit(0.8, None), hit(0.7, None)],
+            vec![hit(0.9, None), hit(0.8, None), hit(0.7, None)],
+        ];
+
+        // Every page is cut from its own fusion of the same pool, as a
+        // request per page would be
+        let page = |offset: usize| -> Vec<DocumentId> {
+            let hits = fuse_queries(queries.clone(), 10);
+            group_by_document(hits, GroupScore::Max)
+                .into_iter()
+                .skip(offset)
+                .take(3)
+                .map(|g| g.hits[0].result.document_id)
+                .collect()
+        };
+
+        let (first, second) = (page(0), page(3));
+        assert_eq!(first.len(), 3);
+        assert_eq!(second.len(), 3);
+        assert!(first.iter().all(|id| !second.contains(id)));
+    }
+
+    #[test]
+    fn test_queries_fuse_by_rank() {
+        let (a, b, c) = (hit(0.9, None), hit(0.5, None), hit(0.1, None));
+        let ids = [a.result.chunk_id, b.result.chunk_id, c.result.chunk_id];
+
+        // One query's hits keep their scores
+        let single = fuse_queries(vec![vec![a.clone(), b.clone()]], 10);
+        assert_eq!(single[0].result.score, 0.9);
+
+        // b ranks well for both queries, so it beats a, which only one found
+        let fused = fuse_queries(vec![vec![a, b.clone()], vec![b, c]], 2);
+        let order: Vec<ChunkId> = fused.iter().map(|hit| hit.result.chunk_id).collect();
+        assert_eq!(order, [ids[1], ids[0]]);
+        assert!((fused[0].result.score - (1.0 / 62.0 + 1.0 / 61.0)).abs() < 1e-6);
+        assert!(fused.iter().all(|hit| hit.contributions.is_none()));
+    }
+
+    #[test]
+    fn test_rerank_keeps_retrieval_score() {
+        let results = vec![hit(0.9, None), hit(0.4, None)];
+        let (first, second) = (results[0].result.chunk_id, results[1].result.chunk_id);
+
+        let reranked = vec![
+            (second.0.to_string(), "child".to_string(), 7.5),
+            (first.0.to_string(), "child".to_string(), -2.0),
+        ];
+        let hits = apply_rerank(results, reranked);
+        assert_eq!(hits[0].result.chunk_id, second);
+        assert_eq!(hits[0].result.score, 0.4);
+        assert_eq!(hits[0].score(), 7.5);
+        assert_eq!(hits[1].rerank_score, Some(-2.0));
+    }
+}
@@ -0,0 +1,189 @@
+//! Query transformations before retrieval, for short or vague queries:
+//! rephrasings searched alongside the query, a hypothetical answer embedded
+//! in its place (HyDE), and key terms for keyword search. Each is written by
+//! the LLM and off unless the request asks for it.
+
+use cortex_common::config::QueryRewriteConfig;
+use cortex_ml_client::MlClient;
+use serde::{Deserialize, Serialize};
+
+/// Most rephrasings one request may ask for.
+pub const MAX_EXPANSIONS: usize = 5;
+
+const EXPANSION_SYSTEM_PROMPT: &str = "You rewrite queries for a document search engine. \
+     Reply with alternative phrasings of the user's query that use different words for the \
+     same information need, one per line. Do not number them or add any preamble.";
+
+const HYDE_SYSTEM_PROMPT: &str = "You write passages for a document search engine. \
+     Reply with a short passage, as it might appear in a document, that answers the user's \
+     query. Do not add any preamble.";
+
+const KEYWORDS_SYSTEM_PROMPT: &str = "You extract keywords for a keyword search engine. \
+     Reply with the most important words of the user's query, plus close synonyms, \
+     separated by spaces on one line. Do not add any preamble.";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RewriteOptions {
+    /// Also search this many rephrasings of the query, fusing the results.
+    #[serde(default)]
+    pub expansions: usize,
+    /// Embed a hypothetical answer instead of the query.
+    #[serde(default)]
+    pub hyde: bool,
+    /// Give keyword search the query's key terms instead of the query.
+    #[serde(default)]
+    pub keywords: bool,
+}
+
+/// What one retrieval searched with.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchQuery {
+    /// Text for keyword search.
+    pub keyword: String,
+    /// Text that was embedded for vector search.
+    pub embedded: String,
+}
+
+impl RewriteOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.expansions > MAX_EXPANSIONS {
+            return Err(format!("expansions can be at most {MAX_EXPANSIONS}"));
+        }
+        Ok(())
+    }
+}
+
+/// The queries to search with: the user's query, transformed as `options`
+/// asks, then any rephrasings. Without options it is the query alone. A
+/// transformation the LLM fails to write is left out, so the search falls
+/// back to the query instead of failing.
+pub async fn rewrite(
+    ml_client: &MlClient,
+    config: &QueryRewriteConfig,
+    query: &str,
+    options: Option<&RewriteOptions>,
+) -> Vec<SearchQuery> {
+    let options = options.cloned().unwrap_or_default();
+    let optional = |enabled: bool, system_prompt: &'static str, prompt: String| async move {
+        if !enabled {
+            return None;
+        }
+        ml_client
+            .generate(&prompt, system_prompt, &config.provider, &config.model)
+            .await
+            .inspect_err(|e| tracing::warn!(error = %e, "Query rewrite failed, using the query"))
+            .ok()
+    };
+
+    let (expansions, hyde, keywords) = futures::join!(
+        optional(
+            options.expansions > 0,
+            EXPANSION_SYSTEM_PROMPT,
+            format!("Write {} rephrasings of: {query}", options.expansions),
+        ),
+        optional(options.hyde, HYDE_SYSTEM_PROMPT, query.to_string()),
+        optional(options.keywords, KEYWORDS_SYSTEM_PROMPT, query.to_string()),
+    );
+    search_queries(query, options.expansions, expansions, hyde, keywords)
+}
+
+/// The query with whatever the LLM wrote for it: key terms replace it for
+/// keyword search, a hypothetical answer replaces it for vector search, and
+/// each rephrasing is searched on its own.
+fn search_queries(
+    query: &str,
+    max_expansions: usize,
+    expansions: Option<String>,
+    hyde: Option<String>,
+    keywords: Option<String>,
+) -> Vec<SearchQuery> {
+    let mut queries = vec![SearchQuery {
+        keyword: non_empty(keywords, query),
+        embedded: non_empty(hyde, query),
+    }];
+    let expansions = expansions.as_deref().unwrap_or_default();
+    for expansion in parse_expansions(expansions, query, max_expansions) {
+        queries.push(SearchQuery {
+            keyword: expansion.clone(),
+            embedded: expansion,
+        });
+    }
+    queries
+}
+
+/// The generated text, or `fallback` if there is none.
+fn non_empty(generated: Option<String>, fallback: &str) -> String {
+    generated
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+        .unwrap_or_else(|| fallback.to_string())
+}
+
+/// Up to `limit` distinct rephrasings from the LLM's reply, one per line,
+/// without list markers or quotes. Leaves out the query itself and lines
+/// that introduce a list.
+fn parse_expansions(reply: &str, query: &str, limit: usize) -> Vec<String> {
+    let mut expansions: Vec<String> = Vec::new();
+    for line in reply.lines() {
+        let expansion = strip_list_marker(line).trim_matches('"').trim();
+        let seen = expansion.eq_ignore_ascii_case(query)
+            || expansions.iter().any(|e| e.eq_ignore_ascii_case(expansion));
+        if !expansion.is_empty() && !expansion.ends_with(':') && !seen {
+            expansions.push(expansion.to_string());
+        }
+    }
+    expansions.truncate(limit);
+    expansions
+}
+
+/// `line` without a leading `-`, `*`, `1.` or `1)`.
+fn strip_list_marker(line: &str) -> &str {
+    let line = line.trim();
+    if let Some(rest) = line.strip_prefix(['-', '*']) {
+        return rest.trim_start();
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    match line[digits..].strip_prefix(['.', ')']) {
+        Some(rest) if digits > 0 => rest.trim_start(),
+        _ => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expansions_are_cleaned_and_deduplicated() {
+        let reply = "Here you go:\n1. \"vacation policy\"\n2) PTO rules\n- pto rules\n\n* time off";
+        let expansions = parse_expansions(reply, "Vacation policy", 2);
+        assert_eq!(expansions, ["PTO rules", "time off"]);
+
+        let expansions = parse_expansions("2024 budget\n3. 2024 spending plan", "budget", 5);
+        assert_eq!(expansions, ["2024 budget", "2024 spending plan"]);
+    }
+
+    #[test]
+    fn test_hyde_and_keywords_replace_one_side_each() {
+        let queries = search_queries(
+            "pto rules",
+            2,
+            Some("vacation policy\ntime off".to_string()),
+            Some("Employees get 25 days of paid leave.".to_string()),
+            Some("pto vacation leave".to_string()),
+        );
+        assert_eq!(queries.len(), 3);
+        assert_eq!(queries[0].keyword, "pto vacation leave");
+        assert_eq!(queries[0].embedded, "Employees get 25 days of paid leave.");
+        assert_eq!(queries[1].keyword, "vacation policy");
+        assert_eq!(queries[1].embedded, "vacation policy");
+
+        // Nothing written, as when the LLM call failed, falls back to the query
+        let queries = search_queries("pto rules", 2, None, Some("  ".to_string()), None);
+        let expected = SearchQuery {
+            keyword: "pto rules".to_string(),
+            embedded: "pto rules".to_string(),
+        };
+        assert_eq!(queries, [expected]);
+    }
+}
@@ -0,0 +1,132 @@
+use axum::extract::State;
+use axum::routing::post;
+use axum::{Json, Router};
+use cortex_common::chunking::ProfileSelector;
+use cortex_common::types::*;
+use cortex_scheduler::jobs::JobPayload;
+use cortex_scheduler::reindex::ReindexOptions;
+use cortex_store::models::CreateJob;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/admin/reconcile", post(reconcile))
+        .route("/admin/reindex", post(reindex))
+}
+
+#[derive(Debug, Deserialize)]
+struct ReconcileRequest {
+    /// Limit the check to one user; all users are checked when omitted.
+    user_id: Option<Uuid>,
+    /// Delete orphaned chunks and re-index drifted documents.
+    #[serde(default)]
+    repair: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct JobQueuedResponse {
+    job_id: Uuid,
+    status: String,
+}
+
+/// Queue a Postgres/vector store reconciliation. The report is attached to
+/// the job and returned by `GET /ingest/jobs/{job_id}`.
+async fn reconcile(
+    State(state): State<AppState>,
+    Json(req): Json<ReconcileRequest>,
+) -> Result<Json<JobQueuedResponse>, ApiError> {
+    let user_id = req.user_id.map(UserId);
+
+    let job_id = state
+        .postgres
+        .create_job(&CreateJob {
+            // Jobs spanning every user are recorded under the nil user
+            user_id: user_id.unwrap_or(UserId(Uuid::nil())),
+            connector_id: None,
+            job_type: JobType::Reconcile,
+        })
+        .await?;
+
+    state
+        .worker_pool
+        .submit(JobPayload::Reconcile {
+            job_id,
+            user_id,
+            repair: req.repair,
+        })
+        .await
+        .map_err(|e| ApiError::Internal(format!("failed to submit job: {e}")))?;
+
+    Ok(Json(JobQueuedResponse {
+        job_id: job_id.0,
+        status: "queued".to_string(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct ReindexRequest {
+    user_id: Uuid,
+    /// Re-embed with this model. Changing the model rebuilds every document
+    /// into a new index, so it can't be combined with the filters below.
+    embedding_model: Option<String>,
+    /// Re-chunk with this profile name or inline profile.
+    chunking_profile: Option<ProfileSelector>,
+    source_type: Option<SourceType>,
+    document_ids: Option<Vec<Uuid>>,
+}
+
+/// Queue a job that re-chunks and re-embeds a user's documents.
+async fn reindex(
+    State(state): State<AppState>,
+    Json(req): Json<ReindexRequest>,
+) -> Result<Json<JobQueuedResponse>, ApiError> {
+    let chunking_profile = req
+        .chunking_profile
+        .as_ref()
+        .map(|selector| state.config.chunking.resolve(selector))
+        .transpose()
+        .map_err(ApiError::BadRequest)?;
+
+    let filtered = req.source_type.is_some() || req.document_ids.is_some();
+    if req.embedding_model.is_some() && filtered {
+        return Err(ApiError::BadRequest(
+            "embedding_model cannot be combined with source_type or document_ids".to_string(),
+        ));
+    }
+
+    let user_id = UserId(req.user_id);
+    let job_id = state
+        .postgres
+        .create_job(&CreateJob {
+            user_id,
+            connector_id: None,
+            job_type: JobType::Reindex,
+        })
+        .await?;
+
+    state
+        .worker_pool
+        .submit(JobPayload::Reindex {
+            job_id,
+            user_id,
+            options: ReindexOptions {
+                embedding_model: req.embedding_model,
+                chunking_profile,
+                source_type: req.source_type,
+                document_ids: req
+                    .document_ids
+                    .map(|ids| ids.into_iter().map(DocumentId).collect()),
+            },
+        })
+        .await
+        .map_err(|e| ApiError::Internal(format!("failed to submit job: {e}")))?;
+
+    Ok(Json(JobQueuedResponse {
+        job_id: job_id.0,
+        status: "queued".to_string(),
+    }))
+}
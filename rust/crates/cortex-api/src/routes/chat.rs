@@ -3,12 +3,18 @@ use axum::response::sse::{Event, Sse};
 use axum::routing::post;
 use axum::{Json, Router};
 use cortex_common::types::*;
+use cortex_store::filter::FilterExpr;
+use cortex_store::hybrid::FusionMethod;
+use cortex_store::models::SearchFilters;
 use futures::stream::Stream;
 use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
 use uuid::Uuid;
 
 use crate::error::ApiError;
+use crate::highlight::{Highlight, QueryTerms};
+use crate::retrieval::{self, SearchParams};
+use crate::rewrite::{self, RewriteOptions};
 use crate::state::AppState;
 
 pub fn routes() -> Router<AppState> {
@@ -25,6 +31,32 @@ struct ChatRequest {
     #[serde(default = "default_top_k")]
     top_k: usize,
     source_filter: Option<String>,
+    /// Restrict results to one kind of chunk, e.g. only tables.
+    chunk_kind: Option<ChunkKind>,
+    /// Dates, documents, titles and metadata, combined with and/or/not.
+    filter: Option<FilterExpr>,
+    /// Fuse keyword and vector results in cortex (`rrf` or `relative_score`)
+    /// instead of using the vector store's hybrid search.
+    fusion: Option<FusionMethod>,
+    /// Replace matched child chunks with their parent sections before reranking.
+    #[serde(default = "default_expand_parents")]
+    expand_parents: bool,
+    /// Expand each context chunk to this many neighboring chunks on either
+    /// side, merging overlapping passages. Takes the place of `expand_parents`.
+    #[serde(default)]
+    context_window: usize,
+    /// Maximal Marginal Relevance lambda for picking context chunks, so
+    /// the context isn't filled with near-identical chunks; `null` picks by
+    /// relevance alone.
+    #[serde(default = "default_mmr_lambda")]
+    mmr_lambda: Option<f32>,
+    /// At most this many context chunks from any one document.
+    max_chunks_per_document: Option<usize>,
+    /// Rephrase, expand or extract keywords from the query before retrieval.
+    rewrite: Option<RewriteOptions>,
+    /// Send what retrieval ran with as a `debug` event before the citations.
+    #[serde(default)]
+    debug: bool,
     /// Temporary: pass user_id until auth is implemented.
     user_id: Uuid,
 }
@@ -41,6 +73,14 @@ fn default_top_k() -> usize {
     8
 }
 
+fn default_expand_parents() -> bool {
+    true
+}
+
+fn default_mmr_lambda() -> Option<f32> {
+    Some(0.7)
+}
+
 async fn chat(
     State(state): State<AppState>,
     Json(req): Json<ChatRequest>,
@@ -48,57 +88,103 @@ async fn chat(
     if req.query.trim().is_empty() {
         return Err(ApiError::BadRequest("query cannot be empty".to_string()));
     }
+    if let Some(filter) = &req.filter {
+        filter
+            .validate()
+            .map_err(|e| ApiError::BadRequest(format!("invalid filter: {e}")))?;
+    }
+    retrieval::check_diversity(req.mmr_lambda, req.max_chunks_per_document)
+        .map_err(ApiError::BadRequest)?;
+    retrieval::check_limits(req.top_k, None).map_err(ApiError::BadRequest)?;
+    retrieval::check_context_window(req.context_window).map_err(ApiError::BadRequest)?;
+    if let Some(rewrite) = &req.rewrite {
+        rewrite
+            .validate()
+            .map_err(|e| ApiError::BadRequest(format!("invalid rewrite: {e}")))?;
+    }
 
     let user_id = UserId(req.user_id);
 
-    // 1. Embed the query
-    let embeddings = state
+    // Queries are embedded with the model the user's index was built with
+    let index = state.postgres.get_search_index(user_id).await?;
+
+    // 1. Rewrite the query if asked, then embed each query to search with
+    let queries = rewrite::rewrite(
+        &state.ml_client,
+        &state.config.query_rewrite,
+        &req.query,
+        req.rewrite.as_ref(),
+    )
+    .await;
+    let texts = queries.iter().map(|q| q.embedded.clone()).collect();
+    let vectors = state
         .ml_client
-        .embed_batch(vec![req.query.clone()], None)
+        .embed_batch(texts, index.embedding_model.as_deref())
         .await
         .map_err(|e| ApiError::ServiceUnavailable(format!("ML service: {e}")))?;
-
-    let query_vector = embeddings
-        .into_iter()
-        .next()
-        .ok_or_else(|| ApiError::Internal("no embedding returned".to_string()))?;
+    if vectors.len() != queries.len() {
+        return Err(ApiError::Internal("no embedding returned".to_string()));
+    }
 
     // 2. Search for relevant chunks
-    let results = state
-        .weaviate
-        .hybrid_search(
-            &req.query,
-            &query_vector,
+    let filters = SearchFilters {
+        source_type: req.source_filter.clone(),
+        chunk_kind: req.chunk_kind,
+        filter: req.filter.clone(),
+    };
+    let store = state.vector_store.with_index(&index.class_name);
+    let limit = req.top_k * 3; // Over-fetch for reranking
+    let params: Vec<SearchParams> = queries
+        .iter()
+        .zip(&vectors)
+        .map(|(query, vector)| SearchParams {
+            query: &query.keyword,
+            vector,
             user_id,
-            req.source_filter.as_deref(),
-            req.top_k * 3, // Over-fetch for reranking
-            0.7,
-        )
+            filters: &filters,
+            limit,
+            alpha: 0.7,
+            fusion: req.fusion,
+        })
+        .collect();
+    let results = retrieval::search_many(store.as_ref(), &params)
         .await
         .map_err(|e| ApiError::Internal(format!("search failed: {e}")))?;
 
-    // 3. Rerank
-    let docs_for_rerank: Vec<(String, String, f32)> = results
-        .iter()
-        .map(|r| (r.chunk_id.0.to_string(), r.text.clone(), r.score))
-        .collect();
+    let results = if req.expand_parents && req.context_window == 0 {
+        retrieval::expand_to_parents(&state.postgres, results).await?
+    } else {
+        results
+    };
 
-    let reranked = state
-        .ml_client
-        .rerank(&req.query, docs_for_rerank, req.top_k as i32)
+    // 3. Rerank every candidate, then pick a diverse top-k
+    let reranked = retrieval::rerank(&state.ml_client, &req.query, results, limit, None)
         .await
         .map_err(|e| ApiError::ServiceUnavailable(format!("ML service rerank: {e}")))?;
+    let reranked = retrieval::diversify(
+        store.as_ref(),
+        reranked,
+        req.top_k,
+        req.mmr_lambda,
+        req.max_chunks_per_document,
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("search failed: {e}")))?;
+    let reranked =
+        retrieval::expand_to_windows(&state.postgres, reranked, req.context_window).await?;
 
     // 4. Build context from top-k reranked chunks
     let context = reranked
         .iter()
         .enumerate()
-        .map(|(i, (id, text, _score))| {
-            let source = results
-                .iter()
-                .find(|r| r.chunk_id.0.to_string() == *id);
-            let title = source.map(|s| s.document_title.as_str()).unwrap_or("Unknown");
-            format!("[Source {}] ({})\n{}", i + 1, title, text)
+        .map(|(i, hit)| {
+            let source = &hit.result;
+            format!(
+                "[Source {}] ({})\n{}",
+                i + 1,
+                source.document_title,
+                source.text
+            )
         })
         .collect::<Vec<_>>()
         .join("\n\n---\n\n");
@@ -115,25 +201,33 @@ async fn chat(
     let provider = req.provider.clone();
     let model = req.model.clone();
 
-    // Build citation data
+    // Build citation data, quoting the part of each source that best matches
+    let terms = QueryTerms::new(&req.query);
     let citations: Vec<CitationData> = reranked
         .iter()
         .enumerate()
-        .map(|(i, (id, text, _))| {
-            let source = results.iter().find(|r| r.chunk_id.0.to_string() == *id);
+        .map(|(i, hit)| {
+            let snippet = terms.snippet(&hit.result.text);
             CitationData {
                 index: i + 1,
-                chunk_id: id.clone(),
-                document_title: source
-                    .map(|s| s.document_title.clone())
-                    .unwrap_or_default(),
-                source_url: source.and_then(|s| s.source_url.clone()),
-                snippet: text.chars().take(200).collect(),
+                chunk_id: hit.result.chunk_id.0.to_string(),
+                document_title: hit.result.document_title.clone(),
+                source_url: hit.result.source_url.clone(),
+                snippet: snippet.text,
+                highlights: snippet.highlights,
             }
         })
         .collect();
 
+    let debug = req
+        .debug
+        .then(|| serde_json::json!({ "queries": queries }).to_string());
+
     let stream = async_stream::stream! {
+        if let Some(debug) = debug {
+            yield Ok(Event::default().event("debug").data(debug));
+        }
+
         // Send citations first
         let citations_event = serde_json::to_string(&citations).unwrap_or_default();
         yield Ok(Event::default().event("citations").data(citations_event));
@@ -166,4 +260,6 @@ struct CitationData {
     document_title: String,
     source_url: Option<String>,
     snippet: String,
+    /// Query term matches within `snippet`, in characters.
+    highlights: Vec<Highlight>,
 }
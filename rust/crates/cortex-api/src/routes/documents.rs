@@ -0,0 +1,284 @@
+use axum::extract::{Path, Query, State};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use cortex_common::types::*;
+use cortex_store::models::{ChunkRecord, Document, DocumentFilter, DocumentSort};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/documents", get(list_documents))
+        .route("/documents/delete", post(delete_matching))
+        .route(
+            "/documents/:document_id",
+            get(get_document).delete(delete_document),
+        )
+        .route("/documents/:document_id/content", get(get_content))
+}
+
+#[derive(Debug, Deserialize)]
+struct OwnerQuery {
+    /// Temporary: pass user_id in request until auth is implemented.
+    user_id: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListQuery {
+    /// Temporary: pass user_id in request until auth is implemented.
+    user_id: Uuid,
+    source_type: Option<SourceType>,
+    /// Case-insensitive substring of the title.
+    title: Option<String>,
+    #[serde(default)]
+    sort: DocumentSort,
+    limit: Option<i64>,
+    #[serde(default)]
+    offset: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct DocumentResponse {
+    document_id: Uuid,
+    source_type: SourceType,
+    source_id: String,
+    title: String,
+    source_url: Option<String>,
+    mime_type: Option<String>,
+    content_hash: String,
+    chunk_count: i32,
+    chunking_profile: Option<String>,
+    status: DocumentStatus,
+    error: Option<String>,
+    metadata: serde_json::Value,
+    indexed_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<Document> for DocumentResponse {
+    fn from(doc: Document) -> Self {
+        Self {
+            document_id: doc.id.0,
+            source_type: doc.source_type,
+            source_id: doc.source_id,
+            title: doc.title,
+            source_url: doc.source_url,
+            mime_type: doc.mime_type,
+            content_hash: doc.content_hash,
+            chunk_count: doc.chunk_count,
+            chunking_profile: doc.chunking_profile.map(|p| p.name),
+            status: doc.status,
+            error: doc.error_message,
+            metadata: doc.metadata,
+            indexed_at: doc.indexed_at,
+            updated_at: doc.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ListResponse {
+    documents: Vec<DocumentResponse>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+}
+
+async fn list_documents(
+    State(state): State<AppState>,
+    Query(query): Query<ListQuery>,
+) -> Result<Json<ListResponse>, ApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_PAGE_SIZE}"
+        )));
+    }
+    if query.offset < 0 {
+        return Err(ApiError::BadRequest(
+            "offset cannot be negative".to_string(),
+        ));
+    }
+
+    let user_id = UserId(query.user_id);
+    let filter = DocumentFilter {
+        source_type: query.source_type,
+        title: query.title.filter(|t| !t.trim().is_empty()),
+        document_ids: None,
+    };
+
+    let documents = state
+        .postgres
+        .list_documents(user_id, &filter, query.sort, limit, query.offset)
+        .await?;
+    let total = state.postgres.count_documents(user_id, &filter).await?;
+
+    Ok(Json(ListResponse {
+        documents: documents.into_iter().map(Into::into).collect(),
+        total,
+        limit,
+        offset: query.offset,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+struct DetailResponse {
+    #[serde(flatten)]
+    document: DocumentResponse,
+    /// Empty for documents indexed before chunk records were kept.
+    chunks: Vec<ChunkRecord>,
+}
+
+async fn get_document(
+    State(state): State<AppState>,
+    Path(document_id): Path<Uuid>,
+    Query(query): Query<OwnerQuery>,
+) -> Result<Json<DetailResponse>, ApiError> {
+    let doc = owned_document(&state, DocumentId(document_id), UserId(query.user_id)).await?;
+    let chunks = state.postgres.get_document_chunks(doc.id).await?;
+
+    Ok(Json(DetailResponse {
+        document: doc.into(),
+        chunks,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+struct DeleteResponse {
+    deleted: u64,
+}
+
+async fn delete_document(
+    State(state): State<AppState>,
+    Path(document_id): Path<Uuid>,
+    Query(query): Query<OwnerQuery>,
+) -> Result<Json<DeleteResponse>, ApiError> {
+    let user_id = UserId(query.user_id);
+    let doc = owned_document(&state, DocumentId(document_id), user_id).await?;
+    let deleted = delete_documents(&state, user_id, &[doc.id]).await?;
+    Ok(Json(DeleteResponse { deleted }))
+}
+
+#[derive(Debug, Deserialize)]
+struct DeleteMatchingRequest {
+    /// Temporary: pass user_id in request until auth is implemented.
+    user_id: Uuid,
+    source_type: Option<SourceType>,
+    title: Option<String>,
+    document_ids: Option<Vec<Uuid>>,
+}
+
+/// Delete every document of a user matching the filter. At least one filter
+/// is required so an empty body can't wipe a user's index.
+async fn delete_matching(
+    State(state): State<AppState>,
+    Json(req): Json<DeleteMatchingRequest>,
+) -> Result<Json<DeleteResponse>, ApiError> {
+    let user_id = UserId(req.user_id);
+    let filter = DocumentFilter {
+        source_type: req.source_type,
+        title: req.title.filter(|t| !t.trim().is_empty()),
+        document_ids: req
+            .document_ids
+            .map(|ids| ids.into_iter().map(DocumentId).collect()),
+    };
+    if filter.is_empty() {
+        return Err(ApiError::BadRequest(
+            "at least one of source_type, title or document_ids is required".to_string(),
+        ));
+    }
+
+    let ids = state.postgres.list_document_ids(user_id, &filter).await?;
+    let deleted = delete_documents(&state, user_id, &ids).await?;
+    Ok(Json(DeleteResponse { deleted }))
+}
+
+/// Delete documents from the vector store first, then Postgres. If the second
+/// step fails the documents are left with no chunks, which a reconcile flags;
+/// the other order would leave orphaned chunks that still show up in search.
+async fn delete_documents(
+    state: &AppState,
+    user_id: UserId,
+    ids: &[DocumentId],
+) -> Result<u64, ApiError> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    let index = state.postgres.get_search_index(user_id).await?;
+    state
+        .vector_store
+        .with_index(&index.class_name)
+        .delete_chunks_by_documents(ids)
+        .await
+        .map_err(|e| ApiError::Internal(format!("chunk delete failed: {e}")))?;
+
+    let mut content_hashes = state.postgres.delete_documents(user_id, ids).await?;
+    let deleted = content_hashes.len() as u64;
+    tracing::info!(%user_id, deleted, "Deleted documents");
+
+    // Drop retained text no other document shares
+    content_hashes.sort();
+    content_hashes.dedup();
+    for content_hash in &content_hashes {
+        if let Err(e) = state.content.release(&state.postgres, content_hash).await {
+            tracing::warn!(%content_hash, error = %e, "Failed to delete retained content");
+        }
+    }
+    Ok(deleted)
+}
+
+#[derive(Debug, Serialize)]
+struct ContentResponse {
+    document_id: Uuid,
+    title: String,
+    content_hash: String,
+    mime_type: Option<String>,
+    content: String,
+}
+
+/// The original text of the current version of a document. Not found when
+/// content retention is disabled or the document predates it.
+async fn get_content(
+    State(state): State<AppState>,
+    Path(document_id): Path<Uuid>,
+    Query(query): Query<OwnerQuery>,
+) -> Result<Json<ContentResponse>, ApiError> {
+    let doc = owned_document(&state, DocumentId(document_id), UserId(query.user_id)).await?;
+
+    let content = state
+        .content
+        .get(&doc.content_hash)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .ok_or(ApiError::NotFound)?;
+
+    Ok(Json(ContentResponse {
+        document_id: doc.id.0,
+        title: doc.title,
+        content_hash: doc.content_hash,
+        mime_type: doc.mime_type,
+        content,
+    }))
+}
+
+/// Another user's document is reported as not found rather than forbidden.
+async fn owned_document(
+    state: &AppState,
+    document_id: DocumentId,
+    user_id: UserId,
+) -> Result<Document, ApiError> {
+    state
+        .postgres
+        .get_document(document_id)
+        .await?
+        .filter(|doc| doc.user_id == user_id)
+        .ok_or(ApiError::NotFound)
+}
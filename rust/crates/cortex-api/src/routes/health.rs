@@ -10,20 +10,22 @@ pub fn routes() -> Router<AppState> {
 }
 
 async fn health_check(State(state): State<AppState>) -> Json<Value> {
-    let weaviate_ok = state.weaviate.health_check().await.unwrap_or(false);
+    let vector_store_ok = state.vector_store.health_check().await.unwrap_or(false);
 
     let pg_ok = sqlx::query_scalar::<_, i32>("SELECT 1")
         .fetch_one(state.postgres.pool())
         .await
         .is_ok();
 
-    let status = if weaviate_ok && pg_ok { "ok" } else { "degraded" };
+    let status = if vector_store_ok && pg_ok { "ok" } else { "degraded" };
 
     Json(json!({
         "status": status,
         "services": {
             "postgres": if pg_ok { "ok" } else { "error" },
-            "weaviate": if weaviate_ok { "ok" } else { "error" },
+            "vector_store": if vector_store_ok { "ok" } else { "error" },
+            // Reported under its old name too, for monitors that still read it
+            "weaviate": if vector_store_ok { "ok" } else { "error" },
         }
     }))
 }
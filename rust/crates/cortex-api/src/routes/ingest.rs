@@ -1,7 +1,9 @@
 use axum::extract::State;
 use axum::routing::{get, post};
 use axum::{Json, Router};
+use cortex_common::chunking::ProfileSelector;
 use cortex_common::types::*;
+use cortex_connectors::pdf_upload;
 use cortex_scheduler::jobs::JobPayload;
 use cortex_store::models::CreateJob;
 use serde::{Deserialize, Serialize};
@@ -20,6 +22,13 @@ pub fn routes() -> Router<AppState> {
 struct UploadRequest {
     filename: String,
     content: String,
+    /// Client-supplied stable id. Uploads with the same id are versions of one document.
+    external_id: Option<String>,
+    /// Groups uploads; without an external id, the document is identified by
+    /// collection and filename.
+    collection: Option<String>,
+    /// Chunking profile name or inline profile; defaults to the source-type profile.
+    chunking_profile: Option<ProfileSelector>,
     /// Temporary: pass user_id until auth is implemented.
     user_id: Uuid,
 }
@@ -27,6 +36,7 @@ struct UploadRequest {
 #[derive(Debug, Serialize)]
 struct UploadResponse {
     job_id: Uuid,
+    source_id: String,
     status: String,
 }
 
@@ -38,6 +48,29 @@ async fn upload(
         return Err(ApiError::BadRequest("content cannot be empty".to_string()));
     }
 
+    if req
+        .external_id
+        .as_deref()
+        .is_some_and(|id| id.trim().is_empty())
+    {
+        return Err(ApiError::BadRequest(
+            "external_id cannot be empty".to_string(),
+        ));
+    }
+
+    let source_id = pdf_upload::upload_source_id(
+        &req.filename,
+        req.collection.as_deref(),
+        req.external_id.as_deref(),
+    );
+
+    let chunking_profile = req
+        .chunking_profile
+        .as_ref()
+        .map(|selector| state.config.chunking.resolve(selector))
+        .transpose()
+        .map_err(ApiError::BadRequest)?;
+
     let user_id = UserId(req.user_id);
 
     // Create job record
@@ -56,14 +89,18 @@ async fn upload(
         .submit(JobPayload::FileUpload {
             job_id,
             user_id,
+            source_id: source_id.clone(),
             filename: req.filename,
+            collection: req.collection,
             content: req.content,
+            chunking_profile,
         })
         .await
         .map_err(|e| ApiError::Internal(format!("failed to submit job: {e}")))?;
 
     Ok(Json(UploadResponse {
         job_id: job_id.0,
+        source_id,
         status: "queued".to_string(),
     }))
 }
@@ -74,6 +111,8 @@ struct JobResponse {
     status: JobStatus,
     progress: JobProgress,
     error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize)]
@@ -100,5 +139,6 @@ async fn get_job(
             processed: job.processed_items,
         },
         error: job.error_message,
+        result: job.result,
     }))
 }
@@ -1,4 +1,6 @@
+pub mod admin;
 pub mod chat;
+pub mod documents;
 pub mod health;
 pub mod ingest;
 pub mod search;
@@ -28,4 +30,6 @@ fn api_routes() -> Router<AppState> {
         .merge(search::routes())
         .merge(ingest::routes())
         .merge(chat::routes())
+        .merge(documents::routes())
+        .merge(admin::routes())
 }
@@ -2,10 +2,18 @@ use axum::extract::State;
 use axum::routing::post;
 use axum::{Json, Router};
 use cortex_common::types::*;
+use cortex_store::facets::{FacetCounts, FacetRequest};
+use cortex_store::filter::FilterExpr;
+use cortex_store::hybrid::{Contributions, FusionMethod};
+use cortex_store::models::SearchFilters;
+use cortex_store::postgres::PostgresStore;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::error::ApiError;
+use crate::highlight::{Highlight, QueryTerms, Snippet};
+use crate::retrieval::{self, DocumentGroup, GroupScore, RankedHit, SearchParams};
+use crate::rewrite::{self, RewriteOptions, SearchQuery};
 use crate::state::AppState;
 
 pub fn routes() -> Router<AppState> {
@@ -18,8 +26,56 @@ struct SearchRequest {
     #[serde(default = "default_top_k")]
     top_k: usize,
     source_filter: Option<String>,
+    /// Restrict results to one kind of chunk, e.g. only tables.
+    chunk_kind: Option<ChunkKind>,
     #[serde(default = "default_alpha")]
     alpha: f32,
+    /// Fuse keyword and vector results in cortex (`rrf` or `relative_score`)
+    /// instead of using the vector store's hybrid search.
+    fusion: Option<FusionMethod>,
+    /// Dates, documents, titles and metadata, combined with and/or/not.
+    filter: Option<FilterExpr>,
+    /// Return the parent section of each matched child chunk instead of the chunk itself.
+    #[serde(default)]
+    expand_parents: bool,
+    /// Expand each result to this many neighboring chunks on either side,
+    /// merging overlapping passages. Takes the place of `expand_parents`.
+    #[serde(default)]
+    context_window: usize,
+    /// Rerank retrieved hits with the cross-encoder before returning `top_k`.
+    #[serde(default = "default_rerank")]
+    rerank: bool,
+    /// How many hits to retrieve and rerank; defaults to three times `top_k`.
+    rerank_top_n: Option<usize>,
+    /// Reranker model, instead of the ML service's default; must be one the
+    /// ML service allows.
+    rerank_model: Option<String>,
+    /// Re-select results for diversity with Maximal Marginal Relevance;
+    /// 1.0 ranks by relevance alone, lower values penalize similar chunks.
+    mmr_lambda: Option<f32>,
+    /// At most this many results from any one document.
+    max_chunks_per_document: Option<usize>,
+    /// Return documents instead of chunks, each with its best chunks.
+    group_by: Option<GroupBy>,
+    /// How a document's chunk scores add up when grouping by document.
+    #[serde(default)]
+    group_score: GroupScore,
+    /// Chunks returned with each document when grouping by document.
+    #[serde(default = "default_chunks_per_document")]
+    chunks_per_document: usize,
+    /// Documents to skip when grouping by document, to page through them
+    /// `top_k` at a time. Pages are cut from one pool of candidates, so
+    /// paging ends where the pool does.
+    #[serde(default)]
+    offset: usize,
+    /// Facets to count over the chunks that match the filters and any term
+    /// of the keyword queries searched.
+    facets: Option<FacetRequest>,
+    /// Rephrase, expand or extract keywords from the query before searching.
+    rewrite: Option<RewriteOptions>,
+    /// Include what the search ran with in the response.
+    #[serde(default)]
+    debug: bool,
     /// Temporary: pass user_id in request until auth is implemented.
     user_id: Uuid,
 }
@@ -32,11 +88,61 @@ fn default_alpha() -> f32 {
     0.7
 }
 
+fn default_rerank() -> bool {
+    true
+}
+
+fn default_chunks_per_document() -> usize {
+    3
+}
+
+/// Chunks retrieved and grouped when grouping by document, unless
+/// `rerank_top_n` says otherwise. Every page is cut from the same pool.
+const GROUP_CANDIDATES: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum GroupBy {
+    Document,
+}
+
 #[derive(Debug, Serialize)]
 struct SearchResponse {
-    results: Vec<SearchResultItem>,
+    results: SearchResults,
     query: String,
+    /// Results found; when grouping, documents before paging.
     total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    facets: Option<FacetCounts>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    debug: Option<SearchDebug>,
+}
+
+#[derive(Debug, Serialize)]
+struct SearchDebug {
+    /// The query as searched, then any rephrasings fused with it.
+    queries: Vec<SearchQuery>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum SearchResults {
+    Chunks(Vec<SearchResultItem>),
+    Documents(Vec<DocumentResultItem>),
+}
+
+#[derive(Debug, Serialize)]
+struct DocumentResultItem {
+    document_id: Uuid,
+    document_title: String,
+    source_type: SourceType,
+    source_url: Option<String>,
+    /// The document's chunk scores combined by `group_score`.
+    score: f32,
+    /// How many of the retrieved chunks are from this document.
+    matched_chunks: usize,
+    /// The document's best chunks, at most `chunks_per_document`.
+    chunks: Vec<SearchResultItem>,
 }
 
 #[derive(Debug, Serialize)]
@@ -44,11 +150,28 @@ struct SearchResultItem {
     chunk_id: Uuid,
     document_id: Uuid,
     text: String,
+    /// Query term matches in `text`, in characters.
+    highlights: Vec<Highlight>,
+    /// The window of `text` that best matches the query.
+    snippet: Snippet,
+    /// The score results are ordered by: `rerank_score` when reranked,
+    /// otherwise `retrieval_score`.
     score: f32,
+    retrieval_score: f32,
+    rerank_score: Option<f32>,
+    /// Each retriever's rank, score and share of `retrieval_score`, when
+    /// fused in cortex.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contributions: Option<Contributions>,
     document_title: String,
     source_type: SourceType,
     source_url: Option<String>,
     section_title: Option<String>,
+    chunk_kind: ChunkKind,
+    parent_id: Option<Uuid>,
+    /// The chunks joined into `text` when expanded with `context_window`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    window_chunk_ids: Option<Vec<Uuid>>,
 }
 
 async fn search(
@@ -58,52 +181,258 @@ async fn search(
     if req.query.trim().is_empty() {
         return Err(ApiError::BadRequest("query cannot be empty".to_string()));
     }
+    if let Some(filter) = &req.filter {
+        filter
+            .validate()
+            .map_err(|e| ApiError::BadRequest(format!("invalid filter: {e}")))?;
+    }
+    retrieval::check_diversity(req.mmr_lambda, req.max_chunks_per_document)
+        .map_err(ApiError::BadRequest)?;
+    retrieval::check_limits(req.top_k, req.rerank_top_n).map_err(ApiError::BadRequest)?;
+    retrieval::check_context_window(req.context_window).map_err(ApiError::BadRequest)?;
+    if let Some(facets) = &req.facets {
+        facets
+            .validate()
+            .map_err(|e| ApiError::BadRequest(format!("invalid facets: {e}")))?;
+    }
+    let grouped = req.group_by.is_some();
+    if grouped && (req.mmr_lambda.is_some() || req.max_chunks_per_document.is_some()) {
+        return Err(ApiError::BadRequest(
+            "mmr_lambda and max_chunks_per_document cannot be used with group_by".to_string(),
+        ));
+    }
+    if grouped && req.chunks_per_document == 0 {
+        return Err(ApiError::BadRequest(
+            "chunks_per_document must be at least 1".to_string(),
+        ));
+    }
+    if !grouped && req.offset > 0 {
+        return Err(ApiError::BadRequest("offset requires group_by".to_string()));
+    }
+    if req.offset > retrieval::MAX_CANDIDATES {
+        return Err(ApiError::BadRequest(format!(
+            "offset can be at most {}",
+            retrieval::MAX_CANDIDATES
+        )));
+    }
+    if let Some(rewrite) = &req.rewrite {
+        rewrite
+            .validate()
+            .map_err(|e| ApiError::BadRequest(format!("invalid rewrite: {e}")))?;
+    }
+
+    // Queries are embedded with the model the user's index was built with
+    let user_id = UserId(req.user_id);
+    let index = state.postgres.get_search_index(user_id).await?;
 
-    // Embed the query
-    let embeddings = state
+    // Rewrite the query if asked, then embed each query to search with
+    let queries = rewrite::rewrite(
+        &state.ml_client,
+        &state.config.query_rewrite,
+        &req.query,
+        req.rewrite.as_ref(),
+    )
+    .await;
+    let texts = queries.iter().map(|q| q.embedded.clone()).collect();
+    let vectors = state
         .ml_client
-        .embed_batch(vec![req.query.clone()], None)
+        .embed_batch(texts, index.embedding_model.as_deref())
         .await
         .map_err(|e| ApiError::ServiceUnavailable(format!("ML service: {e}")))?;
+    if vectors.len() != queries.len() {
+        return Err(ApiError::Internal("no embedding returned".to_string()));
+    }
 
-    let query_vector = embeddings
-        .into_iter()
-        .next()
-        .ok_or_else(|| ApiError::Internal("no embedding returned".to_string()))?;
+    // Over-fetch so reranking and diversity have candidates to choose from
+    let diversify = req.mmr_lambda.is_some() || req.max_chunks_per_document.is_some();
+    let limit = if grouped {
+        // The same pool for every page, so pages don't overlap or skip
+        req.rerank_top_n.unwrap_or(GROUP_CANDIDATES).max(req.top_k)
+    } else if req.rerank || diversify {
+        req.rerank_top_n
+            .unwrap_or(req.top_k.saturating_mul(3))
+            .max(req.top_k)
+    } else {
+        req.top_k
+    };
 
-    // Hybrid search in Weaviate
-    let user_id = UserId(req.user_id);
-    let results = state
-        .weaviate
-        .hybrid_search(
-            &req.query,
-            &query_vector,
+    // Hybrid search in the vector store
+    let filters = SearchFilters {
+        source_type: req.source_filter.clone(),
+        chunk_kind: req.chunk_kind,
+        filter: req.filter.clone(),
+    };
+    let store = state.vector_store.with_index(&index.class_name);
+    let params: Vec<SearchParams> = queries
+        .iter()
+        .zip(&vectors)
+        .map(|(query, vector)| SearchParams {
+            query: &query.keyword,
+            vector,
             user_id,
-            req.source_filter.as_deref(),
-            req.top_k,
-            req.alpha,
+            filters: &filters,
+            limit,
+            alpha: req.alpha,
+            fusion: req.fusion,
+        })
+        .collect();
+    // Facets count the chunks any of the searched keyword queries match
+    let keywords = queries
+        .iter()
+        .map(|q| q.keyword.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let facets = async {
+        match &req.facets {
+            Some(facets) if !facets.is_empty() => store
+                .facet_counts(&keywords, user_id, &filters, facets)
+                .await
+                .map(Some),
+            _ => Ok(None),
+        }
+    };
+    let (results, facets) =
+        futures::try_join!(retrieval::search_many(store.as_ref(), &params), facets)
+            .map_err(|e| ApiError::Internal(format!("search failed: {e}")))?;
+    let debug = req.debug.then(|| SearchDebug {
+        queries: queries.clone(),
+    });
+
+    let results = if req.expand_parents && req.context_window == 0 {
+        retrieval::expand_to_parents(&state.postgres, results).await?
+    } else {
+        results
+    };
+
+    let hits: Vec<RankedHit> = if req.rerank {
+        // Diversity and grouping pick from every reranked candidate, not
+        // just the best
+        let keep = if diversify || grouped {
+            limit
+        } else {
+            req.top_k
+        };
+        retrieval::rerank(
+            &state.ml_client,
+            &req.query,
+            results,
+            keep,
+            req.rerank_model.as_deref(),
         )
         .await
-        .map_err(|e| ApiError::Internal(format!("search failed: {e}")))?;
+        .map_err(|e| {
+            if e.is_invalid_argument() {
+                ApiError::BadRequest(format!("invalid rerank_model: {e}"))
+            } else {
+                ApiError::ServiceUnavailable(format!("ML service rerank: {e}"))
+            }
+        })?
+    } else {
+        results
+    };
+
+    let terms = QueryTerms::new(&req.query);
+    if let Some(GroupBy::Document) = req.group_by {
+        let groups = retrieval::group_by_document(hits, req.group_score);
+        let total = groups.len();
+        let page = groups.into_iter().skip(req.offset).take(req.top_k);
+        let documents = futures::future::try_join_all(page.map(|group| {
+            DocumentResultItem::new(
+                &state.postgres,
+                group,
+                req.chunks_per_document,
+                req.context_window,
+                &terms,
+            )
+        }))
+        .await?;
+        return Ok(Json(SearchResponse {
+            results: SearchResults::Documents(documents),
+            query: req.query,
+            total,
+            facets,
+            debug,
+        }));
+    }
+
+    let hits = retrieval::diversify(
+        store.as_ref(),
+        hits,
+        req.top_k,
+        req.mmr_lambda,
+        req.max_chunks_per_document,
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("search failed: {e}")))?;
+    let hits = retrieval::expand_to_windows(&state.postgres, hits, req.context_window).await?;
 
-    let total = results.len();
-    let items: Vec<SearchResultItem> = results
+    let total = hits.len();
+    let items: Vec<SearchResultItem> = hits
         .into_iter()
-        .map(|r| SearchResultItem {
+        .map(|hit| SearchResultItem::new(hit, &terms))
+        .collect();
+
+    Ok(Json(SearchResponse {
+        results: SearchResults::Chunks(items),
+        query: req.query,
+        total,
+        facets,
+        debug,
+    }))
+}
+
+impl SearchResultItem {
+    fn new(hit: RankedHit, terms: &QueryTerms) -> Self {
+        let score = hit.score();
+        let r = hit.result;
+        SearchResultItem {
             chunk_id: r.chunk_id.0,
             document_id: r.document_id.0,
+            highlights: terms.highlights(&r.text),
+            snippet: terms.snippet(&r.text),
             text: r.text,
-            score: r.score,
+            score,
+            retrieval_score: r.score,
+            rerank_score: hit.rerank_score,
+            contributions: hit.contributions,
             document_title: r.document_title,
             source_type: r.source_type,
             source_url: r.source_url,
             section_title: r.section_title,
-        })
-        .collect();
+            chunk_kind: r.kind,
+            parent_id: r.parent_id.map(|id| id.0),
+            window_chunk_ids: hit
+                .window
+                .map(|ids| ids.into_iter().map(|id| id.0).collect()),
+        }
+    }
+}
 
-    Ok(Json(SearchResponse {
-        results: items,
-        query: req.query,
-        total,
-    }))
+impl DocumentResultItem {
+    /// The page entry for a document, its best chunks expanded to their
+    /// context windows.
+    async fn new(
+        postgres: &PostgresStore,
+        mut group: DocumentGroup,
+        chunks_per_document: usize,
+        context_window: usize,
+        terms: &QueryTerms,
+    ) -> Result<Self, ApiError> {
+        let matched_chunks = group.hits.len();
+        group.hits.truncate(chunks_per_document);
+        let first = group.hits[0].result.clone();
+        let hits = retrieval::expand_to_windows(postgres, group.hits, context_window).await?;
+        Ok(DocumentResultItem {
+            document_id: first.document_id.0,
+            document_title: first.document_title,
+            source_type: first.source_type,
+            source_url: first.source_url,
+            score: group.score,
+            matched_chunks,
+            chunks: hits
+                .into_iter()
+                .map(|hit| SearchResultItem::new(hit, terms))
+                .collect(),
+        })
+    }
 }
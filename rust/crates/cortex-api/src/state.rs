@@ -1,15 +1,19 @@
 use cortex_common::config::AppConfig;
-use cortex_ingestion::pipeline::IngestionPipeline;
+use cortex_ingestion::pipeline::{IngestionOptions, IngestionPipeline};
 use cortex_ml_client::MlClient;
-use cortex_scheduler::WorkerPool;
+use cortex_scheduler::{Reconciler, WorkerPool};
+use cortex_store::content::ContentStore;
 use cortex_store::postgres::PostgresStore;
-use cortex_store::weaviate::WeaviateStore;
+use cortex_store::vector::{self, VectorStore};
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct AppState {
+    pub config: Arc<AppConfig>,
     pub postgres: PostgresStore,
-    pub weaviate: WeaviateStore,
+    pub vector_store: Arc<dyn VectorStore>,
+    pub content: ContentStore,
     pub ml_client: MlClient,
     pub worker_pool: Arc<WorkerPool>,
 }
@@ -21,8 +25,10 @@ impl AppState {
         postgres.run_migrations().await?;
         tracing::info!("PostgreSQL connected and migrations applied");
 
-        let weaviate = WeaviateStore::new(&config.weaviate_url);
-        tracing::info!("Weaviate client configured at {}", config.weaviate_url);
+        let vector_store = vector::from_config(config, &postgres);
+        tracing::info!(backend = ?config.vector_store.backend, "Vector store configured");
+
+        let content = ContentStore::from_config(&config.content_store, &postgres);
 
         tracing::info!("Connecting to ML service at {}...", config.ml_service_url);
         let ml_client = MlClient::connect(&config.ml_service_url).await?;
@@ -30,16 +36,28 @@ impl AppState {
 
         let pipeline = Arc::new(IngestionPipeline::new(
             postgres.clone(),
-            weaviate.clone(),
+            vector_store.clone(),
+            content.clone(),
             ml_client.clone(),
+            IngestionOptions::from_config(config),
         ));
 
-        let worker_pool = Arc::new(WorkerPool::spawn(4, pipeline, postgres.clone()));
+        let worker_pool = Arc::new(WorkerPool::spawn(
+            4,
+            pipeline.clone(),
+            postgres.clone(),
+            vector_store.clone(),
+        ));
         tracing::info!("Worker pool started with 4 workers");
 
+        Reconciler::new(pipeline, postgres.clone())
+            .spawn(Duration::from_secs(config.reconcile_interval_secs));
+
         Ok(Self {
+            config: Arc::new(config.clone()),
             postgres,
-            weaviate,
+            vector_store,
+            content,
             ml_client,
             worker_pool,
         })
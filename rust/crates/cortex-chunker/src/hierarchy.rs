@@ -0,0 +1,111 @@
+use crate::{estimate_tokens, ChunkingStrategy, TextChunk};
+
+/// A large parent section together with the small child chunks carved out of it.
+#[derive(Debug, Clone)]
+pub struct ParentChunk {
+    pub chunk: TextChunk,
+    pub children: Vec<TextChunk>,
+}
+
+impl ParentChunk {
+    /// A parent whose only child is the chunk itself, for chunks that must
+    /// not be split further (such as tables).
+    pub fn leaf(chunk: TextChunk) -> Self {
+        Self {
+            children: vec![chunk.clone()],
+            chunk,
+        }
+    }
+}
+
+/// Small-to-big chunker: splits text into parent sections, then splits each
+/// parent into child chunks. Children are embedded and searched; parents are
+/// returned as context.
+pub struct HierarchicalChunker {
+    parent: Box<dyn ChunkingStrategy>,
+    child: Box<dyn ChunkingStrategy>,
+}
+
+impl HierarchicalChunker {
+    pub fn new(parent: Box<dyn ChunkingStrategy>, child: Box<dyn ChunkingStrategy>) -> Self {
+        Self { parent, child }
+    }
+
+    /// Chunk `text` into parents and children. Child offsets are relative to
+    /// `text`, the same as parent offsets.
+    ///
+    /// Chunks that already carry a retrieval window (sentence windows) are not
+    /// split further: each becomes the single child of a parent holding its window.
+    pub fn chunk_hierarchy(&self, text: &str, section_title: Option<&str>) -> Vec<ParentChunk> {
+        self.parent
+            .chunk(text, section_title)
+            .into_iter()
+            .map(|mut parent| {
+                if let Some(window) = parent.window.take() {
+                    let child = parent.clone();
+                    parent.token_count_estimate = estimate_tokens(&window);
+                    parent.text = window;
+                    return ParentChunk {
+                        chunk: parent,
+                        children: vec![child],
+                    };
+                }
+
+                let children = self
+                    .child
+                    .chunk(&parent.text, section_title)
+                    .into_iter()
+                    .map(|mut child| {
+                        child.start_char += parent.start_char;
+                        child.end_char += parent.start_char;
+                        child
+                    })
+                    .collect();
+
+                ParentChunk {
+                    chunk: parent,
+                    children,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recursive::RecursiveChunker;
+
+    #[test]
+    fn test_sentence_windows_become_parents() {
+        let chunker = HierarchicalChunker::new(
+            Box::new(crate::sentence::SentenceWindowChunker::new(1, 100)),
+            Box::new(RecursiveChunker::new(10, 0)),
+        );
+        let parents = chunker.chunk_hierarchy("One. Two. Three.", None);
+        assert_eq!(parents.len(), 3);
+        assert_eq!(parents[1].chunk.text, "One. Two. Three.");
+        assert_eq!(parents[1].children.len(), 1);
+        assert_eq!(parents[1].children[0].text, "Two.");
+    }
+
+    #[test]
+    fn test_children_nest_inside_parents() {
+        let chunker = HierarchicalChunker::new(
+            Box::new(RecursiveChunker::new(40, 0)),
+            Box::new(RecursiveChunker::new(10, 0)),
+        );
+        let text = "Alpha beta gamma delta epsilon zeta.\n\nEta theta iota kappa lambda mu.\n\nNu xi omicron pi rho sigma tau upsilon.";
+        let parents = chunker.chunk_hierarchy(text, Some("Greek"));
+
+        assert!(!parents.is_empty());
+        for parent in &parents {
+            assert!(parent.children.len() > 1);
+            for child in &parent.children {
+                assert!(parent.chunk.text.contains(child.text.trim()));
+                assert!(child.start_char >= parent.chunk.start_char);
+                assert_eq!(child.section_title.as_deref(), Some("Greek"));
+            }
+        }
+    }
+}
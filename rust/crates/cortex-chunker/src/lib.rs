@@ -1,6 +1,10 @@
+pub mod hierarchy;
 pub mod recursive;
+pub mod sentence;
 pub mod strategies;
+pub mod table;
 
+use cortex_common::types::ChunkKind;
 use serde::{Deserialize, Serialize};
 
 /// A text chunk produced by the chunking engine.
@@ -12,6 +16,10 @@ pub struct TextChunk {
     pub start_char: usize,
     pub end_char: usize,
     pub token_count_estimate: usize,
+    /// Wider retrieval text around `text`, for strategies that embed a small
+    /// unit but return its surroundings (e.g. sentence windows).
+    pub window: Option<String>,
+    pub kind: ChunkKind,
 }
 
 /// Trait for chunking strategies.
@@ -21,5 +29,5 @@ pub trait ChunkingStrategy: Send + Sync {
 
 /// Estimate token count using the ~4 chars per token heuristic.
 pub fn estimate_tokens(text: &str) -> usize {
-    (text.len() + 3) / 4
+    text.len().div_ceil(4)
 }
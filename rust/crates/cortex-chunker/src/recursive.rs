@@ -1,10 +1,13 @@
+use cortex_common::types::ChunkKind;
+
 use crate::{estimate_tokens, ChunkingStrategy, TextChunk};
 
 /// Recursive character text splitter — splits on decreasing separator granularity.
 pub struct RecursiveChunker {
     target_tokens: usize,
     overlap_tokens: usize,
-    separators: Vec<&'static str>,
+    separators: Vec<String>,
+    min_chunk_tokens: usize,
 }
 
 impl RecursiveChunker {
@@ -12,8 +15,23 @@ impl RecursiveChunker {
         Self {
             target_tokens,
             overlap_tokens,
-            separators: vec!["\n\n", "\n", ". ", " "],
+            separators: ["\n\n", "\n", ". ", " "].map(String::from).to_vec(),
+            min_chunk_tokens: 0,
+        }
+    }
+
+    /// Override the separators, tried in order from coarsest to finest.
+    pub fn with_separators(mut self, separators: Vec<String>) -> Self {
+        if !separators.is_empty() {
+            self.separators = separators;
         }
+        self
+    }
+
+    /// Merge trailing chunks smaller than `min_chunk_tokens` into the previous chunk.
+    pub fn with_min_chunk_tokens(mut self, min_chunk_tokens: usize) -> Self {
+        self.min_chunk_tokens = min_chunk_tokens;
+        self
     }
 
     pub fn default_config() -> Self {
@@ -29,11 +47,17 @@ impl RecursiveChunker {
             return vec![text.to_string()];
         }
 
-        let separator = self.separators.get(depth).copied().unwrap_or(" ");
+        let separator = self
+            .separators
+            .get(depth)
+            .map(String::as_str)
+            .unwrap_or(" ");
 
         let splits: Vec<&str> = text.split(separator).collect();
         let mut chunks: Vec<String> = Vec::new();
         let mut current = String::new();
+        // Length of the overlap prefix carried over into `current`
+        let mut overlap_len = 0;
 
         for split in splits {
             let candidate = if current.is_empty() {
@@ -46,14 +70,21 @@ impl RecursiveChunker {
                 chunks.push(current.clone());
                 // Add overlap from the end of the previous chunk
                 let overlap = self.get_overlap(&current);
+                overlap_len = overlap.len() + separator.len();
                 current = format!("{}{}{}", overlap, separator, split);
             } else {
                 current = candidate;
             }
         }
 
+        // An undersized remainder is folded into the previous chunk, minus its overlap
+        let mut tail = None;
         if !current.is_empty() {
-            chunks.push(current);
+            if !chunks.is_empty() && estimate_tokens(&current) < self.min_chunk_tokens {
+                tail = Some(current[overlap_len..].to_string());
+            } else {
+                chunks.push(current);
+            }
         }
 
         // Recursively split any chunks that are still too large
@@ -66,6 +97,11 @@ impl RecursiveChunker {
             }
         }
 
+        if let (Some(tail), Some(last)) = (tail, result.last_mut()) {
+            last.push_str(separator);
+            last.push_str(&tail);
+        }
+
         result
     }
 
@@ -99,6 +135,8 @@ impl ChunkingStrategy for RecursiveChunker {
                     section_title: section_title.map(String::from),
                     start_char: start,
                     end_char: end,
+                    window: None,
+                    kind: ChunkKind::Text,
                 }
             })
             .collect()
@@ -117,6 +155,15 @@ mod tests {
         assert_eq!(chunks[0].text, "Hello world.");
     }
 
+    #[test]
+    fn test_small_remainder_merged_into_previous_chunk() {
+        let text = "First paragraph with some content here.\n\nSecond paragraph with different content here.\n\nEnd.";
+        let chunker = RecursiveChunker::new(12, 0).with_min_chunk_tokens(4);
+        let chunks = chunker.chunk(text, None);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[1].text.ends_with("here.\n\nEnd."));
+    }
+
     #[test]
     fn test_long_text_split_on_paragraphs() {
         let chunker = RecursiveChunker::new(20, 5); // Very small target for testing
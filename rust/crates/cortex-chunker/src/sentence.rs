@@ -0,0 +1,241 @@
+use std::ops::Range;
+
+use cortex_common::types::ChunkKind;
+
+use crate::{estimate_tokens, ChunkingStrategy, TextChunk};
+
+/// Abbreviations that are followed by a period but almost never end a sentence.
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "vs", "cf", "fig", "figs", "no", "nos",
+    "approx", "dept", "al", "eq", "ca", "vol", "pp", "e.g", "i.e", "a.m", "p.m",
+];
+
+/// Split text into sentences, returning byte ranges with surrounding whitespace trimmed.
+///
+/// A sentence ends at `.`, `!` or `?` (plus any closing quotes or brackets)
+/// followed by whitespace and a word that does not start in lowercase, or at a
+/// blank line. Periods inside decimals and URLs are not followed by whitespace
+/// and so never split; known abbreviations and initials are skipped.
+pub fn split_sentences(text: &str) -> Vec<Range<usize>> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (pos, ch) = chars[i];
+
+        // Blank line: hard paragraph boundary
+        if ch == '\n' {
+            let mut j = i + 1;
+            while j < chars.len() && chars[j].1 != '\n' && chars[j].1.is_whitespace() {
+                j += 1;
+            }
+            if j < chars.len() && chars[j].1 == '\n' {
+                push_trimmed(text, start..pos, &mut sentences);
+                start = chars[j].0;
+                i = j + 1;
+                continue;
+            }
+        }
+
+        if !matches!(ch, '.' | '!' | '?') {
+            i += 1;
+            continue;
+        }
+
+        // Consume runs like "?!" or "..." and any closing punctuation
+        let mut j = i + 1;
+        while j < chars.len() && matches!(chars[j].1, '.' | '!' | '?') {
+            j += 1;
+        }
+        while j < chars.len() && matches!(chars[j].1, '"' | '\'' | ')' | ']' | '”' | '’') {
+            j += 1;
+        }
+
+        // Decimals, URLs and file names have no whitespace after the period
+        if j < chars.len() && !chars[j].1.is_whitespace() {
+            i = j;
+            continue;
+        }
+
+        if ch == '.' && j == i + 1 && is_abbreviation(&text[start..pos]) {
+            i = j;
+            continue;
+        }
+
+        let mut k = j;
+        while k < chars.len() && chars[k].1.is_whitespace() {
+            k += 1;
+        }
+        if k < chars.len() && chars[k].1.is_lowercase() {
+            i = j;
+            continue;
+        }
+
+        let end = chars.get(j).map(|(p, _)| *p).unwrap_or(text.len());
+        push_trimmed(text, start..end, &mut sentences);
+        start = end;
+        i = j;
+    }
+
+    push_trimmed(text, start..text.len(), &mut sentences);
+    sentences
+}
+
+fn is_abbreviation(preceding: &str) -> bool {
+    let word = preceding
+        .rsplit(char::is_whitespace)
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches(|c: char| !c.is_alphanumeric());
+
+    let mut letters = word.chars();
+    // Single-letter initials such as "J. Smith"
+    if let (Some(first), None) = (letters.next(), letters.next()) {
+        return first.is_alphabetic();
+    }
+
+    ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+fn push_trimmed(text: &str, range: Range<usize>, sentences: &mut Vec<Range<usize>>) {
+    let slice = &text[range.clone()];
+    let trimmed_start = slice.len() - slice.trim_start().len();
+    let trimmed = slice.trim();
+    if !trimmed.is_empty() {
+        let start = range.start + trimmed_start;
+        sentences.push(start..start + trimmed.len());
+    }
+}
+
+/// Break sentences longer than `max_tokens` at whitespace so every embedded
+/// unit stays within the embedding model's input size.
+fn cap_sentence_length(
+    text: &str,
+    sentences: Vec<Range<usize>>,
+    max_tokens: usize,
+) -> Vec<Range<usize>> {
+    let max_chars = max_tokens.max(1) * 4;
+    let mut capped = Vec::with_capacity(sentences.len());
+
+    for range in sentences {
+        if estimate_tokens(&text[range.clone()]) <= max_tokens {
+            capped.push(range);
+            continue;
+        }
+
+        let mut piece_start = range.start;
+        let mut last_space = None;
+        for (offset, ch) in text[range.clone()].char_indices() {
+            let pos = range.start + offset;
+            if pos - piece_start >= max_chars {
+                let split_at = last_space.filter(|s| *s > piece_start).unwrap_or(pos);
+                push_trimmed(text, piece_start..split_at, &mut capped);
+                piece_start = split_at;
+            }
+            if ch.is_whitespace() {
+                last_space = Some(pos);
+            }
+        }
+        push_trimmed(text, piece_start..range.end, &mut capped);
+    }
+
+    capped
+}
+
+/// Embeds single sentences but keeps a window of surrounding sentences as the
+/// retrieval text, so matching is precise and context is still complete.
+pub struct SentenceWindowChunker {
+    /// Number of sentences kept on each side of the embedded sentence.
+    window_sentences: usize,
+    /// Sentences longer than this are broken up before embedding.
+    max_sentence_tokens: usize,
+}
+
+impl SentenceWindowChunker {
+    pub fn new(window_sentences: usize, max_sentence_tokens: usize) -> Self {
+        Self {
+            window_sentences,
+            max_sentence_tokens,
+        }
+    }
+}
+
+impl ChunkingStrategy for SentenceWindowChunker {
+    fn chunk(&self, text: &str, section_title: Option<&str>) -> Vec<TextChunk> {
+        let sentences = cap_sentence_length(text, split_sentences(text), self.max_sentence_tokens);
+
+        sentences
+            .iter()
+            .enumerate()
+            .map(|(i, range)| {
+                let first = i.saturating_sub(self.window_sentences);
+                let last = (i + self.window_sentences).min(sentences.len() - 1);
+                let sentence = &text[range.clone()];
+
+                TextChunk {
+                    text: sentence.to_string(),
+                    chunk_index: i,
+                    section_title: section_title.map(String::from),
+                    start_char: range.start,
+                    end_char: range.end,
+                    token_count_estimate: estimate_tokens(sentence),
+                    window: Some(text[sentences[first].start..sentences[last].end].to_string()),
+                    kind: ChunkKind::Text,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sentences(text: &str) -> Vec<&str> {
+        split_sentences(text)
+            .into_iter()
+            .map(|r| &text[r])
+            .collect()
+    }
+
+    #[test]
+    fn test_abbreviations_decimals_and_urls() {
+        let text = "Dr. Smith paid $3.50 for it, e.g. at the store. See https://example.com/a.html?x=1 for details! \
+                    Was it J. R. R. Tolkien? Yes.";
+        assert_eq!(
+            sentences(text),
+            vec![
+                "Dr. Smith paid $3.50 for it, e.g. at the store.",
+                "See https://example.com/a.html?x=1 for details!",
+                "Was it J. R. R. Tolkien?",
+                "Yes.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blank_line_and_quotes() {
+        let text = "Heading without period\n\nShe said \"stop.\" Then left...";
+        assert_eq!(
+            sentences(text),
+            vec![
+                "Heading without period",
+                "She said \"stop.\"",
+                "Then left..."
+            ]
+        );
+    }
+
+    #[test]
+    fn test_window_surrounds_sentence() {
+        let chunker = SentenceWindowChunker::new(1, 100);
+        let chunks = chunker.chunk("One. Two. Three. Four.", None);
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0].text, "One.");
+        assert_eq!(chunks[0].window.as_deref(), Some("One. Two."));
+        assert_eq!(chunks[2].text, "Three.");
+        assert_eq!(chunks[2].window.as_deref(), Some("Two. Three. Four."));
+    }
+}
@@ -0,0 +1,125 @@
+use cortex_common::types::ChunkKind;
+
+use crate::{estimate_tokens, TextChunk};
+
+/// A table kept as structured rows so it can be chunked without being flattened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    /// The header row, as it appeared in the source (e.g. `| Plan | Price |`).
+    pub header: String,
+    /// The header/body delimiter row, if the source format has one (e.g. `|---|---|`).
+    pub separator: Option<String>,
+    pub rows: Vec<String>,
+    /// Byte offset of the table within its section.
+    pub start_offset: usize,
+}
+
+impl Table {
+    fn head(&self) -> String {
+        match &self.separator {
+            Some(separator) => format!("{}\n{}", self.header, separator),
+            None => self.header.clone(),
+        }
+    }
+
+    fn render(&self, rows: &[String]) -> String {
+        let mut text = self.head();
+        for row in rows {
+            text.push('\n');
+            text.push_str(row);
+        }
+        text
+    }
+}
+
+/// Chunk a table, keeping it whole when it fits in `max_tokens`. Larger tables
+/// are split by rows, and every chunk repeats the header row so each one can be
+/// read on its own. A single row is never split.
+pub fn chunk_table(
+    table: &Table,
+    section_title: Option<&str>,
+    max_tokens: usize,
+) -> Vec<TextChunk> {
+    let mut groups: Vec<&[String]> = Vec::new();
+    let mut group_start = 0;
+
+    for end in 1..=table.rows.len() {
+        let too_big = estimate_tokens(&table.render(&table.rows[group_start..end])) > max_tokens;
+        if too_big && end - group_start > 1 {
+            groups.push(&table.rows[group_start..end - 1]);
+            group_start = end - 1;
+        }
+    }
+    if group_start < table.rows.len() || groups.is_empty() {
+        groups.push(&table.rows[group_start..]);
+    }
+
+    let mut offset = table.start_offset + table.head().len() + 1;
+    groups
+        .into_iter()
+        .enumerate()
+        .map(|(i, rows)| {
+            let text = table.render(rows);
+            let start = if i == 0 { table.start_offset } else { offset };
+            offset += rows.iter().map(|r| r.len() + 1).sum::<usize>();
+            // Without the newline after the last row
+            let end = offset - 1;
+
+            TextChunk {
+                token_count_estimate: estimate_tokens(&text),
+                text,
+                chunk_index: i,
+                section_title: section_title.map(String::from),
+                start_char: start,
+                end_char: end,
+                window: None,
+                kind: ChunkKind::Table,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(rows: usize) -> Table {
+        Table {
+            header: "| Plan | Price |".to_string(),
+            separator: Some("|------|-------|".to_string()),
+            rows: (0..rows)
+                .map(|i| format!("| plan-{i} | {i}.99 |"))
+                .collect(),
+            start_offset: 0,
+        }
+    }
+
+    #[test]
+    fn test_small_table_kept_whole() {
+        let chunks = chunk_table(&table(3), None, 400);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].text.lines().count(), 5);
+        assert_eq!(chunks[0].kind, ChunkKind::Table);
+    }
+
+    #[test]
+    fn test_large_table_split_by_rows_with_header() {
+        let chunks = chunk_table(&table(20), Some("Pricing"), 30);
+        assert!(chunks.len() > 1);
+        let total_rows: usize = chunks.iter().map(|c| c.text.lines().count() - 2).sum();
+        assert_eq!(total_rows, 20);
+        for chunk in &chunks {
+            assert!(chunk
+                .text
+                .starts_with("| Plan | Price |\n|------|-------|\n| plan-"));
+        }
+
+        // Offsets cover each chunk's own rows in the source, header only for the first
+        let source = table(20).render(&table(20).rows);
+        assert_eq!(&source[..chunks[0].end_char], chunks[0].text);
+        let second = &source[chunks[1].start_char..chunks[1].end_char];
+        assert!(second.starts_with("| plan-"));
+        assert!(chunks[1].text.ends_with(second));
+        assert_eq!(chunks.last().unwrap().end_char, source.len());
+    }
+}
@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::types::SourceType;
+
+/// Which chunking algorithm a profile uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyKind {
+    #[default]
+    Recursive,
+    /// Embed single sentences, return a window of surrounding sentences.
+    SentenceWindow,
+}
+
+/// A named set of chunking parameters. The profile used for a document is
+/// recorded on it so a reindex can reproduce or deliberately change it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkingProfile {
+    #[serde(default = "default_profile_name")]
+    pub name: String,
+    #[serde(default)]
+    pub strategy: StrategyKind,
+    pub target_tokens: usize,
+    #[serde(default)]
+    pub overlap_tokens: usize,
+    /// Separators tried in order, from coarsest to finest.
+    #[serde(default = "default_separators")]
+    pub separators: Vec<String>,
+    /// Trailing chunks smaller than this are merged into the previous chunk.
+    #[serde(default)]
+    pub min_chunk_tokens: usize,
+    /// Sentences kept on each side of the embedded sentence (`sentence_window` only).
+    /// For that strategy `target_tokens` caps the length of a single sentence.
+    #[serde(default = "default_window_sentences")]
+    pub window_sentences: usize,
+}
+
+impl ChunkingProfile {
+    pub fn recursive(name: &str, target_tokens: usize, overlap_tokens: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            strategy: StrategyKind::Recursive,
+            target_tokens,
+            overlap_tokens,
+            separators: default_separators(),
+            min_chunk_tokens: 0,
+            window_sentences: default_window_sentences(),
+        }
+    }
+
+    pub fn sentence_window(
+        name: &str,
+        window_sentences: usize,
+        max_sentence_tokens: usize,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            strategy: StrategyKind::SentenceWindow,
+            target_tokens: max_sentence_tokens,
+            overlap_tokens: 0,
+            separators: default_separators(),
+            min_chunk_tokens: 0,
+            window_sentences,
+        }
+    }
+}
+
+/// How a caller picks a profile: by name from config, or spelled out in full.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ProfileSelector {
+    Named(String),
+    Inline(ChunkingProfile),
+}
+
+/// Which profile each source type uses by default.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SourceChunking {
+    pub profile: String,
+    /// Profile used instead when the document is no longer than
+    /// `ChunkingConfig::short_document_tokens`.
+    #[serde(default)]
+    pub short_profile: Option<String>,
+}
+
+/// Chunking profiles and per-source-type defaults, e.g.
+/// `CHUNKING__SLACK__PROFILE=chat`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChunkingConfig {
+    /// User-defined profiles. These take precedence over the built-in ones.
+    #[serde(default)]
+    pub profiles: HashMap<String, ChunkingProfile>,
+    #[serde(default = "default_long_form_chunking")]
+    pub notion: SourceChunking,
+    #[serde(default = "default_chat_chunking")]
+    pub slack: SourceChunking,
+    #[serde(default = "default_email_chunking")]
+    pub gmail: SourceChunking,
+    #[serde(default = "default_long_form_chunking")]
+    pub pdf_upload: SourceChunking,
+    #[serde(default = "default_short_document_tokens")]
+    pub short_document_tokens: usize,
+}
+
+impl ChunkingConfig {
+    /// Look up a profile by name, falling back to the built-in profiles.
+    pub fn profile(&self, name: &str) -> Option<ChunkingProfile> {
+        self.profiles
+            .get(name)
+            .cloned()
+            .or_else(|| builtin_profiles().into_iter().find(|p| p.name == name))
+    }
+
+    /// Resolve a caller-supplied selector into a concrete profile.
+    pub fn resolve(&self, selector: &ProfileSelector) -> Result<ChunkingProfile, String> {
+        match selector {
+            ProfileSelector::Named(name) => self
+                .profile(name)
+                .ok_or_else(|| format!("unknown chunking profile: {name}")),
+            ProfileSelector::Inline(profile) => {
+                if profile.target_tokens == 0 {
+                    return Err("chunking profile target_tokens must be positive".to_string());
+                }
+                Ok(profile.clone())
+            }
+        }
+    }
+
+    /// The default profile for a source type and document size.
+    pub fn profile_for(&self, source_type: SourceType, token_count: usize) -> ChunkingProfile {
+        let source = match source_type {
+            SourceType::Notion => &self.notion,
+            SourceType::Slack => &self.slack,
+            SourceType::Gmail => &self.gmail,
+            SourceType::PdfUpload => &self.pdf_upload,
+        };
+
+        let name = match &source.short_profile {
+            Some(short) if token_count <= self.short_document_tokens => short,
+            _ => &source.profile,
+        };
+
+        self.profile(name).unwrap_or_else(|| {
+            tracing::warn!(profile = %name, %source_type, "Unknown chunking profile, using long_form");
+            ChunkingProfile::recursive("long_form", 400, 50)
+        })
+    }
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            profiles: HashMap::new(),
+            notion: default_long_form_chunking(),
+            slack: default_chat_chunking(),
+            gmail: default_email_chunking(),
+            pdf_upload: default_long_form_chunking(),
+            short_document_tokens: default_short_document_tokens(),
+        }
+    }
+}
+
+/// Profiles available without any configuration.
+pub fn builtin_profiles() -> Vec<ChunkingProfile> {
+    vec![
+        // Long-form content: use larger chunks
+        ChunkingProfile::recursive("long_form", 400, 50),
+        ChunkingProfile::recursive("short_form", 300, 40),
+        // Slack messages are already short
+        ChunkingProfile::recursive("chat", 200, 30),
+        // Gmail: moderate chunk size
+        ChunkingProfile::recursive("email", 350, 50),
+        ChunkingProfile::sentence_window("sentence_window", 2, 128),
+    ]
+}
+
+fn default_profile_name() -> String {
+    "custom".to_string()
+}
+
+fn default_separators() -> Vec<String> {
+    ["\n\n", "\n", ". ", " "].map(String::from).to_vec()
+}
+
+fn default_window_sentences() -> usize {
+    2
+}
+
+fn default_long_form_chunking() -> SourceChunking {
+    SourceChunking {
+        profile: "long_form".to_string(),
+        short_profile: Some("short_form".to_string()),
+    }
+}
+
+fn default_chat_chunking() -> SourceChunking {
+    SourceChunking {
+        profile: "chat".to_string(),
+        short_profile: None,
+    }
+}
+
+fn default_email_chunking() -> SourceChunking {
+    SourceChunking {
+        profile: "email".to_string(),
+        short_profile: None,
+    }
+}
+
+fn default_short_document_tokens() -> usize {
+    500
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_profiles_by_source_and_size() {
+        let config = ChunkingConfig::default();
+        assert_eq!(
+            config
+                .profile_for(SourceType::PdfUpload, 2000)
+                .target_tokens,
+            400
+        );
+        assert_eq!(
+            config.profile_for(SourceType::Notion, 100).target_tokens,
+            300
+        );
+        assert_eq!(
+            config.profile_for(SourceType::Slack, 2000).target_tokens,
+            200
+        );
+        assert_eq!(
+            config.profile_for(SourceType::Gmail, 100).target_tokens,
+            350
+        );
+    }
+
+    #[test]
+    fn test_resolve_selector() {
+        let config = ChunkingConfig::default();
+        let named: ProfileSelector = serde_json::from_str(r#""chat""#).unwrap();
+        assert_eq!(config.resolve(&named).unwrap().name, "chat");
+
+        let inline: ProfileSelector =
+            serde_json::from_str(r#"{"target_tokens": 256, "overlap_tokens": 32}"#).unwrap();
+        let profile = config.resolve(&inline).unwrap();
+        assert_eq!(profile.name, "custom");
+        assert_eq!(profile.separators.len(), 4);
+
+        assert!(config
+            .resolve(&ProfileSelector::Named("missing".to_string()))
+            .is_err());
+    }
+}
@@ -1,5 +1,8 @@
 use serde::Deserialize;
 
+use crate::chunking::ChunkingConfig;
+use crate::types::SourceType;
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct AppConfig {
     #[serde(default = "default_database_url")]
@@ -12,6 +15,143 @@ pub struct AppConfig {
     pub host: String,
     #[serde(default = "default_port")]
     pub port: u16,
+    /// Target size of the child chunks that get embedded and searched.
+    #[serde(default = "default_child_chunk_tokens")]
+    pub child_chunk_tokens: usize,
+    #[serde(default = "default_child_chunk_overlap_tokens")]
+    pub child_chunk_overlap_tokens: usize,
+    #[serde(default)]
+    pub contextual_headers: ContextualHeadersConfig,
+    #[serde(default)]
+    pub chunking: ChunkingConfig,
+    /// How often half-written documents are looked for and retried.
+    #[serde(default = "default_reconcile_interval_secs")]
+    pub reconcile_interval_secs: u64,
+    #[serde(default)]
+    pub content_store: ContentStoreConfig,
+    #[serde(default)]
+    pub vector_store: VectorStoreConfig,
+    #[serde(default)]
+    pub query_rewrite: QueryRewriteConfig,
+}
+
+/// Where chunk embeddings are stored and searched, e.g.
+/// `VECTOR_STORE__BACKEND=local` with `VECTOR_STORE__PATH=/var/lib/cortex/vectors`.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct VectorStoreConfig {
+    #[serde(default)]
+    pub backend: VectorStoreBackend,
+    /// Root directory for the `local` backend.
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorStoreBackend {
+    /// Weaviate at `weaviate_url`.
+    #[default]
+    Weaviate,
+    /// The pgvector extension in the main Postgres database.
+    Pgvector,
+    /// In-process and not persisted. For development and tests.
+    Memory,
+    /// Embedded Tantivy and HNSW indexes on disk under `path`, for
+    /// single-binary and air-gapped deployments.
+    Local,
+}
+
+/// Where original document text is kept, e.g. `CONTENT_STORE__BACKEND=filesystem`
+/// with `CONTENT_STORE__PATH=/var/lib/cortex/content`.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ContentStoreConfig {
+    #[serde(default)]
+    pub backend: ContentStoreBackend,
+    /// Root directory for the `filesystem` backend.
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentStoreBackend {
+    /// The `document_contents` table.
+    #[default]
+    Postgres,
+    /// Content-addressed files under `path`, keyed by content hash.
+    Filesystem,
+    /// Don't keep original text. Reindexing and content previews are unavailable.
+    None,
+}
+
+/// The LLM that rewrites search queries when a request asks for it, e.g.
+/// `QUERY_REWRITE__PROVIDER=ollama`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct QueryRewriteConfig {
+    #[serde(default = "default_summary_provider")]
+    pub provider: String,
+    #[serde(default = "default_summary_model")]
+    pub model: String,
+}
+
+/// Contextual chunk headers, configured per source type, e.g.
+/// `CONTEXTUAL_HEADERS__PDF_UPLOAD__ENABLED=true`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ContextualHeadersConfig {
+    #[serde(default)]
+    pub notion: ContextualHeaderSettings,
+    #[serde(default)]
+    pub slack: ContextualHeaderSettings,
+    #[serde(default)]
+    pub gmail: ContextualHeaderSettings,
+    #[serde(default)]
+    pub pdf_upload: ContextualHeaderSettings,
+    /// LLM provider used to write document summaries.
+    #[serde(default = "default_summary_provider")]
+    pub summary_provider: String,
+    #[serde(default = "default_summary_model")]
+    pub summary_model: String,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+pub struct ContextualHeaderSettings {
+    /// Prepend a title and section header to each chunk before embedding.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Also include a one-sentence document summary generated by the ML service.
+    #[serde(default)]
+    pub summary: bool,
+}
+
+impl ContextualHeadersConfig {
+    pub fn for_source(&self, source_type: SourceType) -> ContextualHeaderSettings {
+        match source_type {
+            SourceType::Notion => self.notion,
+            SourceType::Slack => self.slack,
+            SourceType::Gmail => self.gmail,
+            SourceType::PdfUpload => self.pdf_upload,
+        }
+    }
+}
+
+impl Default for ContextualHeadersConfig {
+    fn default() -> Self {
+        Self {
+            notion: ContextualHeaderSettings::default(),
+            slack: ContextualHeaderSettings::default(),
+            gmail: ContextualHeaderSettings::default(),
+            pdf_upload: ContextualHeaderSettings::default(),
+            summary_provider: default_summary_provider(),
+            summary_model: default_summary_model(),
+        }
+    }
+}
+
+impl Default for QueryRewriteConfig {
+    fn default() -> Self {
+        Self {
+            provider: default_summary_provider(),
+            model: default_summary_model(),
+        }
+    }
 }
 
 fn default_database_url() -> String {
@@ -34,9 +174,37 @@ fn default_port() -> u16 {
     8080
 }
 
+fn default_child_chunk_tokens() -> usize {
+    128
+}
+
+fn default_child_chunk_overlap_tokens() -> usize {
+    16
+}
+
+fn default_reconcile_interval_secs() -> u64 {
+    60
+}
+
+fn default_summary_provider() -> String {
+    "claude".to_string()
+}
+
+fn default_summary_model() -> String {
+    "claude-sonnet-4-5-20250929".to_string()
+}
+
 impl AppConfig {
+    /// Load config from the environment, layered over the file named by
+    /// `CORTEX_CONFIG_FILE` if set (useful for structured settings such as
+    /// chunking profiles).
     pub fn from_env() -> Result<Self, config::ConfigError> {
-        config::Config::builder()
+        let mut builder = config::Config::builder();
+        if let Ok(path) = std::env::var("CORTEX_CONFIG_FILE") {
+            builder = builder.add_source(config::File::with_name(&path));
+        }
+
+        builder
             .add_source(config::Environment::default().separator("__"))
             .build()?
             .try_deserialize()
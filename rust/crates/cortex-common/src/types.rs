@@ -30,6 +30,12 @@ macro_rules! impl_id {
             }
         }
 
+        impl Default for $t {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
         impl fmt::Display for $t {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
                 write!(f, "{}", self.0)
@@ -84,6 +90,69 @@ impl std::str::FromStr for SourceType {
     }
 }
 
+/// What kind of content a chunk holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkKind {
+    #[default]
+    Text,
+    Table,
+}
+
+impl fmt::Display for ChunkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkKind::Text => write!(f, "text"),
+            ChunkKind::Table => write!(f, "table"),
+        }
+    }
+}
+
+impl std::str::FromStr for ChunkKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ChunkKind::Text),
+            "table" => Ok(ChunkKind::Table),
+            other => Err(format!("unknown chunk kind: {other}")),
+        }
+    }
+}
+
+/// Indexing state of a document. A document is only `Indexed` once its chunks
+/// are confirmed written to the vector store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentStatus {
+    Indexing,
+    Indexed,
+    Failed,
+}
+
+impl fmt::Display for DocumentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocumentStatus::Indexing => write!(f, "indexing"),
+            DocumentStatus::Indexed => write!(f, "indexed"),
+            DocumentStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+impl std::str::FromStr for DocumentStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "indexing" => Ok(DocumentStatus::Indexing),
+            "indexed" => Ok(DocumentStatus::Indexed),
+            "failed" => Ok(DocumentStatus::Failed),
+            other => Err(format!("unknown document status: {other}")),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum JobStatus {
@@ -113,6 +182,8 @@ pub enum JobType {
     IncrementalSync,
     FileUpload,
     Reindex,
+    /// Compare Postgres and the vector store, optionally repairing drift.
+    Reconcile,
 }
 
 impl fmt::Display for JobType {
@@ -122,6 +193,7 @@ impl fmt::Display for JobType {
             JobType::IncrementalSync => write!(f, "incremental_sync"),
             JobType::FileUpload => write!(f, "file_upload"),
             JobType::Reindex => write!(f, "reindex"),
+            JobType::Reconcile => write!(f, "reconcile"),
         }
     }
 }
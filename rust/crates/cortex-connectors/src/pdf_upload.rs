@@ -1,13 +1,44 @@
 use chrono::Utc;
 use cortex_common::types::SourceType;
 use sha2::{Digest, Sha256};
-use uuid::Uuid;
 
 use crate::traits::RawDocument;
 
+/// Collection used for uploads that don't name one.
+pub const DEFAULT_COLLECTION: &str = "default";
+
+/// The stable identity of an uploaded document across versions.
+///
+/// A client-supplied external id wins; otherwise the id is derived from the
+/// collection and filename, so uploading "handbook.pdf" again into the same
+/// collection replaces the previous version. `/` and `%` in the collection
+/// are percent-encoded, so the first `/` always ends it.
+pub fn upload_source_id(
+    filename: &str,
+    collection: Option<&str>,
+    external_id: Option<&str>,
+) -> String {
+    match external_id {
+        Some(id) => format!("ext:{id}"),
+        None => format!(
+            "file:{}/{}",
+            collection
+                .unwrap_or(DEFAULT_COLLECTION)
+                .replace('%', "%25")
+                .replace('/', "%2F"),
+            filename
+        ),
+    }
+}
+
 /// Create a RawDocument from uploaded PDF content.
 /// In Phase 1, we accept pre-extracted text. Full PDF parsing comes next.
-pub fn create_from_text(filename: &str, text: String) -> RawDocument {
+pub fn create_from_text(
+    source_id: String,
+    filename: &str,
+    collection: Option<&str>,
+    text: String,
+) -> RawDocument {
     let content_hash = {
         let mut hasher = Sha256::new();
         hasher.update(text.as_bytes());
@@ -15,12 +46,15 @@ pub fn create_from_text(filename: &str, text: String) -> RawDocument {
     };
 
     RawDocument {
-        source_id: Uuid::new_v4().to_string(),
+        source_id,
         source_type: SourceType::PdfUpload,
         title: filename.to_string(),
         content: text,
         mime_type: "application/pdf".to_string(),
-        metadata: serde_json::json!({ "filename": filename }),
+        metadata: serde_json::json!({
+            "filename": filename,
+            "collection": collection.unwrap_or(DEFAULT_COLLECTION),
+        }),
         content_hash,
         fetched_at: Utc::now(),
         source_url: None,
@@ -33,3 +67,31 @@ pub fn hash_content(content: &[u8]) -> String {
     hasher.update(content);
     format!("{:x}", hasher.finalize())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upload_source_id() {
+        assert_eq!(
+            upload_source_id("handbook.pdf", None, None),
+            "file:default/handbook.pdf"
+        );
+        assert_eq!(
+            upload_source_id("handbook.pdf", Some("hr"), None),
+            "file:hr/handbook.pdf"
+        );
+        // An external id identifies the document regardless of filename
+        assert_eq!(
+            upload_source_id("handbook-v2.pdf", Some("hr"), Some("doc-42")),
+            "ext:doc-42"
+        );
+        // Slashes can't move the boundary between collection and filename
+        assert_ne!(
+            upload_source_id("c", Some("a/b"), None),
+            upload_source_id("b/c", Some("a"), None)
+        );
+        assert_eq!(upload_source_id("c", Some("a/b%"), None), "file:a%2Fb%25/c");
+    }
+}
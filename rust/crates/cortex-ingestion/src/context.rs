@@ -0,0 +1,72 @@
+use cortex_common::config::ContextualHeadersConfig;
+use cortex_ml_client::MlClient;
+
+/// Maximum number of characters of the document sent to the LLM for summarization.
+const SUMMARY_INPUT_CHARS: usize = 8000;
+
+const SUMMARY_SYSTEM_PROMPT: &str = "You summarize documents for a search index. \
+     Reply with exactly one sentence describing what the document is about. \
+     Do not add any preamble.";
+
+/// Build the contextual header embedded alongside each chunk, so that a chunk
+/// like "it must be rotated every 90 days" still says what "it" is.
+pub fn build_header(title: &str, breadcrumb: &[String], summary: Option<&str>) -> String {
+    let mut lines = vec![format!("Document: {title}")];
+
+    if !breadcrumb.is_empty() {
+        lines.push(format!("Section: {}", breadcrumb.join(" > ")));
+    }
+
+    if let Some(summary) = summary.map(str::trim).filter(|s| !s.is_empty()) {
+        lines.push(format!("Summary: {summary}"));
+    }
+
+    lines.join("\n")
+}
+
+/// Text that gets embedded for a chunk with a contextual header.
+pub fn with_header(header: &str, text: &str) -> String {
+    format!("{header}\n\n{text}")
+}
+
+/// Ask the ML service for a one-sentence summary of the document.
+pub async fn summarize(
+    ml_client: &MlClient,
+    config: &ContextualHeadersConfig,
+    title: &str,
+    content: &str,
+) -> Result<String, cortex_ml_client::MlClientError> {
+    let excerpt: String = content.chars().take(SUMMARY_INPUT_CHARS).collect();
+    let prompt = format!("Title: {title}\n\n{excerpt}");
+
+    let summary = ml_client
+        .generate(
+            &prompt,
+            SUMMARY_SYSTEM_PROMPT,
+            &config.summary_provider,
+            &config.summary_model,
+        )
+        .await?;
+
+    Ok(summary.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_includes_breadcrumb_and_summary() {
+        let breadcrumb = vec!["Security".to_string(), "API keys".to_string()];
+        let header = build_header("Handbook", &breadcrumb, Some(" Company policies. "));
+        assert_eq!(
+            header,
+            "Document: Handbook\nSection: Security > API keys\nSummary: Company policies."
+        );
+    }
+
+    #[test]
+    fn test_header_without_section_or_summary() {
+        assert_eq!(build_header("Notes", &[], Some("  ")), "Document: Notes");
+    }
+}
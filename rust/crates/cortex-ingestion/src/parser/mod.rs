@@ -1,5 +1,7 @@
 pub mod plaintext;
 
+use cortex_chunker::table::Table;
+
 /// A parsed document ready for chunking.
 #[derive(Debug, Clone)]
 pub struct ParsedDocument {
@@ -12,15 +14,27 @@ pub struct ParsedDocument {
 #[derive(Debug, Clone)]
 pub struct Section {
     pub title: Option<String>,
+    /// Titles of the enclosing headings, outermost first, ending with `title`.
+    pub breadcrumb: Vec<String>,
     pub content: String,
+    /// `content` split into prose and tables, in document order.
+    pub blocks: Vec<Block>,
     pub start_offset: usize,
 }
 
+/// A structural block within a section.
+#[derive(Debug, Clone)]
+pub enum Block {
+    Text(String),
+    Table(Table),
+}
+
 /// Parse raw text content into a structured document.
 /// Detects headings (markdown-style # or ALL-CAPS lines) and splits into sections.
 pub fn parse_text(title: &str, content: &str) -> ParsedDocument {
     let mut sections = Vec::new();
     let mut current_title: Option<String> = None;
+    let mut heading_stack: Vec<(usize, String)> = Vec::new();
     let mut current_content = String::new();
     let mut current_start = 0;
 
@@ -33,11 +47,17 @@ pub fn parse_text(title: &str, content: &str) -> ParsedDocument {
             if !current_content.trim().is_empty() {
                 sections.push(Section {
                     title: current_title.take(),
+                    breadcrumb: breadcrumb(&heading_stack),
+                    blocks: split_blocks(current_content.trim()),
                     content: current_content.trim().to_string(),
                     start_offset: current_start,
                 });
             }
-            current_title = Some(trimmed.trim_start_matches('#').trim().to_string());
+            let level = trimmed.chars().take_while(|c| *c == '#').count();
+            let heading = trimmed.trim_start_matches('#').trim().to_string();
+            heading_stack.retain(|(l, _)| *l < level);
+            heading_stack.push((level, heading.clone()));
+            current_title = Some(heading);
             current_content = String::new();
             current_start = content.find(line).unwrap_or(0);
         } else {
@@ -52,6 +72,8 @@ pub fn parse_text(title: &str, content: &str) -> ParsedDocument {
     if !current_content.trim().is_empty() {
         sections.push(Section {
             title: current_title,
+            breadcrumb: breadcrumb(&heading_stack),
+            blocks: split_blocks(current_content.trim()),
             content: current_content.trim().to_string(),
             start_offset: current_start,
         });
@@ -61,6 +83,8 @@ pub fn parse_text(title: &str, content: &str) -> ParsedDocument {
     if sections.is_empty() {
         sections.push(Section {
             title: None,
+            breadcrumb: Vec::new(),
+            blocks: split_blocks(content),
             content: content.to_string(),
             start_offset: 0,
         });
@@ -72,3 +96,100 @@ pub fn parse_text(title: &str, content: &str) -> ParsedDocument {
         full_text: content.to_string(),
     }
 }
+
+/// Split section content into text and markdown pipe tables. A table is a
+/// pipe row immediately followed by a `|---|` delimiter row, then more pipe rows.
+pub fn split_blocks(content: &str) -> Vec<Block> {
+    let lines: Vec<&str> = content.split('\n').collect();
+    let mut blocks = Vec::new();
+    let mut text = String::new();
+    let mut offset = 0;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let starts_table =
+            is_table_row(line) && lines.get(i + 1).is_some_and(|l| is_delimiter_row(l));
+
+        if !starts_table {
+            text.push_str(line);
+            text.push('\n');
+            offset += line.len() + 1;
+            i += 1;
+            continue;
+        }
+
+        if !text.trim().is_empty() {
+            blocks.push(Block::Text(text.trim().to_string()));
+        }
+        text.clear();
+
+        let start_offset = offset;
+        let header = line.trim().to_string();
+        let separator = lines[i + 1].trim().to_string();
+        offset += line.len() + lines[i + 1].len() + 2;
+        i += 2;
+
+        let mut rows = Vec::new();
+        while i < lines.len() && is_table_row(lines[i]) {
+            rows.push(lines[i].trim().to_string());
+            offset += lines[i].len() + 1;
+            i += 1;
+        }
+
+        blocks.push(Block::Table(Table {
+            header,
+            separator: Some(separator),
+            rows,
+            start_offset,
+        }));
+    }
+
+    if !text.trim().is_empty() {
+        blocks.push(Block::Text(text.trim().to_string()));
+    }
+
+    blocks
+}
+
+fn is_table_row(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.starts_with('|') && trimmed.matches('|').count() >= 2
+}
+
+fn is_delimiter_row(line: &str) -> bool {
+    let trimmed = line.trim();
+    is_table_row(trimmed)
+        && trimmed.contains('-')
+        && trimmed.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+fn breadcrumb(heading_stack: &[(usize, String)]) -> Vec<String> {
+    heading_stack
+        .iter()
+        .map(|(_, title)| title.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables_become_blocks() {
+        let content = "Pricing below.\n\n| Plan | Price |\n|------|------:|\n| Basic | 10 |\n| Pro | 25 |\n\nPrices exclude VAT.";
+        let blocks = split_blocks(content);
+        assert_eq!(blocks.len(), 3);
+
+        let Block::Table(table) = &blocks[1] else {
+            panic!("expected a table block");
+        };
+        assert_eq!(table.header, "| Plan | Price |");
+        assert_eq!(table.rows, vec!["| Basic | 10 |", "| Pro | 25 |"]);
+        assert_eq!(
+            &content[table.start_offset..table.start_offset + 6],
+            "| Plan"
+        );
+        assert!(matches!(&blocks[2], Block::Text(t) if t == "Prices exclude VAT."));
+    }
+}
@@ -1,10 +1,23 @@
-use cortex_chunker::{estimate_tokens, strategies};
+use chrono::{DateTime, Utc};
+use cortex_chunker::hierarchy::{self, HierarchicalChunker};
+use cortex_chunker::recursive::RecursiveChunker;
+use cortex_chunker::{estimate_tokens, strategies, table};
+use cortex_common::chunking::{ChunkingConfig, ChunkingProfile, ProfileSelector};
+use cortex_common::config::{AppConfig, ContextualHeadersConfig};
 use cortex_common::types::*;
 use cortex_connectors::traits::RawDocument;
 use cortex_ml_client::MlClient;
-use cortex_store::models::{Chunk, CreateDocument};
+use cortex_store::content::{ContentStore, ContentStoreError};
+use cortex_store::models::{
+    Chunk, CreateDocument, Document, OutboxEntry, ParentChunk, ReindexedDocument, SearchIndex,
+};
 use cortex_store::postgres::PostgresStore;
-use cortex_store::weaviate::WeaviateStore;
+use cortex_store::vector::{VectorStore, VectorStoreError};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::context;
+use crate::parser::Block;
 
 #[derive(Debug)]
 pub enum IngestResult {
@@ -12,31 +25,89 @@ pub enum IngestResult {
     Skipped,
 }
 
+/// Tunables for the ingestion pipeline.
+#[derive(Debug, Clone)]
+pub struct IngestionOptions {
+    /// Chunking profiles and per-source-type defaults for parent sections.
+    pub chunking: ChunkingConfig,
+    /// Target size of the child chunks that get embedded. Parent sections are
+    /// sized by the chunking profile.
+    pub child_chunk_tokens: usize,
+    pub child_chunk_overlap_tokens: usize,
+    /// Per-source-type contextual headers prepended to chunks before embedding.
+    pub contextual_headers: ContextualHeadersConfig,
+}
+
+impl IngestionOptions {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            chunking: config.chunking.clone(),
+            child_chunk_tokens: config.child_chunk_tokens,
+            child_chunk_overlap_tokens: config.child_chunk_overlap_tokens,
+            contextual_headers: config.contextual_headers.clone(),
+        }
+    }
+}
+
+impl Default for IngestionOptions {
+    fn default() -> Self {
+        Self {
+            chunking: ChunkingConfig::default(),
+            child_chunk_tokens: 128,
+            child_chunk_overlap_tokens: 16,
+            contextual_headers: ContextualHeadersConfig::default(),
+        }
+    }
+}
+
 pub struct IngestionPipeline {
     postgres: PostgresStore,
-    weaviate: WeaviateStore,
+    vector_store: Arc<dyn VectorStore>,
+    content: ContentStore,
     ml_client: MlClient,
+    options: IngestionOptions,
 }
 
 impl IngestionPipeline {
-    pub fn new(postgres: PostgresStore, weaviate: WeaviateStore, ml_client: MlClient) -> Self {
+    pub fn new(
+        postgres: PostgresStore,
+        vector_store: Arc<dyn VectorStore>,
+        content: ContentStore,
+        ml_client: MlClient,
+        options: IngestionOptions,
+    ) -> Self {
         Self {
             postgres,
-            weaviate,
+            vector_store,
+            content,
             ml_client,
+            options,
         }
     }
 
+    /// The chunking profile a connector's or upload's override selects.
+    pub fn resolve_profile(&self, selector: &ProfileSelector) -> Result<ChunkingProfile, String> {
+        self.options.chunking.resolve(selector)
+    }
+
     /// Process a single document through the full ingestion pipeline.
+    ///
+    /// `chunking_profile` overrides the source-type default profile.
     pub async fn ingest(
         &self,
         doc: RawDocument,
         user_id: UserId,
+        chunking_profile: Option<ChunkingProfile>,
     ) -> Result<IngestResult, IngestionError> {
         // 1. Check content hash — skip if unchanged
         let already_indexed = self
             .postgres
-            .has_content_hash(user_id, &doc.source_type.to_string(), &doc.content_hash)
+            .has_content_hash(
+                user_id,
+                &doc.source_type.to_string(),
+                &doc.source_id,
+                &doc.content_hash,
+            )
             .await
             .map_err(|e| IngestionError::Database(e.to_string()))?;
 
@@ -45,84 +116,492 @@ impl IngestionPipeline {
             return Ok(IngestResult::Skipped);
         }
 
+        self.index_document(doc, user_id, chunking_profile).await
+    }
+
+    /// Re-chunk and re-embed an indexed document from its retained content, in
+    /// place. `chunking_profile` defaults to the profile it was indexed with.
+    pub async fn reindex_document(
+        &self,
+        doc: &Document,
+        chunking_profile: Option<ChunkingProfile>,
+    ) -> Result<IngestResult, IngestionError> {
+        let raw = self.stored_document(doc).await?;
+        let profile = chunking_profile.or_else(|| doc.chunking_profile.clone());
+        self.index_document(raw, doc.user_id, profile).await
+    }
+
+    /// Re-chunk and re-embed a document into `index` (a shadow class) without
+    /// touching its Postgres record. The result is applied by
+    /// `PostgresStore::commit_reindex` once every document has been rebuilt.
+    pub async fn rebuild_into(
+        &self,
+        doc: &Document,
+        chunking_profile: Option<ChunkingProfile>,
+        index: &SearchIndex,
+    ) -> Result<ReindexedDocument, IngestionError> {
+        let raw = self.stored_document(doc).await?;
+        let profile = chunking_profile.or_else(|| doc.chunking_profile.clone());
+
+        let Some(prepared) = self
+            .prepare(&raw, profile, index.embedding_model.as_deref())
+            .await?
+        else {
+            return Ok(ReindexedDocument {
+                document_id: doc.id,
+                chunking_profile: doc.chunking_profile.clone(),
+                parents: Vec::new(),
+                chunks: Vec::new(),
+                chunk_count: 0,
+            });
+        };
+
+        let timestamps = (doc.indexed_at, doc.updated_at);
+        let (parents, chunks) = build_models(&raw, &prepared, doc.id, doc.user_id, timestamps);
+        self.vector_store
+            .with_index(&index.class_name)
+            .upsert_chunks(&chunks, &prepared.embeddings)
+            .await?;
+
+        Ok(ReindexedDocument {
+            document_id: doc.id,
+            chunking_profile: Some(prepared.profile),
+            parents,
+            chunk_count: chunks.len() as i32,
+            chunks,
+        })
+    }
+
+    async fn index_document(
+        &self,
+        doc: RawDocument,
+        user_id: UserId,
+        chunking_profile: Option<ChunkingProfile>,
+    ) -> Result<IngestResult, IngestionError> {
+        let index = self
+            .postgres
+            .get_search_index(user_id)
+            .await
+            .map_err(|e| IngestionError::Database(e.to_string()))?;
+
+        let previous_hash = self
+            .postgres
+            .current_content_hash(user_id, &doc.source_type.to_string(), &doc.source_id)
+            .await
+            .map_err(|e| IngestionError::Database(e.to_string()))?;
+
+        // 2–5. Parse, chunk, build headers and embed
+        let prepared = match self
+            .prepare(
+                &doc,
+                chunking_profile.clone(),
+                index.embedding_model.as_deref(),
+            )
+            .await?
+        {
+            Some(prepared) => prepared,
+            // A changed version with nothing to index still replaces the
+            // previous one, so it doesn't stay searchable under the old hash
+            None if previous_hash.is_some() => {
+                tracing::warn!(source_id = %doc.source_id, "No chunks produced, clearing previous version");
+                PreparedDocument::empty(self.profile_for(&doc, chunking_profile))
+            }
+            None => {
+                tracing::warn!(source_id = %doc.source_id, "No chunks produced, skipping");
+                return Ok(IngestResult::Skipped);
+            }
+        };
+
+        // 6. Phase one: mark the document `indexing`, record the work in the
+        //    outbox, then keep the original text. Once the row refers to it,
+        //    deleting another document with the same text can't remove it
+        let payload = serde_json::to_value(OutboxPayload {
+            document: doc.clone(),
+            chunking_profile: Some(prepared.profile.clone()),
+        })
+        .map_err(|e| IngestionError::Parse(e.to_string()))?;
+
+        let doc_id = self
+            .postgres
+            .begin_indexing(
+                &CreateDocument {
+                    user_id,
+                    source_type: doc.source_type,
+                    source_id: doc.source_id.clone(),
+                    title: doc.title.clone(),
+                    source_url: doc.source_url.clone(),
+                    content_hash: doc.content_hash.clone(),
+                    chunk_count: prepared.embeddings.len() as i32,
+                    mime_type: Some(doc.mime_type.clone()),
+                    metadata: doc.metadata.clone(),
+                    chunking_profile: Some(prepared.profile.clone()),
+                },
+                &payload,
+            )
+            .await
+            .map_err(|e| IngestionError::Database(e.to_string()))?;
+        self.content.put(&doc.content_hash, &doc.content).await?;
+        // The document no longer refers to the version it replaces
+        if let Some(previous_hash) = previous_hash.filter(|h| *h != doc.content_hash) {
+            self.release_content(&previous_hash).await;
+        }
+
+        // 7. Build parent and child models; parents go to Postgres, children to
+        //    the vector store with a record of each mirrored in Postgres.
+        //    Chunks carry the document's timestamps for date filters
+        let now = Utc::now();
+        let timestamps = self
+            .postgres
+            .get_document(doc_id)
+            .await
+            .map_err(|e| IngestionError::Database(e.to_string()))?
+            .map_or((now, now), |stored| (stored.indexed_at, stored.updated_at));
+        let (parents, chunks) = build_models(&doc, &prepared, doc_id, user_id, timestamps);
+
+        // 8. Swap in the new chunk set; on failure the outbox entry is left for the reconciler
+        let store = self.vector_store.with_index(&index.class_name);
+        if let Err(e) = self
+            .write_chunks(
+                store.as_ref(),
+                doc_id,
+                &parents,
+                &chunks,
+                &prepared.embeddings,
+            )
+            .await
+        {
+            if let Err(db_err) = self
+                .postgres
+                .mark_indexing_failed(doc_id, &e.to_string())
+                .await
+            {
+                tracing::error!(%doc_id, error = %db_err, "Failed to record indexing failure");
+            }
+            return Err(e);
+        }
+
+        // 9. Phase two: the vector write is confirmed
+        self.postgres
+            .finish_indexing(doc_id, chunks.len() as i32)
+            .await
+            .map_err(|e| IngestionError::Database(e.to_string()))?;
+
+        let chunk_count = chunks.len();
+        tracing::info!(
+            source_id = %doc.source_id,
+            chunk_count,
+            profile = %prepared.profile.name,
+            "Document ingested successfully"
+        );
+
+        Ok(IngestResult::Indexed { chunk_count })
+    }
+
+    /// Parse, chunk and embed a document. Returns `None` if it produced no chunks.
+    async fn prepare(
+        &self,
+        doc: &RawDocument,
+        chunking_profile: Option<ChunkingProfile>,
+        embedding_model: Option<&str>,
+    ) -> Result<Option<PreparedDocument>, IngestionError> {
         // 2. Parse into sections
         let parsed = crate::parser::parse_text(&doc.title, &doc.content);
 
-        // 3. Select chunking strategy and chunk
-        let token_count = estimate_tokens(&doc.content);
-        let chunker = strategies::select_strategy(doc.source_type, token_count);
+        // 3. Resolve the chunking profile and split into parent sections and child chunks
+        let profile = self.profile_for(doc, chunking_profile);
+        let chunker = HierarchicalChunker::new(
+            strategies::build_strategy(&profile),
+            Box::new(RecursiveChunker::new(
+                self.options.child_chunk_tokens,
+                self.options.child_chunk_overlap_tokens,
+            )),
+        );
 
-        let mut all_chunks = Vec::new();
+        let mut all_parents = Vec::new();
         for section in &parsed.sections {
-            let text_chunks = chunker.chunk(&section.content, section.title.as_deref());
-            all_chunks.extend(text_chunks);
+            let offset = locate(&doc.content, &section.content, section.start_offset)
+                .map(|(start, _)| start);
+            for block in &section.blocks {
+                let parents = match block {
+                    Block::Text(text) => chunker.chunk_hierarchy(text, section.title.as_deref()),
+                    // Tables are embedded and returned whole (or as header-led row groups)
+                    Block::Table(t) => {
+                        table::chunk_table(t, section.title.as_deref(), profile.target_tokens)
+                            .into_iter()
+                            .map(hierarchy::ParentChunk::leaf)
+                            .collect()
+                    }
+                };
+                all_parents.extend(parents.into_iter().map(|parent| (section, offset, parent)));
+            }
         }
+        all_parents.retain(|(_, _, p)| !p.children.is_empty());
 
-        if all_chunks.is_empty() {
-            tracing::warn!(source_id = %doc.source_id, "No chunks produced, skipping");
-            return Ok(IngestResult::Skipped);
+        if all_parents.is_empty() {
+            return Ok(None);
         }
 
-        // 4. Generate embeddings via ML service
-        let texts: Vec<String> = all_chunks.iter().map(|c| c.text.clone()).collect();
-        let embeddings = self
-            .ml_client
-            .embed_batch(texts, None)
+        // 4. Build contextual headers if enabled for this source type
+        let header_settings = self.options.contextual_headers.for_source(doc.source_type);
+
+        let summary = if header_settings.enabled && header_settings.summary {
+            match context::summarize(
+                &self.ml_client,
+                &self.options.contextual_headers,
+                &doc.title,
+                &doc.content,
+            )
             .await
-            ?;
+            {
+                Ok(summary) => Some(summary),
+                Err(e) => {
+                    tracing::warn!(source_id = %doc.source_id, error = %e, "Document summary failed, continuing without it");
+                    None
+                }
+            }
+        } else {
+            None
+        };
 
-        // 5. Create document record in Postgres
-        let doc_id = self
-            .postgres
-            .create_document(&CreateDocument {
-                user_id,
-                source_type: doc.source_type,
-                source_id: doc.source_id.clone(),
-                title: doc.title.clone(),
-                source_url: doc.source_url.clone(),
-                content_hash: doc.content_hash.clone(),
-                chunk_count: all_chunks.len() as i32,
-                mime_type: Some(doc.mime_type.clone()),
-                metadata: doc.metadata.clone(),
+        let headers: Vec<Option<String>> = all_parents
+            .iter()
+            .map(|(section, _, _)| {
+                header_settings.enabled.then(|| {
+                    context::build_header(&doc.title, &section.breadcrumb, summary.as_deref())
+                })
+            })
+            .collect();
+
+        // 5. Generate embeddings for the child chunks via ML service
+        let texts: Vec<String> = all_parents
+            .iter()
+            .zip(&headers)
+            .flat_map(|((_, _, p), header)| {
+                p.children.iter().map(move |c| match header {
+                    Some(header) => context::with_header(header, &c.text),
+                    None => c.text.clone(),
+                })
             })
+            .collect();
+        let embeddings = self.ml_client.embed_batch(texts, embedding_model).await?;
+
+        let (section_offsets, parents) = all_parents
+            .into_iter()
+            .map(|(_, offset, parent)| (offset, parent))
+            .unzip();
+        Ok(Some(PreparedDocument {
+            profile,
+            parents,
+            headers,
+            section_offsets,
+            embeddings,
+        }))
+    }
+
+    /// Delete the retained text of a replaced version unless another document
+    /// still has it. A failure only leaves the text behind, so it is logged.
+    async fn release_content(&self, content_hash: &str) {
+        if let Err(e) = self.content.release(&self.postgres, content_hash).await {
+            tracing::warn!(%content_hash, error = %e, "Failed to delete replaced content");
+        }
+    }
+
+    /// `chunking_profile`, or the default profile for the document's source
+    /// type and size.
+    fn profile_for(
+        &self,
+        doc: &RawDocument,
+        chunking_profile: Option<ChunkingProfile>,
+    ) -> ChunkingProfile {
+        chunking_profile.unwrap_or_else(|| {
+            let token_count = estimate_tokens(&doc.content);
+            self.options
+                .chunking
+                .profile_for(doc.source_type, token_count)
+        })
+    }
+
+    /// Rebuild the ingestable form of a stored document from its retained text.
+    async fn stored_document(&self, doc: &Document) -> Result<RawDocument, IngestionError> {
+        let content = self
+            .content
+            .get(&doc.content_hash)
+            .await?
+            .ok_or(IngestionError::MissingContent(doc.id))?;
+
+        Ok(RawDocument {
+            source_id: doc.source_id.clone(),
+            source_type: doc.source_type,
+            title: doc.title.clone(),
+            content,
+            mime_type: doc
+                .mime_type
+                .clone()
+                .unwrap_or_else(|| "text/plain".to_string()),
+            metadata: doc.metadata.clone(),
+            content_hash: doc.content_hash.clone(),
+            fetched_at: Utc::now(),
+            source_url: doc.source_url.clone(),
+        })
+    }
+
+    /// Re-run an ingest whose vector write was never confirmed.
+    pub async fn retry_outbox(&self, entry: &OutboxEntry) -> Result<IngestResult, IngestionError> {
+        let payload: OutboxPayload = serde_json::from_value(entry.payload.clone())
+            .map_err(|e| IngestionError::Parse(format!("invalid outbox payload: {e}")))?;
+
+        let result = self
+            .ingest(payload.document, entry.user_id, payload.chunking_profile)
+            .await;
+
+        // The claim already counted this retry; record why it failed, whatever
+        // the stage, so the entry shows it.
+        if let Err(e) = &result {
+            self.postgres
+                .mark_indexing_failed(entry.document_id, &e.to_string())
+                .await
+                .map_err(|e| IngestionError::Database(e.to_string()))?;
+        }
+        result
+    }
+
+    /// Swap the document's chunk set: the new children are written before the
+    /// old ones are deleted, so a re-ingested document never disappears from
+    /// search and a failed write leaves the previous version intact.
+    async fn write_chunks(
+        &self,
+        store: &dyn VectorStore,
+        doc_id: DocumentId,
+        parents: &[ParentChunk],
+        chunks: &[Chunk],
+        embeddings: &[Vec<f32>],
+    ) -> Result<(), IngestionError> {
+        let old_ids = store.chunk_ids_by_document(doc_id).await?;
+
+        if chunks.is_empty() {
+            // A version with no chunks only clears the old set
+        } else if let Err(e) = store.upsert_chunks(chunks, embeddings).await {
+            // Don't leave a partial new set next to the old one
+            let new_ids: Vec<ChunkId> = chunks.iter().map(|c| c.id).collect();
+            let _ = store.delete_chunks_by_ids(&new_ids).await;
+            return Err(e.into());
+        }
+
+        self.postgres
+            .replace_document_chunks(doc_id, parents, chunks)
             .await
             .map_err(|e| IngestionError::Database(e.to_string()))?;
 
-        // 6. Delete any old chunks for this document
-        let _ = self.weaviate.delete_chunks_by_document(doc_id).await;
+        store.delete_chunks_by_ids(&old_ids).await?;
+        Ok(())
+    }
+}
 
-        // 7. Build chunk models and batch upsert to Weaviate
-        let chunks: Vec<Chunk> = all_chunks
-            .iter()
-            .enumerate()
-            .map(|(i, tc)| Chunk {
+/// A document's chunks and embeddings, before they are tied to a document id.
+struct PreparedDocument {
+    profile: ChunkingProfile,
+    parents: Vec<hierarchy::ParentChunk>,
+    /// Contextual header of each parent's children, if enabled.
+    headers: Vec<Option<String>>,
+    /// Where each parent's section content starts in the document, if found.
+    section_offsets: Vec<Option<usize>>,
+    /// One embedding per child chunk, in order.
+    embeddings: Vec<Vec<f32>>,
+}
+
+impl PreparedDocument {
+    /// A version that produced no chunks.
+    fn empty(profile: ChunkingProfile) -> Self {
+        Self {
+            profile,
+            parents: Vec::new(),
+            headers: Vec::new(),
+            section_offsets: Vec::new(),
+            embeddings: Vec::new(),
+        }
+    }
+}
+
+/// Build the stored parent sections (Postgres) and child chunks (vector store).
+/// Child chunks are located in the document content in order, so each search
+/// starts at the previous chunk's start to allow for overlap. Table chunks
+/// repeat their header row, so they are placed by the rows they cover instead.
+fn build_models(
+    doc: &RawDocument,
+    prepared: &PreparedDocument,
+    doc_id: DocumentId,
+    user_id: UserId,
+    (indexed_at, updated_at): (DateTime<Utc>, DateTime<Utc>),
+) -> (Vec<ParentChunk>, Vec<Chunk>) {
+    let mut parents = Vec::with_capacity(prepared.parents.len());
+    let mut chunks = Vec::with_capacity(prepared.embeddings.len());
+    let mut cursor = 0;
+    let sections = prepared
+        .parents
+        .iter()
+        .zip(&prepared.headers)
+        .zip(&prepared.section_offsets);
+    for (parent_index, ((parent, header), section_offset)) in sections.enumerate() {
+        let parent_id = ChunkId::new();
+        parents.push(ParentChunk {
+            id: parent_id,
+            document_id: doc_id,
+            user_id,
+            chunk_index: parent_index as i32,
+            text: parent.chunk.text.clone(),
+            section_title: parent.chunk.section_title.clone(),
+        });
+
+        for child in &parent.children {
+            let span = match child.kind {
+                ChunkKind::Table => section_offset
+                    .map(|start| (start + child.start_char, start + child.end_char))
+                    .filter(|&(_, end)| end <= doc.content.len()),
+                _ => locate(&doc.content, &child.text, cursor),
+            };
+            if let Some((start, _)) = span {
+                cursor = start;
+            }
+            chunks.push(Chunk {
                 id: ChunkId::new(),
                 document_id: doc_id,
                 user_id,
-                text: tc.text.clone(),
+                text: child.text.clone(),
                 source_type: doc.source_type,
                 document_title: doc.title.clone(),
                 source_url: doc.source_url.clone(),
-                chunk_index: i as i32,
-                section_title: tc.section_title.clone(),
+                chunk_index: chunks.len() as i32,
+                section_title: child.section_title.clone(),
+                kind: child.kind,
+                parent_id: Some(parent_id),
+                context_header: header.clone(),
                 metadata: doc.metadata.clone(),
-            })
-            .collect();
-
-        self.weaviate
-            .batch_upsert_chunks(&chunks, &embeddings)
-            .await
-            ?;
+                indexed_at,
+                updated_at,
+                start_offset: span.map(|(start, _)| start as i32),
+                end_offset: span.map(|(_, end)| end as i32),
+            });
+        }
+    }
 
-        let chunk_count = chunks.len();
-        tracing::info!(
-            source_id = %doc.source_id,
-            chunk_count,
-            "Document ingested successfully"
-        );
+    (parents, chunks)
+}
 
-        Ok(IngestResult::Indexed { chunk_count })
+/// Byte range of `text` in `content`, searching from `from`. Chunkers that
+/// rewrite whitespace or split tables produce text that isn't found verbatim.
+fn locate(content: &str, text: &str, from: usize) -> Option<(usize, usize)> {
+    if text.is_empty() {
+        return None;
     }
+    let start = from + content.get(from..)?.find(text)?;
+    Some((start, start + text.len()))
+}
+
+/// What the ingest outbox stores so a half-written document can be retried.
+#[derive(Debug, Serialize, Deserialize)]
+struct OutboxPayload {
+    document: RawDocument,
+    chunking_profile: Option<ChunkingProfile>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -130,9 +609,13 @@ pub enum IngestionError {
     #[error("database error: {0}")]
     Database(String),
     #[error("vector store error: {0}")]
-    VectorStore(#[from] cortex_store::weaviate::WeaviateError),
+    VectorStore(#[from] VectorStoreError),
     #[error("ML service error: {0}")]
     MlService(#[from] cortex_ml_client::MlClientError),
     #[error("parse error: {0}")]
     Parse(String),
+    #[error("content store error: {0}")]
+    ContentStore(#[from] ContentStoreError),
+    #[error("no retained content for document {0}")]
+    MissingContent(DocumentId),
 }
@@ -39,12 +39,14 @@ impl MlClient {
         Ok(embeddings)
     }
 
-    /// Rerank documents against a query using a cross-encoder.
+    /// Rerank documents against a query using a cross-encoder. `None` uses
+    /// the ML service's configured reranker model.
     pub async fn rerank(
         &self,
         query: &str,
         documents: Vec<(String, String, f32)>, // (id, text, score)
         top_k: i32,
+        model: Option<&str>,
     ) -> Result<Vec<(String, String, f32)>, MlClientError> {
         let mut client = self.client.clone();
         let docs = documents
@@ -61,7 +63,7 @@ impl MlClient {
             query: query.to_string(),
             documents: docs,
             top_k,
-            model: String::new(),
+            model: model.unwrap_or_default().to_string(),
         });
 
         let response = client.rerank(request).await?.into_inner();
@@ -95,6 +97,29 @@ impl MlClient {
         let response = client.generate(request).await?;
         Ok(response.into_inner())
     }
+
+    /// Run a generation to completion and return the full response text.
+    pub async fn generate(
+        &self,
+        prompt: &str,
+        system_prompt: &str,
+        provider: &str,
+        model: &str,
+    ) -> Result<String, MlClientError> {
+        let mut stream = self
+            .generate_stream(prompt, system_prompt, provider, model)
+            .await?;
+
+        let mut text = String::new();
+        while let Some(chunk) = stream.message().await? {
+            text.push_str(&chunk.text);
+            if chunk.is_final {
+                break;
+            }
+        }
+
+        Ok(text)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -104,3 +129,11 @@ pub enum MlClientError {
     #[error("gRPC status error: {0}")]
     Status(#[from] tonic::Status),
 }
+
+impl MlClientError {
+    /// Whether the ML service rejected the request's arguments, e.g. a
+    /// model it does not allow.
+    pub fn is_invalid_argument(&self) -> bool {
+        matches!(self, MlClientError::Status(status) if status.code() == tonic::Code::InvalidArgument)
+    }
+}
@@ -0,0 +1,244 @@
+use cortex_common::types::*;
+use cortex_ingestion::pipeline::{IngestionError, IngestionPipeline};
+use cortex_store::models::Document;
+use cortex_store::postgres::PostgresStore;
+use cortex_store::vector::VectorStore;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Chunks in the vector store whose document has no Postgres row.
+#[derive(Debug, Clone, Serialize)]
+pub struct OrphanedChunks {
+    pub user_id: UserId,
+    pub document_id: DocumentId,
+    pub chunk_count: usize,
+}
+
+/// A document whose recorded `chunk_count` disagrees with the vector store.
+#[derive(Debug, Clone, Serialize)]
+pub struct CountMismatch {
+    pub user_id: UserId,
+    pub document_id: DocumentId,
+    pub title: String,
+    pub expected: i32,
+    pub actual: usize,
+}
+
+/// A drifted document that couldn't be re-indexed.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedRepair {
+    pub document_id: DocumentId,
+    pub error: String,
+}
+
+/// Where Postgres and the vector store disagree.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConsistencyReport {
+    pub users_checked: usize,
+    pub documents_checked: usize,
+    pub orphaned_chunks: Vec<OrphanedChunks>,
+    pub count_mismatches: Vec<CountMismatch>,
+    /// Indexed documents with no chunks at all in the vector store.
+    pub empty_documents: Vec<DocumentId>,
+    /// Whether orphans were deleted and drifted documents re-indexed.
+    pub repaired: bool,
+    /// Drifted and empty documents re-indexed from their retained text.
+    pub reindexed: Vec<DocumentId>,
+    /// Drifted documents whose original text wasn't retained. They are
+    /// flagged so the next upload or sync of the same content re-indexes them.
+    pub skipped_without_content: Vec<DocumentId>,
+    /// Drifted documents whose re-index failed; the rest are still repaired.
+    pub failed: Vec<FailedRepair>,
+}
+
+impl ConsistencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.orphaned_chunks.is_empty()
+            && self.count_mismatches.is_empty()
+            && self.empty_documents.is_empty()
+    }
+}
+
+/// Compare Postgres with the vector store for one user, or for every user
+/// found in either store. With `repair`, orphaned chunks are deleted and
+/// drifted documents are re-indexed from their retained text.
+pub async fn check(
+    pipeline: &IngestionPipeline,
+    postgres: &PostgresStore,
+    vector_store: &dyn VectorStore,
+    user_id: Option<UserId>,
+    repair: bool,
+) -> anyhow::Result<ConsistencyReport> {
+    let users: HashSet<UserId> = match user_id {
+        Some(user_id) => HashSet::from([user_id]),
+        None => {
+            let mut users: HashSet<_> =
+                postgres.list_document_owners().await?.into_iter().collect();
+            users.extend(vector_store.chunk_owners().await?);
+            users
+        }
+    };
+
+    let mut report = ConsistencyReport {
+        users_checked: users.len(),
+        repaired: repair,
+        ..Default::default()
+    };
+
+    for user_id in users {
+        let index = postgres.get_search_index(user_id).await?;
+        let store = vector_store.with_index(&index.class_name);
+        // Count chunks before listing documents: a document row is written
+        // before its chunks, so chunks of an ingest that starts in between
+        // are never mistaken for orphans
+        let chunk_counts = store.chunk_counts_by_document(user_id).await?;
+        let documents = postgres.list_all_documents(user_id).await?;
+        report.documents_checked += documents.len();
+
+        let orphans_before = report.orphaned_chunks.len();
+        let mismatches_before = report.count_mismatches.len();
+        let empty_before = report.empty_documents.len();
+        compare(user_id, &documents, &chunk_counts, &mut report);
+
+        if repair {
+            let orphans: Vec<DocumentId> = report.orphaned_chunks[orphans_before..]
+                .iter()
+                .map(|o| o.document_id)
+                .collect();
+            store.delete_chunks_by_documents(&orphans).await?;
+
+            let drifted: HashSet<DocumentId> = report.count_mismatches[mismatches_before..]
+                .iter()
+                .map(|m| m.document_id)
+                .chain(report.empty_documents[empty_before..].iter().copied())
+                .collect();
+            for doc in documents.iter().filter(|doc| drifted.contains(&doc.id)) {
+                reindex(pipeline, doc, &mut report).await;
+            }
+        }
+    }
+
+    if !report.skipped_without_content.is_empty() {
+        postgres
+            .mark_needs_reindex(
+                &report.skipped_without_content,
+                "vector store out of sync with document record",
+            )
+            .await?;
+    }
+
+    Ok(report)
+}
+
+/// Rebuild a drifted document's chunk set from its retained text, replacing
+/// whatever chunks it has left. Failures are recorded in the report.
+async fn reindex(pipeline: &IngestionPipeline, doc: &Document, report: &mut ConsistencyReport) {
+    match pipeline.reindex_document(doc, None).await {
+        Ok(_) => report.reindexed.push(doc.id),
+        Err(IngestionError::MissingContent(id)) => report.skipped_without_content.push(id),
+        Err(e) => {
+            tracing::warn!(document_id = %doc.id, error = %e, "Re-indexing drifted document failed");
+            report.failed.push(FailedRepair {
+                document_id: doc.id,
+                error: e.to_string(),
+            });
+        }
+    }
+}
+
+fn compare(
+    user_id: UserId,
+    documents: &[Document],
+    chunk_counts: &HashMap<DocumentId, usize>,
+    report: &mut ConsistencyReport,
+) {
+    for doc in documents {
+        // Documents mid-ingest are expected to disagree until the write lands
+        if doc.status != DocumentStatus::Indexed {
+            continue;
+        }
+
+        // A version that produced no chunks is indexed with a count of 0
+        match chunk_counts.get(&doc.id).copied().unwrap_or(0) {
+            actual if actual == doc.chunk_count as usize => {}
+            0 => report.empty_documents.push(doc.id),
+            actual => report.count_mismatches.push(CountMismatch {
+                user_id,
+                document_id: doc.id,
+                title: doc.title.clone(),
+                expected: doc.chunk_count,
+                actual,
+            }),
+        }
+    }
+
+    let known: HashSet<DocumentId> = documents.iter().map(|d| d.id).collect();
+    let mut orphans: Vec<_> = chunk_counts
+        .iter()
+        .filter(|(id, _)| !known.contains(id))
+        .map(|(&document_id, &chunk_count)| OrphanedChunks {
+            user_id,
+            document_id,
+            chunk_count,
+        })
+        .collect();
+    orphans.sort_by_key(|o| o.document_id.0);
+    report.orphaned_chunks.extend(orphans);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn document(chunk_count: i32, status: DocumentStatus) -> Document {
+        Document {
+            id: DocumentId::new(),
+            user_id: UserId::new(),
+            source_type: SourceType::PdfUpload,
+            source_id: "handbook".to_string(),
+            title: "Handbook".to_string(),
+            source_url: None,
+            content_hash: String::new(),
+            chunk_count,
+            mime_type: None,
+            metadata: serde_json::json!({}),
+            chunking_profile: None,
+            status,
+            error_message: None,
+            indexed_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_compare_finds_drift() {
+        let user_id = UserId::new();
+        let ok = document(3, DocumentStatus::Indexed);
+        let short = document(5, DocumentStatus::Indexed);
+        let empty = document(2, DocumentStatus::Indexed);
+        let in_flight = document(4, DocumentStatus::Indexing);
+        // Indexed from a version that produced no chunks
+        let cleared = document(0, DocumentStatus::Indexed);
+        let orphan = DocumentId::new();
+
+        // Chunks of a document still being written aren't orphans
+        let counts = HashMap::from([(ok.id, 3), (short.id, 2), (in_flight.id, 1), (orphan, 7)]);
+        let mut report = ConsistencyReport::default();
+        compare(
+            user_id,
+            &[ok, short.clone(), empty.clone(), in_flight, cleared],
+            &counts,
+            &mut report,
+        );
+
+        assert_eq!(report.empty_documents, vec![empty.id]);
+        assert_eq!(report.count_mismatches.len(), 1);
+        assert_eq!(report.count_mismatches[0].document_id, short.id);
+        assert_eq!(report.count_mismatches[0].actual, 2);
+        assert_eq!(report.orphaned_chunks.len(), 1);
+        assert_eq!(report.orphaned_chunks[0].document_id, orphan);
+        assert_eq!(report.orphaned_chunks[0].chunk_count, 7);
+        assert!(!report.is_consistent());
+    }
+}
@@ -1,5 +1,8 @@
+use cortex_common::chunking::ChunkingProfile;
 use cortex_common::types::*;
 
+use crate::reindex::ReindexOptions;
+
 /// A job to be executed by the worker pool.
 #[derive(Debug, Clone)]
 pub enum JobPayload {
@@ -7,8 +10,13 @@ pub enum JobPayload {
     FileUpload {
         job_id: JobId,
         user_id: UserId,
+        /// Stable identity of the upload; a new version replaces the document with the same id.
+        source_id: String,
         filename: String,
+        collection: Option<String>,
         content: String,
+        /// Overrides the source-type default chunking profile.
+        chunking_profile: Option<ChunkingProfile>,
     },
     /// Run a full sync for a connector.
     FullSync {
@@ -22,6 +30,19 @@ pub enum JobPayload {
         user_id: UserId,
         connector_id: ConnectorId,
     },
+    /// Compare Postgres with the vector store, for one user or all of them.
+    Reconcile {
+        job_id: JobId,
+        user_id: Option<UserId>,
+        /// Delete orphaned chunks and re-index drifted documents.
+        repair: bool,
+    },
+    /// Re-chunk and re-embed a user's documents.
+    Reindex {
+        job_id: JobId,
+        user_id: UserId,
+        options: ReindexOptions,
+    },
 }
 
 impl JobPayload {
@@ -30,6 +51,8 @@ impl JobPayload {
             JobPayload::FileUpload { job_id, .. } => *job_id,
             JobPayload::FullSync { job_id, .. } => *job_id,
             JobPayload::IncrementalSync { job_id, .. } => *job_id,
+            JobPayload::Reconcile { job_id, .. } => *job_id,
+            JobPayload::Reindex { job_id, .. } => *job_id,
         }
     }
 }
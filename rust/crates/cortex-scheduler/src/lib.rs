@@ -1,4 +1,8 @@
+pub mod consistency;
 pub mod jobs;
+pub mod reconciler;
+pub mod reindex;
 pub mod worker;
 
+pub use reconciler::Reconciler;
 pub use worker::WorkerPool;
@@ -0,0 +1,59 @@
+use cortex_ingestion::pipeline::IngestionPipeline;
+use cortex_store::postgres::PostgresStore;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Give up on a document after this many retries.
+const MAX_ATTEMPTS: i32 = 5;
+/// Entries retried per pass.
+const BATCH_SIZE: usize = 50;
+
+/// Background task that repairs half-written documents by replaying their
+/// ingest outbox entries.
+pub struct Reconciler {
+    pipeline: Arc<IngestionPipeline>,
+    postgres: PostgresStore,
+}
+
+impl Reconciler {
+    pub fn new(pipeline: Arc<IngestionPipeline>, postgres: PostgresStore) -> Self {
+        Self { pipeline, postgres }
+    }
+
+    /// Run a reconciliation pass every `interval` in the background.
+    pub fn spawn(self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.run_once().await {
+                    tracing::error!(error = %e, "Outbox reconciliation failed");
+                }
+            }
+        })
+    }
+
+    /// Retry outbox entries whose lease has run out (a crash or a failed
+    /// write), one at a time so each is leased only while it is retried.
+    /// Returns how many were repaired.
+    pub async fn run_once(&self) -> anyhow::Result<usize> {
+        let mut repaired = 0;
+        for _ in 0..BATCH_SIZE {
+            let Some(entry) = self.postgres.claim_outbox_entry(MAX_ATTEMPTS).await? else {
+                break;
+            };
+            let document_id = entry.document_id;
+            match self.pipeline.retry_outbox(&entry).await {
+                Ok(_) => {
+                    repaired += 1;
+                    tracing::info!(%document_id, attempts = entry.attempts, "Repaired half-written document");
+                }
+                Err(e) => {
+                    tracing::warn!(%document_id, error = %e, "Retrying half-written document failed");
+                }
+            }
+        }
+
+        Ok(repaired)
+    }
+}
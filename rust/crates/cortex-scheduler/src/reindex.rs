@@ -0,0 +1,258 @@
+use cortex_common::chunking::ChunkingProfile;
+use cortex_common::types::*;
+use cortex_ingestion::pipeline::{IngestResult, IngestionError, IngestionPipeline};
+use cortex_store::models::{Document, ReindexedDocument, SearchIndex};
+use cortex_store::postgres::PostgresStore;
+use cortex_store::vector::{VectorStore, DEFAULT_INDEX};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// What to rebuild and how.
+#[derive(Debug, Clone, Default)]
+pub struct ReindexOptions {
+    /// Embed with this model instead of the user's current one.
+    pub embedding_model: Option<String>,
+    /// Re-chunk with this profile instead of each document's current one.
+    pub chunking_profile: Option<ChunkingProfile>,
+    /// Only rebuild documents of this source type.
+    pub source_type: Option<SourceType>,
+    /// Only rebuild these documents.
+    pub document_ids: Option<Vec<DocumentId>>,
+}
+
+impl ReindexOptions {
+    fn is_filtered(&self) -> bool {
+        self.source_type.is_some() || self.document_ids.is_some()
+    }
+
+    fn matches(&self, doc: &Document) -> bool {
+        self.source_type.is_none_or(|t| doc.source_type == t)
+            && self
+                .document_ids
+                .as_ref()
+                .is_none_or(|ids| ids.contains(&doc.id))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReindexReport {
+    /// The class the user's chunks live in after the job.
+    pub class_name: String,
+    pub embedding_model: Option<String>,
+    /// Whether a shadow class was built and swapped in.
+    pub swapped: bool,
+    pub documents_reindexed: usize,
+    pub chunk_count: usize,
+    /// Documents left as they were because their original text wasn't retained.
+    pub skipped_without_content: Vec<DocumentId>,
+}
+
+/// Re-chunk and re-embed a user's documents from their retained text.
+///
+/// A new embedding model puts vectors in a different space, so every document
+/// is rebuilt into a fresh shadow class and the user is switched over in one
+/// transaction once all of them are written; search never mixes the two. A
+/// document without retained text fails the job, since it would be lost in the
+/// swap. Without a model change, documents are rebuilt in place one at a time,
+/// each swapping its own chunk set, and documents without text are skipped.
+pub async fn run(
+    pipeline: &IngestionPipeline,
+    postgres: &PostgresStore,
+    vector_store: &dyn VectorStore,
+    job_id: JobId,
+    user_id: UserId,
+    options: ReindexOptions,
+) -> anyhow::Result<ReindexReport> {
+    let current = postgres.get_search_index(user_id).await?;
+    let documents: Vec<Document> = postgres
+        .list_all_documents(user_id)
+        .await?
+        .into_iter()
+        .filter(|doc| doc.status != DocumentStatus::Indexing && options.matches(doc))
+        .collect();
+
+    let changes_model = options
+        .embedding_model
+        .as_ref()
+        .is_some_and(|model| current.embedding_model.as_ref() != Some(model));
+
+    if !changes_model {
+        return reindex_in_place(pipeline, postgres, job_id, current, documents, options).await;
+    }
+
+    if options.is_filtered() {
+        anyhow::bail!("changing the embedding model requires reindexing all of a user's documents");
+    }
+
+    let shadow = SearchIndex {
+        class_name: format!("Chunk_{}", Uuid::new_v4().simple()),
+        embedding_model: options.embedding_model.clone(),
+    };
+    let shadow_store = vector_store.with_index(&shadow.class_name);
+    shadow_store.ensure_index().await?;
+
+    let started_at = chrono::Utc::now();
+    let rebuilt = async {
+        let mut rebuilt =
+            rebuild_all(pipeline, postgres, job_id, &documents, &options, &shadow).await?;
+        // Documents ingested while the shadow was being built went to the old
+        // class; rebuild them too so none are missing once it is swapped in
+        let caught_up_at = chrono::Utc::now();
+        let caught_up =
+            catch_up(pipeline, postgres, user_id, started_at, &options, &shadow).await?;
+        rebuilt.retain(|doc| !caught_up.iter().any(|c| c.document_id == doc.document_id));
+        rebuilt.extend(caught_up);
+        anyhow::Ok((rebuilt, caught_up_at))
+    }
+    .await;
+    let (rebuilt, caught_up_at) = match rebuilt {
+        Ok(rebuilt) => rebuilt,
+        Err(e) => {
+            if let Err(drop_err) = shadow_store.drop_index().await {
+                tracing::warn!(class = %shadow.class_name, error = %drop_err, "Failed to drop shadow class");
+            }
+            return Err(e);
+        }
+    };
+
+    postgres.commit_reindex(user_id, &shadow, &rebuilt).await?;
+    tracing::info!(%user_id, class = %shadow.class_name, "Swapped in rebuilt search index");
+
+    // Anything ingested between the catch-up and the swap still went to the
+    // old class; new ingests now go to the shadow. The swap has happened, so
+    // failures here are flagged for a later re-index instead of failing the job
+    if let Err(e) = catch_up_after_swap(pipeline, postgres, user_id, caught_up_at).await {
+        tracing::warn!(%user_id, error = %e, "Failed to catch up on documents ingested during the swap");
+    }
+
+    // The old chunks are unreachable now; the default index is shared
+    let old_store = vector_store.with_index(&current.class_name);
+    let cleanup = if current.class_name == DEFAULT_INDEX {
+        old_store.delete_chunks_by_user(user_id).await
+    } else {
+        old_store.drop_index().await
+    };
+    if let Err(e) = cleanup {
+        tracing::warn!(class = %current.class_name, error = %e, "Failed to clean up old search index");
+    }
+
+    Ok(ReindexReport {
+        class_name: shadow.class_name,
+        embedding_model: shadow.embedding_model,
+        swapped: true,
+        documents_reindexed: rebuilt.len(),
+        chunk_count: rebuilt.iter().map(|d| d.chunk_count as usize).sum(),
+        skipped_without_content: Vec::new(),
+    })
+}
+
+async fn reindex_in_place(
+    pipeline: &IngestionPipeline,
+    postgres: &PostgresStore,
+    job_id: JobId,
+    index: SearchIndex,
+    documents: Vec<Document>,
+    options: ReindexOptions,
+) -> anyhow::Result<ReindexReport> {
+    let total = documents.len() as i32;
+    let mut chunk_count = 0;
+    let mut skipped = Vec::new();
+
+    for (i, doc) in documents.iter().enumerate() {
+        match pipeline
+            .reindex_document(doc, options.chunking_profile.clone())
+            .await
+        {
+            Ok(IngestResult::Indexed { chunk_count: n }) => chunk_count += n,
+            Ok(IngestResult::Skipped) => {}
+            Err(IngestionError::MissingContent(id)) => skipped.push(id),
+            Err(e) => return Err(e.into()),
+        }
+        let _ = postgres
+            .update_job_progress(job_id, i as i32 + 1, total)
+            .await;
+    }
+
+    Ok(ReindexReport {
+        class_name: index.class_name,
+        embedding_model: index.embedding_model,
+        swapped: false,
+        documents_reindexed: documents.len() - skipped.len(),
+        chunk_count,
+        skipped_without_content: skipped,
+    })
+}
+
+/// Re-index into the swapped-in index the documents changed since `since`.
+/// Documents still being ingested are left to their own write. Documents
+/// that fail are flagged as needing a re-index.
+async fn catch_up_after_swap(
+    pipeline: &IngestionPipeline,
+    postgres: &PostgresStore,
+    user_id: UserId,
+    since: chrono::DateTime<chrono::Utc>,
+) -> anyhow::Result<()> {
+    let mut failed = Vec::new();
+    for doc in postgres.list_all_documents(user_id).await? {
+        if doc.updated_at <= since || doc.status == DocumentStatus::Indexing {
+            continue;
+        }
+        if let Err(e) = pipeline.reindex_document(&doc, None).await {
+            tracing::warn!(document_id = %doc.id, error = %e, "Re-indexing document after swap failed");
+            failed.push(doc.id);
+        }
+    }
+    if !failed.is_empty() {
+        postgres
+            .mark_needs_reindex(&failed, "ingested while the search index was swapped")
+            .await?;
+    }
+    Ok(())
+}
+
+/// Rebuild into `shadow` the documents changed since `since`.
+async fn catch_up(
+    pipeline: &IngestionPipeline,
+    postgres: &PostgresStore,
+    user_id: UserId,
+    since: chrono::DateTime<chrono::Utc>,
+    options: &ReindexOptions,
+    shadow: &SearchIndex,
+) -> anyhow::Result<Vec<ReindexedDocument>> {
+    let mut rebuilt = Vec::new();
+    for doc in postgres.list_all_documents(user_id).await? {
+        if doc.updated_at > since && doc.status != DocumentStatus::Indexing {
+            rebuilt.push(
+                pipeline
+                    .rebuild_into(&doc, options.chunking_profile.clone(), shadow)
+                    .await?,
+            );
+        }
+    }
+    Ok(rebuilt)
+}
+
+async fn rebuild_all(
+    pipeline: &IngestionPipeline,
+    postgres: &PostgresStore,
+    job_id: JobId,
+    documents: &[Document],
+    options: &ReindexOptions,
+    shadow: &SearchIndex,
+) -> anyhow::Result<Vec<ReindexedDocument>> {
+    let total = documents.len() as i32;
+    let mut rebuilt = Vec::with_capacity(documents.len());
+
+    for (i, doc) in documents.iter().enumerate() {
+        rebuilt.push(
+            pipeline
+                .rebuild_into(doc, options.chunking_profile.clone(), shadow)
+                .await?,
+        );
+        let _ = postgres
+            .update_job_progress(job_id, i as i32 + 1, total)
+            .await;
+    }
+
+    Ok(rebuilt)
+}
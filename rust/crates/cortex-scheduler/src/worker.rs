@@ -2,10 +2,12 @@ use cortex_common::types::*;
 use cortex_connectors::pdf_upload;
 use cortex_ingestion::pipeline::IngestionPipeline;
 use cortex_store::postgres::PostgresStore;
+use cortex_store::vector::VectorStore;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
 use crate::jobs::JobPayload;
+use crate::{consistency, reindex};
 
 /// Async worker pool that processes ingestion jobs.
 pub struct WorkerPool {
@@ -18,6 +20,7 @@ impl WorkerPool {
         concurrency: usize,
         pipeline: Arc<IngestionPipeline>,
         postgres: PostgresStore,
+        vector_store: Arc<dyn VectorStore>,
     ) -> Self {
         let (tx, rx) = mpsc::channel::<JobPayload>(256);
         let rx = Arc::new(tokio::sync::Mutex::new(rx));
@@ -26,6 +29,7 @@ impl WorkerPool {
             let rx = rx.clone();
             let pipeline = pipeline.clone();
             let postgres = postgres.clone();
+            let vector_store = vector_store.clone();
 
             tokio::spawn(async move {
                 loop {
@@ -47,7 +51,8 @@ impl WorkerPool {
                         .update_job_status(job_id, JobStatus::Running, None)
                         .await;
 
-                    let result = process_job(&pipeline, &postgres, job).await;
+                    let result =
+                        process_job(&pipeline, &postgres, vector_store.as_ref(), job).await;
 
                     match result {
                         Ok(()) => {
@@ -80,24 +85,88 @@ impl WorkerPool {
 async fn process_job(
     pipeline: &IngestionPipeline,
     postgres: &PostgresStore,
+    vector_store: &dyn VectorStore,
     job: JobPayload,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     match job {
         JobPayload::FileUpload {
             job_id,
             user_id,
+            source_id,
             filename,
+            collection,
             content,
+            chunking_profile,
         } => {
-            let raw_doc = pdf_upload::create_from_text(&filename, content);
+            let raw_doc =
+                pdf_upload::create_from_text(source_id, &filename, collection.as_deref(), content);
             let _ = postgres.update_job_progress(job_id, 0, 1).await;
-            pipeline.ingest(raw_doc, user_id).await?;
+            pipeline.ingest(raw_doc, user_id, chunking_profile).await?;
             let _ = postgres.update_job_progress(job_id, 1, 1).await;
             Ok(())
         }
-        JobPayload::FullSync { .. } | JobPayload::IncrementalSync { .. } => {
+        JobPayload::Reconcile {
+            job_id,
+            user_id,
+            repair,
+        } => {
+            let report =
+                consistency::check(pipeline, postgres, vector_store, user_id, repair).await?;
+            postgres
+                .set_job_result(job_id, &serde_json::to_value(&report)?)
+                .await?;
+
+            tracing::info!(
+                %job_id,
+                orphaned = report.orphaned_chunks.len(),
+                mismatched = report.count_mismatches.len(),
+                empty = report.empty_documents.len(),
+                reindexed = report.reindexed.len(),
+                failed = report.failed.len(),
+                repair,
+                "Reconciliation finished"
+            );
+            Ok(())
+        }
+        JobPayload::Reindex {
+            job_id,
+            user_id,
+            options,
+        } => {
+            let report =
+                reindex::run(pipeline, postgres, vector_store, job_id, user_id, options).await?;
+            postgres
+                .set_job_result(job_id, &serde_json::to_value(&report)?)
+                .await?;
+
+            tracing::info!(
+                %job_id,
+                %user_id,
+                documents = report.documents_reindexed,
+                swapped = report.swapped,
+                "Reindex finished"
+            );
+            Ok(())
+        }
+        JobPayload::FullSync { connector_id, .. }
+        | JobPayload::IncrementalSync { connector_id, .. } => {
+            let connector = postgres
+                .get_connector(connector_id)
+                .await?
+                .ok_or_else(|| format!("connector {connector_id} not found"))?;
+            // Documents the sync ingests use the connector's profile override
+            let chunking_profile = connector
+                .chunking_profile
+                .as_ref()
+                .map(|selector| pipeline.resolve_profile(selector))
+                .transpose()?;
+
             // Connector sync will be implemented in Phase 3
-            tracing::warn!("Connector sync not yet implemented");
+            tracing::warn!(
+                %connector_id,
+                chunking_profile = ?chunking_profile.map(|p| p.name),
+                "Connector sync not yet implemented"
+            );
             Ok(())
         }
     }
@@ -0,0 +1,141 @@
+use cortex_common::config::{ContentStoreBackend, ContentStoreConfig};
+use std::path::{Path, PathBuf};
+
+use crate::postgres::PostgresStore;
+
+/// Used by the filesystem backend when no path is configured.
+const DEFAULT_CONTENT_DIR: &str = "data/content";
+
+/// Keeps the original text of each document version, keyed by content hash,
+/// so documents can be reindexed and previewed without the source system.
+#[derive(Clone)]
+pub enum ContentStore {
+    Postgres(PostgresStore),
+    /// Content-addressed files: `<root>/<first two hex chars>/<hash>`.
+    Filesystem(PathBuf),
+    /// Content is not retained.
+    Disabled,
+}
+
+impl ContentStore {
+    pub fn from_config(config: &ContentStoreConfig, postgres: &PostgresStore) -> Self {
+        match config.backend {
+            ContentStoreBackend::Postgres => ContentStore::Postgres(postgres.clone()),
+            ContentStoreBackend::Filesystem => ContentStore::Filesystem(PathBuf::from(
+                config.path.as_deref().unwrap_or(DEFAULT_CONTENT_DIR),
+            )),
+            ContentStoreBackend::None => ContentStore::Disabled,
+        }
+    }
+
+    pub async fn put(&self, content_hash: &str, content: &str) -> Result<(), ContentStoreError> {
+        match self {
+            ContentStore::Postgres(postgres) => {
+                postgres.put_document_content(content_hash, content).await?;
+            }
+            ContentStore::Filesystem(root) => {
+                let path = blob_path(root, content_hash)?;
+                if tokio::fs::try_exists(&path).await? {
+                    return Ok(());
+                }
+                if let Some(dir) = path.parent() {
+                    tokio::fs::create_dir_all(dir).await?;
+                }
+                // Write then rename, so readers never see a partial file
+                let tmp = path.with_extension(format!("tmp-{}", uuid::Uuid::new_v4().simple()));
+                tokio::fs::write(&tmp, content).await?;
+                tokio::fs::rename(&tmp, &path).await?;
+            }
+            ContentStore::Disabled => {}
+        }
+        Ok(())
+    }
+
+    pub async fn get(&self, content_hash: &str) -> Result<Option<String>, ContentStoreError> {
+        match self {
+            ContentStore::Postgres(postgres) => {
+                Ok(postgres.get_document_content(content_hash).await?)
+            }
+            ContentStore::Filesystem(root) => {
+                match tokio::fs::read_to_string(blob_path(root, content_hash)?).await {
+                    Ok(content) => Ok(Some(content)),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            ContentStore::Disabled => Ok(None),
+        }
+    }
+
+    /// Delete the content of a version once no document refers to it, as
+    /// after the last document with it is deleted or moves to a new version.
+    /// `documents` is where references are looked up. Returns whether it was
+    /// deleted.
+    pub async fn release(
+        &self,
+        documents: &PostgresStore,
+        content_hash: &str,
+    ) -> Result<bool, ContentStoreError> {
+        match self {
+            ContentStore::Postgres(postgres) => {
+                Ok(postgres.delete_unreferenced_content(content_hash).await?)
+            }
+            ContentStore::Filesystem(root) => {
+                if documents.content_hash_in_use(content_hash).await? {
+                    return Ok(false);
+                }
+                match tokio::fs::remove_file(blob_path(root, content_hash)?).await {
+                    Ok(()) => Ok(true),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            ContentStore::Disabled => Ok(false),
+        }
+    }
+}
+
+fn blob_path(root: &Path, content_hash: &str) -> Result<PathBuf, ContentStoreError> {
+    // Hashes are hex digests; anything else could escape the root directory
+    if content_hash.len() < 3 || !content_hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ContentStoreError::InvalidHash(content_hash.to_string()));
+    }
+    Ok(root.join(&content_hash[..2]).join(content_hash))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ContentStoreError {
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid content hash: {0}")]
+    InvalidHash(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_filesystem_round_trip() {
+        let root = std::env::temp_dir().join(format!("cortex-content-{}", uuid::Uuid::new_v4()));
+        let store = ContentStore::Filesystem(root.clone());
+        let hash = "ab".repeat(32);
+
+        assert_eq!(store.get(&hash).await.unwrap(), None);
+        store.put(&hash, "original text").await.unwrap();
+        assert_eq!(
+            store.get(&hash).await.unwrap().as_deref(),
+            Some("original text")
+        );
+        assert!(root.join("ab").join(&hash).exists());
+
+        assert!(matches!(
+            store.get("../../etc/passwd").await,
+            Err(ContentStoreError::InvalidHash(_))
+        ));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+//! Facet counts over the chunks a search matches, for filter sidebars.
+//!
+//! Backends count the values they group by and feed them to a
+//! `FacetCounter`, which keeps the requested facets and orders them.
+
+use chrono::{DateTime, Datelike, Days, NaiveDate, Utc};
+use cortex_common::types::DocumentId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::filter::FilterFields;
+
+/// Most values returned per facet, besides date buckets.
+const MAX_FACET_VALUES: usize = 50;
+/// Most metadata keys one request may count.
+const MAX_METADATA_FACETS: usize = 10;
+
+/// Which facets to count.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct FacetRequest {
+    #[serde(default)]
+    pub source_type: bool,
+    #[serde(default)]
+    pub document: bool,
+    /// Bucket the documents' `indexed_at` by this interval.
+    pub indexed_at: Option<DateInterval>,
+    /// Top-level document metadata keys to count values of.
+    #[serde(default)]
+    pub metadata: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateInterval {
+    Day,
+    /// Weeks starting on Monday.
+    Week,
+    Month,
+    Year,
+}
+
+/// Chunk counts per value; each facet is present only if requested.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FacetCounts {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_type: Option<Vec<FacetCount>>,
+    /// Values are document ids.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document: Option<Vec<FacetCount>>,
+    /// Values are the first day of each bucket, oldest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indexed_at: Option<Vec<FacetCount>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, Vec<FacetCount>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: usize,
+}
+
+impl FacetRequest {
+    pub fn is_empty(&self) -> bool {
+        !self.source_type && !self.document && self.indexed_at.is_none() && self.metadata.is_empty()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.metadata.len() > MAX_METADATA_FACETS {
+            return Err(format!(
+                "at most {MAX_METADATA_FACETS} metadata facets can be counted"
+            ));
+        }
+        if self.metadata.iter().any(String::is_empty) {
+            return Err("metadata facets need a key".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl DateInterval {
+    /// The first day of the bucket `time` falls in.
+    pub fn bucket(self, time: DateTime<Utc>) -> NaiveDate {
+        let date = time.date_naive();
+        match self {
+            DateInterval::Day => date,
+            DateInterval::Week => {
+                let offset = date.weekday().num_days_from_monday();
+                date - Days::new(u64::from(offset))
+            }
+            DateInterval::Month => date.with_day(1).unwrap_or(date),
+            DateInterval::Year => date.with_ordinal(1).unwrap_or(date),
+        }
+    }
+}
+
+/// Adds up grouped counts into the requested facets.
+pub struct FacetCounter<'a> {
+    request: &'a FacetRequest,
+    source_type: HashMap<String, usize>,
+    document: HashMap<String, usize>,
+    indexed_at: BTreeMap<NaiveDate, usize>,
+    metadata: HashMap<String, HashMap<String, usize>>,
+}
+
+impl<'a> FacetCounter<'a> {
+    pub fn new(request: &'a FacetRequest) -> Self {
+        Self {
+            request,
+            source_type: HashMap::new(),
+            document: HashMap::new(),
+            indexed_at: BTreeMap::new(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Count one matched chunk, for stores that scan their chunks.
+    pub fn add_chunk(&mut self, source_type: &str, fields: &FilterFields) {
+        if self.request.source_type {
+            self.add_source_type(source_type, 1);
+        }
+        if self.request.document {
+            self.add_document(fields.document_id, 1);
+        }
+        self.add_indexed_at(fields.indexed_at, 1);
+        for field in fields.metadata_fields {
+            self.add_metadata_field(field, 1);
+        }
+    }
+
+    pub fn add_source_type(&mut self, source_type: &str, count: usize) {
+        *self.source_type.entry(source_type.to_string()).or_default() += count;
+    }
+
+    pub fn add_document(&mut self, document_id: DocumentId, count: usize) {
+        *self.document.entry(document_id.to_string()).or_default() += count;
+    }
+
+    pub fn add_indexed_at(&mut self, indexed_at: DateTime<Utc>, count: usize) {
+        if let Some(interval) = self.request.indexed_at {
+            *self
+                .indexed_at
+                .entry(interval.bucket(indexed_at))
+                .or_default() += count;
+        }
+    }
+
+    /// Count an encoded metadata field (see `filter::metadata_fields`) if its
+    /// key was requested.
+    pub fn add_metadata_field(&mut self, field: &str, count: usize) {
+        let Ok((key, value)) = serde_json::from_str::<(String, String)>(field) else {
+            return;
+        };
+        if self.request.metadata.contains(&key) {
+            *self
+                .metadata
+                .entry(key)
+                .or_default()
+                .entry(value)
+                .or_default() += count;
+        }
+    }
+
+    pub fn finish(self) -> FacetCounts {
+        let request = self.request;
+        let mut metadata = self.metadata;
+        FacetCounts {
+            source_type: request.source_type.then(|| top_values(self.source_type)),
+            document: request.document.then(|| top_values(self.document)),
+            indexed_at: request.indexed_at.map(|_| {
+                self.indexed_at
+                    .into_iter()
+                    .map(|(day, count)| FacetCount {
+                        value: day.to_string(),
+                        count,
+                    })
+                    .collect()
+            }),
+            metadata: request
+                .metadata
+                .iter()
+                .map(|key| {
+                    let counts = metadata.remove(key).unwrap_or_default();
+                    (key.clone(), top_values(counts))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The most frequent values first, ties in value order.
+fn top_values(counts: HashMap<String, usize>) -> Vec<FacetCount> {
+    let mut values: Vec<FacetCount> = counts
+        .into_iter()
+        .map(|(value, count)| FacetCount { value, count })
+        .collect();
+    values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    values.truncate(MAX_FACET_VALUES);
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter;
+    use serde_json::json;
+
+    #[test]
+    fn test_counter_keeps_requested_facets() {
+        let request: FacetRequest = serde_json::from_value(json!({
+            "source_type": true,
+            "indexed_at": "week",
+            "metadata": ["tags"]
+        }))
+        .unwrap();
+        let metadata =
+            filter::metadata_fields(&json!({ "tags": ["hr", "policy"], "owner": "ann" }));
+        let fields = FilterFields {
+            document_id: DocumentId::new(),
+            document_title: "Handbook",
+            section_title: None,
+            // A Wednesday
+            indexed_at: "2024-05-15T10:00:00Z".parse().unwrap(),
+            updated_at: "2024-05-15T10:00:00Z".parse().unwrap(),
+            metadata_fields: &metadata,
+        };
+
+        let mut counter = FacetCounter::new(&request);
+        counter.add_chunk("pdf_upload", &fields);
+        counter.add_chunk("pdf_upload", &fields);
+        counter.add_source_type("notion", 1);
+        let counts = counter.finish();
+
+        let source_types = counts.source_type.unwrap();
+        assert_eq!(source_types[0].value, "pdf_upload");
+        assert_eq!(source_types[0].count, 2);
+        assert!(counts.document.is_none());
+        let weeks = counts.indexed_at.unwrap();
+        assert_eq!(weeks.len(), 1);
+        assert_eq!(weeks[0].value, "2024-05-13");
+        assert_eq!(counts.metadata.len(), 1);
+        assert_eq!(counts.metadata["tags"].len(), 2);
+    }
+}
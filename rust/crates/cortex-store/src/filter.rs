@@ -0,0 +1,252 @@
+use chrono::{DateTime, Utc};
+use cortex_common::types::DocumentId;
+use serde::{Deserialize, Serialize};
+
+use crate::hybrid;
+
+/// Limits that keep a filter cheap to translate and evaluate.
+const MAX_FILTER_DEPTH: usize = 8;
+const MAX_FILTER_CONDITIONS: usize = 64;
+const MAX_FILTER_DOCUMENT_IDS: usize = 1_000;
+
+/// A condition on a user's chunks, combined with `and`, `or` and `not`:
+///
+/// ```json
+/// {"and": [
+///   {"updated_at": {"from": "2024-01-01T00:00:00Z"}},
+///   {"not": {"metadata": {"key": "collection", "value": "drafts"}}},
+///   {"or": [{"title": "employee handbook"}, {"section": "refunds"}]}
+/// ]}
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterExpr {
+    And(Vec<FilterExpr>),
+    Or(Vec<FilterExpr>),
+    Not(Box<FilterExpr>),
+    /// When the document was first indexed.
+    IndexedAt(DateRange),
+    /// When the document was last written.
+    UpdatedAt(DateRange),
+    /// Only chunks of these documents.
+    DocumentIds(Vec<DocumentId>),
+    /// Chunks of any document but these.
+    ExcludeDocumentIds(Vec<DocumentId>),
+    /// Every word of the value appears in the document title.
+    Title(String),
+    /// Every word of the value appears in the section title.
+    Section(String),
+    Metadata(MetadataCondition),
+}
+
+/// `from` is inclusive and `to` exclusive; at least one is required.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DateRange {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// A top-level document metadata entry equals `value`, or contains it if the
+/// entry is an array. Nested objects can't be filtered on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetadataCondition {
+    pub key: String,
+    pub value: MetadataValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MetadataValue {
+    String(String),
+    Number(serde_json::Number),
+    Bool(bool),
+}
+
+impl MetadataCondition {
+    /// The encoded field this condition matches, see `metadata_fields`.
+    pub fn field(&self) -> String {
+        let value = match &self.value {
+            MetadataValue::String(s) => s.clone(),
+            MetadataValue::Number(n) => n.to_string(),
+            MetadataValue::Bool(b) => b.to_string(),
+        };
+        encode_field(&self.key, &value)
+    }
+}
+
+/// The values of a chunk that filters are evaluated against.
+pub struct FilterFields<'a> {
+    pub document_id: DocumentId,
+    pub document_title: &'a str,
+    pub section_title: Option<&'a str>,
+    pub indexed_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// See `metadata_fields`.
+    pub metadata_fields: &'a [String],
+}
+
+impl FilterExpr {
+    /// Reject filters that are empty where a condition is needed, or too
+    /// large to translate.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut conditions = 0;
+        self.validate_at(1, &mut conditions)
+    }
+
+    fn validate_at(&self, depth: usize, conditions: &mut usize) -> Result<(), String> {
+        if depth > MAX_FILTER_DEPTH {
+            return Err(format!("filter is nested deeper than {MAX_FILTER_DEPTH}"));
+        }
+        *conditions += 1;
+        if *conditions > MAX_FILTER_CONDITIONS {
+            return Err(format!(
+                "filter has more than {MAX_FILTER_CONDITIONS} conditions"
+            ));
+        }
+
+        match self {
+            FilterExpr::And(operands) | FilterExpr::Or(operands) => {
+                if operands.is_empty() {
+                    return Err("and/or need at least one operand".to_string());
+                }
+                for operand in operands {
+                    operand.validate_at(depth + 1, conditions)?;
+                }
+            }
+            FilterExpr::Not(operand) => operand.validate_at(depth + 1, conditions)?,
+            FilterExpr::IndexedAt(range) | FilterExpr::UpdatedAt(range) => {
+                if range.from.is_none() && range.to.is_none() {
+                    return Err("date ranges need `from` or `to`".to_string());
+                }
+            }
+            FilterExpr::DocumentIds(ids) | FilterExpr::ExcludeDocumentIds(ids) => {
+                if ids.is_empty() || ids.len() > MAX_FILTER_DOCUMENT_IDS {
+                    return Err(format!(
+                        "document id lists need 1 to {MAX_FILTER_DOCUMENT_IDS} ids"
+                    ));
+                }
+            }
+            FilterExpr::Title(value) | FilterExpr::Section(value) => {
+                if hybrid::tokenize(value).is_empty() {
+                    return Err("title and section filters need at least one word".to_string());
+                }
+            }
+            FilterExpr::Metadata(condition) => {
+                if condition.key.is_empty() {
+                    return Err("metadata filters need a key".to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluate the filter in process, for stores without a query language.
+    pub fn matches(&self, fields: &FilterFields) -> bool {
+        match self {
+            FilterExpr::And(operands) => operands.iter().all(|f| f.matches(fields)),
+            FilterExpr::Or(operands) => operands.iter().any(|f| f.matches(fields)),
+            FilterExpr::Not(operand) => !operand.matches(fields),
+            FilterExpr::IndexedAt(range) => range.contains(fields.indexed_at),
+            FilterExpr::UpdatedAt(range) => range.contains(fields.updated_at),
+            FilterExpr::DocumentIds(ids) => ids.contains(&fields.document_id),
+            FilterExpr::ExcludeDocumentIds(ids) => !ids.contains(&fields.document_id),
+            FilterExpr::Title(value) => contains_words(fields.document_title, value),
+            FilterExpr::Section(value) => {
+                contains_words(fields.section_title.unwrap_or_default(), value)
+            }
+            FilterExpr::Metadata(condition) => fields.metadata_fields.contains(&condition.field()),
+        }
+    }
+}
+
+impl DateRange {
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        self.from.is_none_or(|from| time >= from) && self.to.is_none_or(|to| time < to)
+    }
+}
+
+/// Whether every word of `words` appears in `text`, compared as tokens.
+pub fn contains_words(text: &str, words: &str) -> bool {
+    let tokens = hybrid::tokenize(text);
+    hybrid::tokenize(words).iter().all(|w| tokens.contains(w))
+}
+
+/// Flatten a document's metadata into exact-match fields, one per top-level
+/// scalar and per scalar array element. Stores index these so metadata
+/// conditions don't need to parse JSON.
+pub fn metadata_fields(metadata: &serde_json::Value) -> Vec<String> {
+    let Some(entries) = metadata.as_object() else {
+        return Vec::new();
+    };
+
+    let mut fields = Vec::new();
+    for (key, value) in entries {
+        let values = match value {
+            serde_json::Value::Array(items) => items.iter().collect(),
+            other => vec![other],
+        };
+        for value in values {
+            let value = match value {
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Number(n) => n.to_string(),
+                serde_json::Value::Bool(b) => b.to_string(),
+                _ => continue,
+            };
+            fields.push(encode_field(key, &value));
+        }
+    }
+    fields
+}
+
+/// A JSON pair, so keys and values containing separators stay unambiguous.
+fn encode_field(key: &str, value: &str) -> String {
+    serde_json::json!([key, value]).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_filter_parses_and_matches() {
+        let filter: FilterExpr = serde_json::from_value(json!({
+            "and": [
+                { "updated_at": { "from": "2024-01-01T00:00:00Z" } },
+                { "not": { "metadata": { "key": "collection", "value": "drafts" } } },
+                { "or": [{ "title": "Employee handbook" }, { "section": "refunds" }] }
+            ]
+        }))
+        .unwrap();
+        filter.validate().unwrap();
+
+        let metadata = metadata_fields(&json!({ "collection": "policies", "tags": ["hr", 2] }));
+        assert!(metadata.contains(&r#"["tags","2"]"#.to_string()));
+        let fields = FilterFields {
+            document_id: DocumentId::new(),
+            document_title: "The employee handbook (2024)",
+            section_title: None,
+            indexed_at: "2023-06-01T00:00:00Z".parse().unwrap(),
+            updated_at: "2024-03-01T00:00:00Z".parse().unwrap(),
+            metadata_fields: &metadata,
+        };
+        assert!(filter.matches(&fields));
+
+        let drafts = metadata_fields(&json!({ "collection": "drafts" }));
+        assert!(!filter.matches(&FilterFields {
+            metadata_fields: &drafts,
+            ..fields
+        }));
+    }
+
+    #[test]
+    fn test_validate_rejects_empty_conditions() {
+        assert!(FilterExpr::Or(vec![]).validate().is_err());
+        assert!(FilterExpr::Title("  -- ".to_string()).validate().is_err());
+        let range = DateRange {
+            from: None,
+            to: None,
+        };
+        assert!(FilterExpr::IndexedAt(range).validate().is_err());
+    }
+}
@@ -0,0 +1,305 @@
+//! Scoring for backends that run keyword and vector search separately and
+//! fuse the results themselves, matching Weaviate's hybrid search, and the
+//! fusion methods callers can pick per request.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// BM25 parameters, the same defaults as Weaviate.
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+
+/// Rank offset of reciprocal rank fusion, the usual value from Cormack et al.
+const RRF_K: f32 = 60.0;
+
+/// How keyword and vector results retrieved separately are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FusionMethod {
+    /// Reciprocal rank fusion: each list adds `weight / (60 + rank)`, so
+    /// only positions matter, not how the retrievers score.
+    Rrf,
+    /// Min-max normalized scores blended by weight, like Weaviate.
+    RelativeScore,
+}
+
+/// What one retriever added to a fused result.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Contribution {
+    /// 1-based position in the retriever's results.
+    pub rank: usize,
+    /// The retriever's own score.
+    pub score: f32,
+    /// Its share of the fused score.
+    pub fused: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Contributions {
+    pub keyword: Option<Contribution>,
+    pub vector: Option<Contribution>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fused<K> {
+    pub key: K,
+    pub score: f32,
+    pub contributions: Contributions,
+}
+
+/// Lowercased alphanumeric terms, roughly Weaviate's `word` tokenization.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// BM25 score of each document against `query`, over the given corpus.
+/// Documents without any query term score 0.
+pub fn bm25_scores(query: &str, documents: &[Vec<String>]) -> Vec<f32> {
+    let mut terms = tokenize(query);
+    terms.sort();
+    terms.dedup();
+    if terms.is_empty() || documents.is_empty() {
+        return vec![0.0; documents.len()];
+    }
+
+    let n = documents.len() as f32;
+    let avg_len = documents.iter().map(Vec::len).sum::<usize>() as f32 / n;
+    let doc_freq: HashMap<&str, usize> = terms
+        .iter()
+        .map(|term| {
+            let df = documents
+                .iter()
+                .filter(|doc| doc.iter().any(|t| t == term))
+                .count();
+            (term.as_str(), df)
+        })
+        .collect();
+
+    documents
+        .iter()
+        .map(|doc| {
+            let len = doc.len() as f32;
+            terms
+                .iter()
+                .map(|term| {
+                    let tf = doc.iter().filter(|t| *t == term).count() as f32;
+                    if tf == 0.0 {
+                        return 0.0;
+                    }
+                    let df = doc_freq[term.as_str()] as f32;
+                    let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+                    let norm = 1.0 - BM25_B + BM25_B * len / avg_len.max(1.0);
+                    idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * norm)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// Weaviate's relative score fusion: each result list is min-max normalized
+/// to [0, 1], then the lists are combined as `alpha * vector + (1 - alpha) *
+/// keyword`. A result missing from one list gets 0 from it. Returned best
+/// first.
+pub fn relative_score_fusion<K: Copy + Eq + Hash>(
+    keyword: &[(K, f32)],
+    vector: &[(K, f32)],
+    alpha: f32,
+) -> Vec<(K, f32)> {
+    fuse(keyword, vector, FusionMethod::RelativeScore, alpha)
+        .into_iter()
+        .map(|fused| (fused.key, fused.score))
+        .collect()
+}
+
+/// Fuse two result lists, each best first, weighting the vector list by
+/// `alpha` and the keyword list by `1 - alpha`. Returned best first, with
+/// what each list contributed.
+pub fn fuse<K: Copy + Eq + Hash>(
+    keyword: &[(K, f32)],
+    vector: &[(K, f32)],
+    method: FusionMethod,
+    alpha: f32,
+) -> Vec<Fused<K>> {
+    let alpha = alpha.clamp(0.0, 1.0);
+    fuse_weighted(&[(keyword, 1.0 - alpha), (vector, alpha)], method)
+        .into_iter()
+        .map(|(key, score, contributions)| Fused {
+            key,
+            score,
+            contributions: Contributions {
+                keyword: contributions[0],
+                vector: contributions[1],
+            },
+        })
+        .collect()
+}
+
+/// Fuse any number of result lists weighted equally, as for the searches of
+/// several rewritten queries. Returned best first.
+pub fn fuse_many<K: Copy + Eq + Hash>(
+    lists: &[&[(K, f32)]],
+    method: FusionMethod,
+) -> Vec<(K, f32)> {
+    let lists: Vec<(&[(K, f32)], f32)> = lists.iter().map(|&list| (list, 1.0)).collect();
+    fuse_weighted(&lists, method)
+        .into_iter()
+        .map(|(key, score, _)| (key, score))
+        .collect()
+}
+
+/// Fuse weighted result lists, best first. Each result comes with what every
+/// list contributed to it, in the order of `lists`. Ties go to the result
+/// ranked highest in any list, then to the one seen first, so the order (and
+/// with it pagination) is stable across calls.
+fn fuse_weighted<K: Copy + Eq + Hash>(
+    lists: &[(&[(K, f32)], f32)],
+    method: FusionMethod,
+) -> Vec<(K, f32, Vec<Option<Contribution>>)> {
+    // Results in the order first seen, and where each key's result is
+    let mut fused: Vec<(K, f32, Vec<Option<Contribution>>)> = Vec::new();
+    let mut positions: HashMap<K, usize> = HashMap::new();
+    for (list, &(results, weight)) in lists.iter().enumerate() {
+        let shares: Vec<f32> = match method {
+            FusionMethod::Rrf => (1..=results.len())
+                .map(|rank| weight / (RRF_K + rank as f32))
+                .collect(),
+            FusionMethod::RelativeScore => normalize(results)
+                .into_iter()
+                .map(|(_, score)| weight * score)
+                .collect(),
+        };
+        for (i, (&(key, score), share)) in results.iter().zip(shares).enumerate() {
+            let position = *positions.entry(key).or_insert_with(|| {
+                fused.push((key, 0.0, vec![None; lists.len()]));
+                fused.len() - 1
+            });
+            let (_, total, contributions) = &mut fused[position];
+            *total += share;
+            contributions[list] = Some(Contribution {
+                rank: i + 1,
+                score,
+                fused: share,
+            });
+        }
+    }
+
+    let best_rank = |contributions: &[Option<Contribution>]| {
+        contributions.iter().flatten().map(|c| c.rank).min()
+    };
+    // A stable sort, so full ties keep the order first seen
+    fused.sort_by(|a, b| {
+        b.1.total_cmp(&a.1)
+            .then_with(|| best_rank(&a.2).cmp(&best_rank(&b.2)))
+    });
+    fused
+}
+
+fn normalize<K: Copy>(results: &[(K, f32)]) -> Vec<(K, f32)> {
+    let min = results.iter().map(|r| r.1).fold(f32::INFINITY, f32::min);
+    let max = results
+        .iter()
+        .map(|r| r.1)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let range = max - min;
+    results
+        .iter()
+        .map(|&(key, score)| {
+            // A single result, or all tied, counts as a full match
+            let score = if range > 0.0 {
+                (score - min) / range
+            } else {
+                1.0
+            };
+            (key, score)
+        })
+        .collect()
+}
+
+/// The `limit` best entries, best first.
+pub fn top_k<K: Copy>(scores: impl IntoIterator<Item = (K, f32)>, limit: usize) -> Vec<(K, f32)> {
+    let mut scores: Vec<(K, f32)> = scores.into_iter().collect();
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scores.truncate(limit);
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_score_fusion_weights_by_alpha() {
+        let keyword = [(1, 8.0), (2, 4.0), (3, 0.0)];
+        let vector = [(3, 0.9), (2, 0.8), (1, 0.1)];
+
+        let vector_only = relative_score_fusion(&keyword, &vector, 1.0);
+        assert_eq!(vector_only[0], (3, 1.0));
+
+        let keyword_only = relative_score_fusion(&keyword, &vector, 0.0);
+        assert_eq!(keyword_only[0], (1, 1.0));
+
+        // 2 is close to the top of both lists, so it wins an even blend
+        let even = relative_score_fusion(&keyword, &vector, 0.5);
+        assert_eq!(even[0].0, 2);
+    }
+
+    #[test]
+    fn test_rrf_uses_ranks_and_reports_contributions() {
+        let keyword = [(1, 80.0), (2, 4.0)];
+        let vector = [(2, 0.9), (3, 0.8)];
+
+        let fused = fuse(&keyword, &vector, FusionMethod::Rrf, 0.5);
+        assert_eq!(fused[0].key, 2);
+        let contributions = fused[0].contributions;
+        assert_eq!(contributions.keyword.unwrap().rank, 2);
+        assert_eq!(contributions.vector.unwrap().score, 0.9);
+        assert_eq!(
+            fused[0].score,
+            contributions.keyword.unwrap().fused + contributions.vector.unwrap().fused
+        );
+        assert!(fused[1].contributions.vector.is_none());
+    }
+
+    #[test]
+    fn test_fuse_many_rewards_agreement_across_lists() {
+        let first = [(1, 0.9), (2, 0.8), (3, 0.7)];
+        let second = [(4, 0.9), (2, 0.5)];
+        let third = [(2, 3.0), (5, 1.0)];
+
+        let fused = fuse_many(&[&first, &second, &third], FusionMethod::Rrf);
+        assert_eq!(fused.len(), 5);
+        assert_eq!(fused[0].0, 2);
+        let expected = 2.0 / (RRF_K + 2.0) + 1.0 / (RRF_K + 1.0);
+        assert!((fused[0].1 - expected).abs() < 1e-6);
+        // Top of one list only, ahead of lower ranks in one list; tied, so
+        // the one seen first wins
+        let order: Vec<i32> = fused.iter().map(|r| r.0).collect();
+        assert_eq!(order, vec![2, 1, 4, 5, 3]);
+    }
+
+    #[test]
+    fn test_bm25_prefers_rarer_terms() {
+        let docs: Vec<Vec<String>> = ["the refund policy", "the the the", "shipping policy"]
+            .iter()
+            .map(|d| tokenize(d))
+            .collect();
+        let scores = bm25_scores("refund policy", &docs);
+        assert!(scores[0] > scores[2]);
+        assert_eq!(scores[1], 0.0);
+    }
+}
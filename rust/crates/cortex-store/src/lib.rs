@@ -1,3 +1,13 @@
+pub mod content;
+pub mod facets;
+pub mod filter;
+pub mod hybrid;
+pub mod local;
+pub mod memory;
 pub mod models;
+pub mod pgvector;
 pub mod postgres;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+pub mod vector;
 pub mod weaviate;
@@ -0,0 +1,318 @@
+//! A small HNSW graph (Malkov & Yashunin) over cosine similarity.
+//!
+//! Vectors are normalized on insert so similarity is a dot product. Removal
+//! only marks a node deleted: it keeps routing searches but is never
+//! returned. Callers rebuild the graph once too many nodes are deleted.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hnsw {
+    /// Neighbors kept per node on the upper layers; layer 0 keeps twice as many.
+    m: usize,
+    ef_construction: usize,
+    nodes: Vec<Node>,
+    entry_point: Option<u32>,
+    deleted: usize,
+    rng_state: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Node {
+    vector: Vec<f32>,
+    /// Neighbors on each layer the node is part of, from layer 0 up.
+    neighbors: Vec<Vec<u32>>,
+    deleted: bool,
+}
+
+impl Default for Hnsw {
+    fn default() -> Self {
+        Self::new(16, 128)
+    }
+}
+
+impl Hnsw {
+    pub fn new(m: usize, ef_construction: usize) -> Self {
+        Self {
+            m,
+            ef_construction,
+            nodes: Vec::new(),
+            entry_point: None,
+            deleted: 0,
+            rng_state: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    /// Nodes not marked deleted.
+    pub fn len(&self) -> usize {
+        self.nodes.len() - self.deleted
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn deleted(&self) -> usize {
+        self.deleted
+    }
+
+    pub fn dimensions(&self) -> Option<usize> {
+        self.nodes.first().map(|n| n.vector.len())
+    }
+
+    pub fn vector(&self, node: u32) -> &[f32] {
+        &self.nodes[node as usize].vector
+    }
+
+    /// Add a vector and return its node id.
+    pub fn insert(&mut self, vector: &[f32]) -> u32 {
+        let id = self.nodes.len() as u32;
+        let level = self.random_level();
+        self.nodes.push(Node {
+            vector: normalized(vector),
+            neighbors: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+
+        let Some(mut entry) = self.entry_point else {
+            self.entry_point = Some(id);
+            return id;
+        };
+
+        let query = self.nodes[id as usize].vector.clone();
+        let top_level = self.level(entry);
+
+        // Greedy descent through the layers above the new node's level
+        for layer in (level + 1..=top_level).rev() {
+            entry = self.search_layer(&query, &[entry], 1, layer)[0].node;
+        }
+
+        let mut entries = vec![entry];
+        for layer in (0..=level.min(top_level)).rev() {
+            let candidates = self.search_layer(&query, &entries, self.ef_construction, layer);
+            let max = self.max_neighbors(layer);
+            let selected: Vec<u32> = candidates.iter().take(max).map(|c| c.node).collect();
+
+            for &neighbor in &selected {
+                let links = &mut self.nodes[neighbor as usize].neighbors[layer];
+                links.push(id);
+                if links.len() > max {
+                    self.prune(neighbor, layer, max);
+                }
+            }
+            self.nodes[id as usize].neighbors[layer] = selected;
+            entries = candidates.iter().map(|c| c.node).collect();
+        }
+
+        if level > top_level {
+            self.entry_point = Some(id);
+        }
+        id
+    }
+
+    pub fn remove(&mut self, node: u32) {
+        let node = &mut self.nodes[node as usize];
+        if !node.deleted {
+            node.deleted = true;
+            self.deleted += 1;
+        }
+    }
+
+    /// Up to `k` nodes most similar to `query` that pass `filter`, best first,
+    /// with their cosine similarity. `ef` trades speed for recall.
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef: usize,
+        filter: impl Fn(u32) -> bool,
+    ) -> Vec<(u32, f32)> {
+        let Some(mut entry) = self.entry_point else {
+            return Vec::new();
+        };
+        let query = normalized(query);
+
+        for layer in (1..=self.level(entry)).rev() {
+            entry = self.search_layer(&query, &[entry], 1, layer)[0].node;
+        }
+
+        self.search_layer(&query, &[entry], ef.max(k), 0)
+            .into_iter()
+            .filter(|c| !self.nodes[c.node as usize].deleted && filter(c.node))
+            .take(k)
+            .map(|c| (c.node, 1.0 - c.distance))
+            .collect()
+    }
+
+    /// The `ef` nodes closest to `query` on one layer, closest first.
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entries: &[u32],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Candidate> {
+        let mut visited: HashSet<u32> = entries.iter().copied().collect();
+        let mut frontier: BinaryHeap<std::cmp::Reverse<Candidate>> = BinaryHeap::new();
+        let mut best: BinaryHeap<Candidate> = BinaryHeap::new();
+
+        for &node in entries {
+            let candidate = Candidate {
+                node,
+                distance: self.distance(query, node),
+            };
+            frontier.push(std::cmp::Reverse(candidate));
+            best.push(candidate);
+        }
+
+        while let Some(std::cmp::Reverse(current)) = frontier.pop() {
+            let worst = best.peek().map_or(f32::INFINITY, |c| c.distance);
+            if current.distance > worst && best.len() >= ef {
+                break;
+            }
+
+            let Some(links) = self.nodes[current.node as usize].neighbors.get(layer) else {
+                continue;
+            };
+            for &neighbor in links {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let candidate = Candidate {
+                    node: neighbor,
+                    distance: self.distance(query, neighbor),
+                };
+                let worst = best.peek().map_or(f32::INFINITY, |c| c.distance);
+                if best.len() < ef || candidate.distance < worst {
+                    frontier.push(std::cmp::Reverse(candidate));
+                    best.push(candidate);
+                    if best.len() > ef {
+                        best.pop();
+                    }
+                }
+            }
+        }
+
+        best.into_sorted_vec()
+    }
+
+    /// Keep only the `max` closest neighbors of `node` on `layer`.
+    fn prune(&mut self, node: u32, layer: usize, max: usize) {
+        let vector = self.nodes[node as usize].vector.clone();
+        let mut links: Vec<Candidate> = self.nodes[node as usize].neighbors[layer]
+            .iter()
+            .map(|&n| Candidate {
+                node: n,
+                distance: self.distance(&vector, n),
+            })
+            .collect();
+        links.sort();
+        links.truncate(max);
+        self.nodes[node as usize].neighbors[layer] = links.into_iter().map(|c| c.node).collect();
+    }
+
+    fn distance(&self, query: &[f32], node: u32) -> f32 {
+        let vector = &self.nodes[node as usize].vector;
+        1.0 - query.iter().zip(vector).map(|(a, b)| a * b).sum::<f32>()
+    }
+
+    fn level(&self, node: u32) -> usize {
+        self.nodes[node as usize].neighbors.len() - 1
+    }
+
+    fn max_neighbors(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.m * 2
+        } else {
+            self.m
+        }
+    }
+
+    /// Exponentially distributed level with normalization factor 1/ln(M).
+    fn random_level(&mut self) -> usize {
+        // xorshift64*, so the graph is reproducible and needs no extra crate
+        self.rng_state ^= self.rng_state >> 12;
+        self.rng_state ^= self.rng_state << 25;
+        self.rng_state ^= self.rng_state >> 27;
+        let bits = self.rng_state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11;
+        let uniform = (bits as f64 + 1.0) / (1u64 << 53) as f64;
+        (-uniform.ln() / (self.m as f64).ln()).floor() as usize
+    }
+}
+
+fn normalized(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|v| v / norm).collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    node: u32,
+    distance: f32,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_matches_brute_force() {
+        let mut graph = Hnsw::new(8, 64);
+        let mut state = 7u32;
+        let mut next = || {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (state >> 16) as f32 / 65_536.0 - 0.5
+        };
+        let vectors: Vec<Vec<f32>> = (0..500)
+            .map(|_| (0..16).map(|_| next()).collect())
+            .collect();
+        for v in &vectors {
+            graph.insert(v);
+        }
+        graph.remove(3);
+
+        let query = &vectors[42];
+        let found = graph.search(query, 10, 64, |_| true);
+        assert_eq!(found[0].0, 42);
+        assert!(found.iter().all(|(node, _)| *node != 3));
+
+        let mut exact: Vec<(u32, f32)> = (0..vectors.len() as u32)
+            .filter(|&n| n != 3)
+            .map(|n| (n, 1.0 - graph.distance(&normalized(query), n)))
+            .collect();
+        exact.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let exact: HashSet<u32> = exact.iter().take(10).map(|e| e.0).collect();
+        let recalled = found.iter().filter(|(n, _)| exact.contains(n)).count();
+        assert!(recalled >= 9, "recall {recalled}/10");
+
+        let even = graph.search(query, 5, 64, |n| n % 2 == 0);
+        assert!(even.iter().all(|(n, _)| n % 2 == 0));
+    }
+}
@@ -0,0 +1,1172 @@
+//! An embedded vector store: Tantivy for BM25 over chunk text, title and
+//! section, and an HNSW graph for vector search, both kept on local disk.
+//!
+//! Each index lives in its own directory under the root:
+//!
+//! ```text
+//! <root>/<index>/text/         Tantivy index
+//! <root>/<index>/vectors.bin   HNSW graph and chunk payloads
+//! <root>/<index>/vectors.log   changes made since vectors.bin was written
+//! ```
+
+mod hnsw;
+
+use async_trait::async_trait;
+use cortex_common::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{
+    AllQuery, BooleanQuery, ConstScoreQuery, Occur, Query, RangeQuery, TermQuery,
+};
+use tantivy::schema::{IndexRecordOption, Schema, Value, FAST, INDEXED, STORED, STRING, TEXT};
+use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+
+use crate::facets::{FacetCounter, FacetCounts, FacetRequest};
+use crate::filter::{self, DateRange, FilterExpr, FilterFields};
+use crate::hybrid;
+use crate::models::{Chunk, SearchFilters, SearchResult};
+use crate::vector::{self, VectorStore, VectorStoreError, DEFAULT_INDEX};
+use hnsw::Hnsw;
+
+const VECTORS_FILE: &str = "vectors.bin";
+const VECTORS_LOG: &str = "vectors.log";
+
+/// The log is folded into a new snapshot once it is larger than both the
+/// snapshot and this, so each write costs its own size, not the index's.
+const MIN_LOG_BYTES_BEFORE_SNAPSHOT: u64 = 16 << 20;
+
+/// Memory Tantivy may buffer before flushing a segment.
+const WRITER_MEMORY_BYTES: usize = 50_000_000;
+
+/// The graph is rebuilt once deleted nodes outnumber live ones.
+const MIN_DELETED_BEFORE_COMPACTION: usize = 1_000;
+
+/// Vector store for single-binary and air-gapped deployments. Writes are
+/// committed to disk before returning; searches never leave the process.
+#[derive(Clone)]
+pub struct LocalVectorStore {
+    root: PathBuf,
+    /// Indexes opened so far, shared by every handle so each directory has
+    /// exactly one Tantivy writer.
+    opened: Arc<Mutex<HashMap<String, Arc<LocalIndex>>>>,
+    index: String,
+}
+
+struct LocalIndex {
+    dir: PathBuf,
+    fields: Fields,
+    reader: IndexReader,
+    writer: Mutex<IndexWriter>,
+    vectors: RwLock<Vectors>,
+    log: Mutex<VectorLog>,
+}
+
+#[derive(Clone, Copy)]
+struct Fields {
+    id: tantivy::schema::Field,
+    document_id: tantivy::schema::Field,
+    user_id: tantivy::schema::Field,
+    source_type: tantivy::schema::Field,
+    chunk_kind: tantivy::schema::Field,
+    text: tantivy::schema::Field,
+    document_title: tantivy::schema::Field,
+    section_title: tantivy::schema::Field,
+    /// Microseconds since the epoch.
+    indexed_at: tantivy::schema::Field,
+    updated_at: tantivy::schema::Field,
+    /// One value per encoded metadata field, see `filter::metadata_fields`.
+    metadata: tantivy::schema::Field,
+}
+
+/// Everything besides the BM25 index, persisted as a snapshot plus a log
+/// of the changes made since.
+#[derive(Default, Serialize, Deserialize)]
+struct Vectors {
+    graph: Hnsw,
+    /// Chunk stored at each graph node; `None` once removed.
+    chunks: Vec<Option<StoredChunk>>,
+    nodes: HashMap<ChunkId, u32>,
+}
+
+/// One change to `Vectors`. Replaying a change that is already in the
+/// snapshot leaves it as it was.
+#[derive(Serialize, Deserialize)]
+enum LogEntry {
+    Upsert(Box<StoredChunk>, Vec<f32>),
+    Remove(ChunkId),
+}
+
+/// The open change log, each entry a little-endian `u32` length followed by
+/// the bincode entry.
+struct VectorLog {
+    file: std::fs::File,
+    bytes: u64,
+    snapshot_bytes: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct StoredChunk {
+    id: ChunkId,
+    document_id: DocumentId,
+    user_id: UserId,
+    text: String,
+    document_title: String,
+    source_type: SourceType,
+    source_url: Option<String>,
+    section_title: Option<String>,
+    kind: ChunkKind,
+    parent_id: Option<ChunkId>,
+    indexed_at: chrono::DateTime<chrono::Utc>,
+    updated_at: chrono::DateTime<chrono::Utc>,
+    metadata_fields: Vec<String>,
+}
+
+impl LocalVectorStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            opened: Arc::default(),
+            index: DEFAULT_INDEX.to_string(),
+        }
+    }
+
+    /// The open index, or `None` if it doesn't exist and `create` is false.
+    fn open(&self, create: bool) -> Result<Option<Arc<LocalIndex>>, VectorStoreError> {
+        vector::check_index_name(&self.index)?;
+        let mut opened = self.opened.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(index) = opened.get(&self.index) {
+            return Ok(Some(index.clone()));
+        }
+
+        let dir = self.root.join(&self.index);
+        if !create && !dir.exists() {
+            return Ok(None);
+        }
+        let index = Arc::new(LocalIndex::open(dir)?);
+        opened.insert(self.index.clone(), index.clone());
+        Ok(Some(index))
+    }
+
+    fn upsert(&self, chunks: Vec<Chunk>, vectors: Vec<Vec<f32>>) -> Result<(), VectorStoreError> {
+        if chunks.len() != vectors.len() {
+            return Err(VectorStoreError::InvalidInput(format!(
+                "{} chunks but {} vectors",
+                chunks.len(),
+                vectors.len()
+            )));
+        }
+        let Some(index) = self.open(true)? else {
+            return Ok(());
+        };
+        let fields = index.fields;
+
+        let Some(batch_dimensions) = vectors.first().map(Vec::len) else {
+            return Ok(());
+        };
+        if let Some(bad) = vectors.iter().find(|v| v.len() != batch_dimensions) {
+            return Err(VectorStoreError::InvalidInput(format!(
+                "vector has {} dimensions but the first in the batch has {batch_dimensions}",
+                bad.len()
+            )));
+        }
+
+        let mut data = index.write_vectors();
+        let dimensions = data.graph.dimensions().filter(|_| !data.graph.is_empty());
+        if let Some(dimensions) = dimensions.filter(|&d| d != batch_dimensions) {
+            return Err(VectorStoreError::InvalidInput(format!(
+                "vector has {batch_dimensions} dimensions but the index has {dimensions}"
+            )));
+        }
+
+        let mut writer = index.lock_writer();
+        let mut changes = Vec::with_capacity(chunks.len());
+        for (chunk, vector) in chunks.iter().zip(vectors) {
+            let stored = StoredChunk::from(chunk);
+            data.upsert(stored.clone(), &vector);
+            changes.push(LogEntry::Upsert(Box::new(stored), vector));
+
+            writer.delete_term(Term::from_field_text(fields.id, &chunk.id.to_string()));
+            let mut document = doc!(
+                fields.id => chunk.id.to_string(),
+                fields.document_id => chunk.document_id.to_string(),
+                fields.user_id => chunk.user_id.to_string(),
+                fields.source_type => chunk.source_type.to_string(),
+                fields.chunk_kind => chunk.kind.to_string(),
+                fields.text => chunk.text.as_str(),
+                fields.document_title => chunk.document_title.as_str(),
+                fields.section_title => chunk.section_title.as_deref().unwrap_or_default(),
+                fields.indexed_at => chunk.indexed_at.timestamp_micros(),
+                fields.updated_at => chunk.updated_at.timestamp_micros(),
+            );
+            for field in filter::metadata_fields(&chunk.metadata) {
+                document.add_text(fields.metadata, field);
+            }
+            writer.add_document(document)?;
+        }
+        index.commit(&mut writer, &data, &changes)
+    }
+
+    fn search(
+        &self,
+        query: &str,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+        alpha: f32,
+    ) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let Some(index) = self.open(false)? else {
+            return Ok(Vec::new());
+        };
+        let data = index.read_vectors();
+
+        // Like the other backends, each side only contributes its top `limit` hits
+        let keyword = if alpha < 1.0 {
+            index.keyword_search(&data, query, user_id, filters, limit)?
+        } else {
+            Vec::new()
+        };
+        let semantic = if alpha > 0.0 {
+            data.vector_search(vector, user_id, filters, limit)
+        } else {
+            Vec::new()
+        };
+
+        let results = hybrid::relative_score_fusion(&keyword, &semantic, alpha)
+            .into_iter()
+            .take(limit)
+            .filter_map(|(node, score)| Some(data.chunk(node)?.search_result(score)))
+            .collect();
+        Ok(results)
+    }
+
+    /// Run one retriever on its own, keeping its scores.
+    fn search_one(
+        &self,
+        retrieve: impl FnOnce(&LocalIndex, &Vectors) -> Result<Vec<(u32, f32)>, VectorStoreError>,
+    ) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let Some(index) = self.open(false)? else {
+            return Ok(Vec::new());
+        };
+        let data = index.read_vectors();
+        let results = retrieve(&index, &data)?
+            .into_iter()
+            .filter_map(|(node, score)| Some(data.chunk(node)?.search_result(score)))
+            .collect();
+        Ok(results)
+    }
+
+    /// Remove the chunks `remove` selects, along with the Tantivy documents
+    /// matching the `terms` built for this index.
+    fn delete(
+        &self,
+        terms: impl FnOnce(&Fields) -> Vec<Term>,
+        remove: impl Fn(&StoredChunk) -> bool,
+    ) -> Result<(), VectorStoreError> {
+        let Some(index) = self.open(false)? else {
+            return Ok(());
+        };
+        let mut data = index.write_vectors();
+        let ids: Vec<ChunkId> = data
+            .chunks
+            .iter()
+            .flatten()
+            .filter(|chunk| remove(chunk))
+            .map(|chunk| chunk.id)
+            .collect();
+        for &id in &ids {
+            data.remove(id);
+        }
+        if data.graph.deleted() >= MIN_DELETED_BEFORE_COMPACTION
+            && data.graph.deleted() > data.graph.len()
+        {
+            data.compact();
+        }
+
+        let mut writer = index.lock_writer();
+        for term in terms(&index.fields) {
+            writer.delete_term(term);
+        }
+        let changes: Vec<LogEntry> = ids.into_iter().map(LogEntry::Remove).collect();
+        index.commit(&mut writer, &data, &changes)
+    }
+
+    fn drop_dir(&self) -> Result<(), VectorStoreError> {
+        vector::check_index_name(&self.index)?;
+        // Dropping the last handle releases the Tantivy writer lock
+        self.opened
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.index);
+        match std::fs::remove_dir_all(self.root.join(&self.index)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&Vectors) -> T) -> Result<Option<T>, VectorStoreError> {
+        Ok(self.open(false)?.map(|index| f(&index.read_vectors())))
+    }
+}
+
+/// Run blocking index work off the async runtime.
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, VectorStoreError> + Send + 'static,
+) -> Result<T, VectorStoreError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| VectorStoreError::Io(std::io::Error::other(e)))?
+}
+
+#[async_trait]
+impl VectorStore for LocalVectorStore {
+    fn with_index(&self, name: &str) -> Arc<dyn VectorStore> {
+        Arc::new(Self {
+            root: self.root.clone(),
+            opened: self.opened.clone(),
+            index: name.to_string(),
+        })
+    }
+
+    fn index_name(&self) -> &str {
+        &self.index
+    }
+
+    async fn ensure_index(&self) -> Result<(), VectorStoreError> {
+        let store = self.clone();
+        blocking(move || store.open(true).map(|_| ())).await
+    }
+
+    async fn drop_index(&self) -> Result<(), VectorStoreError> {
+        let store = self.clone();
+        blocking(move || store.drop_dir()).await
+    }
+
+    async fn upsert_chunks(
+        &self,
+        chunks: &[Chunk],
+        vectors: &[Vec<f32>],
+    ) -> Result<(), VectorStoreError> {
+        let store = self.clone();
+        let (chunks, vectors) = (chunks.to_vec(), vectors.to_vec());
+        blocking(move || store.upsert(chunks, vectors)).await
+    }
+
+    async fn hybrid_search(
+        &self,
+        query: &str,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+        alpha: f32,
+    ) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let store = self.clone();
+        let (query, vector, filters) = (query.to_string(), vector.to_vec(), filters.clone());
+        blocking(move || store.search(&query, &vector, user_id, &filters, limit, alpha)).await
+    }
+
+    async fn keyword_search(
+        &self,
+        query: &str,
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let store = self.clone();
+        let (query, filters) = (query.to_string(), filters.clone());
+        blocking(move || {
+            store.search_one(|index, data| {
+                index.keyword_search(data, &query, user_id, &filters, limit)
+            })
+        })
+        .await
+    }
+
+    async fn vector_search(
+        &self,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let store = self.clone();
+        let (vector, filters) = (vector.to_vec(), filters.clone());
+        blocking(move || {
+            store.search_one(|_, data| Ok(data.vector_search(&vector, user_id, &filters, limit)))
+        })
+        .await
+    }
+
+    async fn facet_counts(
+        &self,
+        query: &str,
+        user_id: UserId,
+        filters: &SearchFilters,
+        request: &FacetRequest,
+    ) -> Result<FacetCounts, VectorStoreError> {
+        let store = self.clone();
+        let terms = hybrid::tokenize(query);
+        let (filters, request) = (filters.clone(), request.clone());
+        blocking(move || {
+            let counts = store.read(|data| {
+                let mut counter = FacetCounter::new(&request);
+                for chunk in data.chunks.iter().flatten() {
+                    if chunk.matches(user_id, &filters) && chunk.has_any_term(&terms) {
+                        counter.add_chunk(&chunk.source_type.to_string(), &chunk.filter_fields());
+                    }
+                }
+                counter.finish()
+            })?;
+            Ok(counts.unwrap_or_default())
+        })
+        .await
+    }
+
+    async fn chunk_ids_by_document(
+        &self,
+        document_id: DocumentId,
+    ) -> Result<Vec<ChunkId>, VectorStoreError> {
+        let store = self.clone();
+        blocking(move || {
+            let ids = store.read(|data| {
+                data.chunks
+                    .iter()
+                    .flatten()
+                    .filter(|chunk| chunk.document_id == document_id)
+                    .map(|chunk| chunk.id)
+                    .collect()
+            })?;
+            Ok(ids.unwrap_or_default())
+        })
+        .await
+    }
+
+    async fn delete_chunks_by_ids(&self, ids: &[ChunkId]) -> Result<(), VectorStoreError> {
+        let store = self.clone();
+        let ids = ids.to_vec();
+        blocking(move || {
+            let terms = |fields: &Fields| {
+                ids.iter()
+                    .map(|id| Term::from_field_text(fields.id, &id.to_string()))
+                    .collect()
+            };
+            store.delete(terms, |chunk| ids.contains(&chunk.id))
+        })
+        .await
+    }
+
+    async fn delete_chunks_by_documents(
+        &self,
+        document_ids: &[DocumentId],
+    ) -> Result<(), VectorStoreError> {
+        let store = self.clone();
+        let document_ids = document_ids.to_vec();
+        blocking(move || {
+            let terms = |fields: &Fields| {
+                document_ids
+                    .iter()
+                    .map(|id| Term::from_field_text(fields.document_id, &id.to_string()))
+                    .collect()
+            };
+            store.delete(terms, |chunk| document_ids.contains(&chunk.document_id))
+        })
+        .await
+    }
+
+    async fn delete_chunks_by_user(&self, user_id: UserId) -> Result<(), VectorStoreError> {
+        let store = self.clone();
+        blocking(move || {
+            let terms =
+                |fields: &Fields| vec![Term::from_field_text(fields.user_id, &user_id.to_string())];
+            store.delete(terms, |chunk| chunk.user_id == user_id)
+        })
+        .await
+    }
+
+    async fn chunk_counts_by_document(
+        &self,
+        user_id: UserId,
+    ) -> Result<HashMap<DocumentId, usize>, VectorStoreError> {
+        let store = self.clone();
+        blocking(move || {
+            let counts = store.read(|data| {
+                let mut counts = HashMap::new();
+                for chunk in data
+                    .chunks
+                    .iter()
+                    .flatten()
+                    .filter(|c| c.user_id == user_id)
+                {
+                    *counts.entry(chunk.document_id).or_default() += 1;
+                }
+                counts
+            })?;
+            Ok(counts.unwrap_or_default())
+        })
+        .await
+    }
+
+    async fn chunk_vectors(
+        &self,
+        ids: &[ChunkId],
+    ) -> Result<HashMap<ChunkId, Vec<f32>>, VectorStoreError> {
+        let store = self.clone();
+        let ids = ids.to_vec();
+        blocking(move || {
+            // The graph keeps vectors normalized, which cosine similarity ignores
+            let vectors = store.read(|data| {
+                ids.iter()
+                    .filter_map(|id| {
+                        let node = *data.nodes.get(id)?;
+                        Some((*id, data.graph.vector(node).to_vec()))
+                    })
+                    .collect()
+            })?;
+            Ok(vectors.unwrap_or_default())
+        })
+        .await
+    }
+
+    async fn chunk_owners(&self) -> Result<Vec<UserId>, VectorStoreError> {
+        let store = self.clone();
+        blocking(move || {
+            let owners = store.read(|data| {
+                let mut owners: Vec<UserId> =
+                    data.chunks.iter().flatten().map(|c| c.user_id).collect();
+                owners.sort_by_key(|id| id.0);
+                owners.dedup();
+                owners
+            })?;
+            Ok(owners.unwrap_or_default())
+        })
+        .await
+    }
+
+    async fn health_check(&self) -> Result<bool, VectorStoreError> {
+        Ok(tokio::fs::create_dir_all(&self.root).await.is_ok())
+    }
+}
+
+impl LocalIndex {
+    fn open(dir: PathBuf) -> Result<Self, VectorStoreError> {
+        let mut schema = Schema::builder();
+        let fields = Fields::new(&mut schema);
+
+        let text_dir = dir.join("text");
+        std::fs::create_dir_all(&text_dir)?;
+        let directory = MmapDirectory::open(&text_dir).map_err(tantivy::TantivyError::from)?;
+        let text = Index::open_or_create(directory, schema.build())?;
+        let reader = text
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let writer = text.writer(WRITER_MEMORY_BYTES)?;
+
+        let snapshot = read_if_exists(&dir.join(VECTORS_FILE))?;
+        let mut vectors = match &snapshot {
+            Some(bytes) => bincode::deserialize(bytes)?,
+            None => Vectors::default(),
+        };
+
+        // A write interrupted by a crash leaves a partial last entry, which
+        // is cut off so new entries follow the last whole one
+        let log_path = dir.join(VECTORS_LOG);
+        let log_bytes = read_if_exists(&log_path)?.unwrap_or_default();
+        let replayed = vectors.replay(&log_bytes);
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)?;
+        file.set_len(replayed)?;
+        let log = VectorLog {
+            file,
+            bytes: replayed,
+            snapshot_bytes: snapshot.map_or(0, |bytes| bytes.len() as u64),
+        };
+
+        Ok(Self {
+            dir,
+            fields,
+            reader,
+            writer: Mutex::new(writer),
+            vectors: RwLock::new(vectors),
+            log: Mutex::new(log),
+        })
+    }
+
+    fn read_vectors(&self) -> std::sync::RwLockReadGuard<'_, Vectors> {
+        self.vectors.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write_vectors(&self) -> std::sync::RwLockWriteGuard<'_, Vectors> {
+        self.vectors.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_writer(&self) -> std::sync::MutexGuard<'_, IndexWriter> {
+        self.writer.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Commit the Tantivy writer and log the vector `changes`, so both
+    /// halves of the index are durable when a write returns. Once the log
+    /// outgrows the snapshot, `data` is snapshotted and the log emptied.
+    fn commit(
+        &self,
+        writer: &mut IndexWriter,
+        data: &Vectors,
+        changes: &[LogEntry],
+    ) -> Result<(), VectorStoreError> {
+        writer.commit()?;
+        self.reader.reload()?;
+
+        let mut log = self.log.lock().unwrap_or_else(|e| e.into_inner());
+        if log.bytes >= log.snapshot_bytes.max(MIN_LOG_BYTES_BEFORE_SNAPSHOT) {
+            let snapshot = bincode::serialize(data)?;
+            write_atomically(&self.dir.join(VECTORS_FILE), &snapshot)?;
+            log.file.set_len(0)?;
+            log.bytes = 0;
+            log.snapshot_bytes = snapshot.len() as u64;
+            return Ok(());
+        }
+
+        let mut bytes = Vec::new();
+        for change in changes {
+            let entry = bincode::serialize(change)?;
+            bytes.extend((entry.len() as u32).to_le_bytes());
+            bytes.extend(entry);
+        }
+        log.file.write_all(&bytes)?;
+        log.bytes += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// BM25 over text, title and section, matching any query term, scoped to
+    /// the user and filters. Returns graph nodes with their scores.
+    fn keyword_search(
+        &self,
+        data: &Vectors,
+        query: &str,
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<(u32, f32)>, VectorStoreError> {
+        let fields = self.fields;
+        let mut terms = hybrid::tokenize(query);
+        terms.sort();
+        terms.dedup();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let any_term: Vec<(Occur, Box<dyn Query>)> = terms
+            .iter()
+            .flat_map(|term| {
+                [fields.text, fields.document_title, fields.section_title].map(|field| {
+                    let query: Box<dyn Query> = Box::new(TermQuery::new(
+                        Term::from_field_text(field, term),
+                        IndexRecordOption::WithFreqs,
+                    ));
+                    (Occur::Should, query)
+                })
+            })
+            .collect();
+
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![
+            (Occur::Must, Box::new(BooleanQuery::new(any_term))),
+            filter_clause(fields.user_id, &user_id.to_string()),
+        ];
+        if let Some(source_type) = &filters.source_type {
+            clauses.push(filter_clause(fields.source_type, source_type));
+        }
+        if let Some(kind) = filters.chunk_kind {
+            clauses.push(filter_clause(fields.chunk_kind, &kind.to_string()));
+        }
+        if let Some(filter) = &filters.filter {
+            let query = ConstScoreQuery::new(fields.filter_query(filter), 0.0);
+            clauses.push((Occur::Must, Box::new(query)));
+        }
+
+        let searcher = self.reader.searcher();
+        let hits = searcher.search(&BooleanQuery::new(clauses), &TopDocs::with_limit(limit))?;
+
+        let mut scored = Vec::with_capacity(hits.len());
+        for (score, address) in hits {
+            let doc: TantivyDocument = searcher.doc(address)?;
+            let node = doc
+                .get_first(fields.id)
+                .and_then(|v| v.as_str())
+                .and_then(|id| id.parse().ok())
+                .and_then(|id| data.nodes.get(&ChunkId(id)));
+            if let Some(&node) = node {
+                scored.push((node, score));
+            }
+        }
+        Ok(scored)
+    }
+}
+
+/// A required exact-match clause that doesn't affect the score.
+fn filter_clause(field: tantivy::schema::Field, value: &str) -> (Occur, Box<dyn Query>) {
+    let term = TermQuery::new(
+        Term::from_field_text(field, value),
+        IndexRecordOption::Basic,
+    );
+    (
+        Occur::Must,
+        Box::new(ConstScoreQuery::new(Box::new(term), 0.0)),
+    )
+}
+
+impl Fields {
+    fn new(schema: &mut tantivy::schema::SchemaBuilder) -> Self {
+        Self {
+            id: schema.add_text_field("id", STRING | STORED),
+            document_id: schema.add_text_field("document_id", STRING),
+            user_id: schema.add_text_field("user_id", STRING),
+            source_type: schema.add_text_field("source_type", STRING),
+            chunk_kind: schema.add_text_field("chunk_kind", STRING),
+            text: schema.add_text_field("text", TEXT),
+            document_title: schema.add_text_field("document_title", TEXT),
+            section_title: schema.add_text_field("section_title", TEXT),
+            indexed_at: schema.add_i64_field("indexed_at", INDEXED | FAST),
+            updated_at: schema.add_i64_field("updated_at", INDEXED | FAST),
+            metadata: schema.add_text_field("metadata", STRING),
+        }
+    }
+
+    /// Translate a filter into a query matching the same chunks.
+    fn filter_query(&self, expr: &FilterExpr) -> Box<dyn Query> {
+        let text_term = |field, value: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(field, value),
+                IndexRecordOption::Basic,
+            ))
+        };
+        let words = |field, value: &str| -> Box<dyn Query> {
+            let clauses = hybrid::tokenize(value)
+                .iter()
+                .map(|word| (Occur::Must, text_term(field, word)))
+                .collect();
+            Box::new(BooleanQuery::new(clauses))
+        };
+        let documents = |ids: &[DocumentId]| -> Box<dyn Query> {
+            let clauses = ids
+                .iter()
+                .map(|id| (Occur::Should, text_term(self.document_id, &id.to_string())))
+                .collect();
+            Box::new(BooleanQuery::new(clauses))
+        };
+        // A negated clause needs a positive one to subtract from
+        let not = |query: Box<dyn Query>| -> Box<dyn Query> {
+            Box::new(BooleanQuery::new(vec![
+                (Occur::Must, Box::new(AllQuery)),
+                (Occur::MustNot, query),
+            ]))
+        };
+
+        match expr {
+            FilterExpr::And(operands) | FilterExpr::Or(operands) => {
+                let occur = match expr {
+                    FilterExpr::And(_) => Occur::Must,
+                    _ => Occur::Should,
+                };
+                let clauses = operands
+                    .iter()
+                    .map(|operand| (occur, self.filter_query(operand)))
+                    .collect();
+                Box::new(BooleanQuery::new(clauses))
+            }
+            FilterExpr::Not(operand) => not(self.filter_query(operand)),
+            FilterExpr::IndexedAt(range) => date_query(self.indexed_at, range),
+            FilterExpr::UpdatedAt(range) => date_query(self.updated_at, range),
+            FilterExpr::DocumentIds(ids) => documents(ids),
+            FilterExpr::ExcludeDocumentIds(ids) => not(documents(ids)),
+            FilterExpr::Title(value) => words(self.document_title, value),
+            FilterExpr::Section(value) => words(self.section_title, value),
+            FilterExpr::Metadata(condition) => text_term(self.metadata, &condition.field()),
+        }
+    }
+}
+
+fn date_query(field: tantivy::schema::Field, range: &DateRange) -> Box<dyn Query> {
+    let bound = |time: Option<chrono::DateTime<chrono::Utc>>, included: bool| match time {
+        Some(time) => {
+            let term = Term::from_field_i64(field, time.timestamp_micros());
+            if included {
+                Bound::Included(term)
+            } else {
+                Bound::Excluded(term)
+            }
+        }
+        None => Bound::Unbounded,
+    };
+    Box::new(RangeQuery::new(
+        bound(range.from, true),
+        bound(range.to, false),
+    ))
+}
+
+impl Vectors {
+    fn chunk(&self, node: u32) -> Option<&StoredChunk> {
+        self.chunks.get(node as usize)?.as_ref()
+    }
+
+    /// The `limit` nodes of the user's filtered chunks most similar to
+    /// `vector`, with their cosine similarity.
+    fn vector_search(
+        &self,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Vec<(u32, f32)> {
+        let matches = |chunk: &StoredChunk| chunk.matches(user_id, filters);
+        let ef = (limit * 4).max(64);
+        let found = self.graph.search(vector, limit, ef, |node| {
+            self.chunk(node).is_some_and(matches)
+        });
+        if found.len() >= limit {
+            return found;
+        }
+
+        // The graph is shared by every user, so a user with few chunks may
+        // not be reachable within `ef`; scan their chunks instead
+        let candidates = self
+            .chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| chunk.as_ref().is_some_and(matches))
+            .map(|(node, _)| {
+                let node = node as u32;
+                (
+                    node,
+                    hybrid::cosine_similarity(vector, self.graph.vector(node)),
+                )
+            });
+        hybrid::top_k(candidates, limit)
+    }
+
+    fn upsert(&mut self, chunk: StoredChunk, vector: &[f32]) {
+        self.remove(chunk.id);
+        let node = self.graph.insert(vector);
+        self.nodes.insert(chunk.id, node);
+        self.chunks.push(Some(chunk));
+    }
+
+    /// Apply the whole entries at the start of `log`, returning how many
+    /// bytes they take up.
+    fn replay(&mut self, log: &[u8]) -> u64 {
+        let mut offset = 0;
+        while let Some(header) = log.get(offset..offset + 4) {
+            let len = u32::from_le_bytes(header.try_into().unwrap_or_default()) as usize;
+            let Some(entry) = log.get(offset + 4..offset + 4 + len) else {
+                break;
+            };
+            match bincode::deserialize(entry) {
+                Ok(LogEntry::Upsert(chunk, vector)) => self.upsert(*chunk, &vector),
+                Ok(LogEntry::Remove(id)) => self.remove(id),
+                Err(_) => break,
+            }
+            offset += 4 + len;
+        }
+        offset as u64
+    }
+
+    fn remove(&mut self, id: ChunkId) {
+        if let Some(node) = self.nodes.remove(&id) {
+            self.graph.remove(node);
+            self.chunks[node as usize] = None;
+        }
+    }
+
+    /// Rebuild the graph from live nodes only.
+    fn compact(&mut self) {
+        let mut compacted = Vectors::default();
+        for (node, chunk) in self.chunks.iter().enumerate() {
+            if let Some(chunk) = chunk {
+                let new_node = compacted.graph.insert(self.graph.vector(node as u32));
+                compacted.nodes.insert(chunk.id, new_node);
+                compacted.chunks.push(Some(chunk.clone()));
+            }
+        }
+        *self = compacted;
+    }
+}
+
+impl From<&Chunk> for StoredChunk {
+    fn from(chunk: &Chunk) -> Self {
+        Self {
+            id: chunk.id,
+            document_id: chunk.document_id,
+            user_id: chunk.user_id,
+            text: chunk.text.clone(),
+            document_title: chunk.document_title.clone(),
+            source_type: chunk.source_type,
+            source_url: chunk.source_url.clone(),
+            section_title: chunk.section_title.clone(),
+            kind: chunk.kind,
+            parent_id: chunk.parent_id,
+            indexed_at: chunk.indexed_at,
+            updated_at: chunk.updated_at,
+            metadata_fields: filter::metadata_fields(&chunk.metadata),
+        }
+    }
+}
+
+impl StoredChunk {
+    fn matches(&self, user_id: UserId, filters: &SearchFilters) -> bool {
+        self.user_id == user_id
+            && filters
+                .source_type
+                .as_ref()
+                .is_none_or(|t| self.source_type.to_string() == *t)
+            && filters.chunk_kind.is_none_or(|k| self.kind == k)
+            && filters
+                .filter
+                .as_ref()
+                .is_none_or(|f| f.matches(&self.filter_fields()))
+    }
+
+    /// Whether the text, title or section title contains any of `terms`,
+    /// or `terms` is empty.
+    fn has_any_term(&self, terms: &[String]) -> bool {
+        if terms.is_empty() {
+            return true;
+        }
+        let searchable = [
+            self.text.as_str(),
+            &self.document_title,
+            self.section_title.as_deref().unwrap_or_default(),
+        ]
+        .join(" ");
+        hybrid::tokenize(&searchable)
+            .iter()
+            .any(|t| terms.contains(t))
+    }
+
+    fn filter_fields(&self) -> FilterFields<'_> {
+        FilterFields {
+            document_id: self.document_id,
+            document_title: &self.document_title,
+            section_title: self.section_title.as_deref(),
+            indexed_at: self.indexed_at,
+            updated_at: self.updated_at,
+            metadata_fields: &self.metadata_fields,
+        }
+    }
+
+    fn search_result(&self, score: f32) -> SearchResult {
+        SearchResult {
+            chunk_id: self.id,
+            document_id: self.document_id,
+            text: self.text.clone(),
+            score,
+            document_title: self.document_title.clone(),
+            source_type: self.source_type,
+            source_url: self.source_url.clone(),
+            section_title: self.section_title.clone(),
+            kind: self.kind,
+            parent_id: self.parent_id,
+        }
+    }
+}
+
+/// The file's contents, or `None` if there is no such file.
+fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>, VectorStoreError> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Write then rename, so a crash never leaves a partial file behind.
+fn write_atomically(path: &Path, bytes: &[u8]) -> Result<(), VectorStoreError> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::chunk;
+
+    #[tokio::test]
+    async fn test_index_survives_reopen() {
+        let root = std::env::temp_dir().join(format!("cortex-local-{}", uuid::Uuid::new_v4()));
+        let user_id = UserId::new();
+        let doc_id = DocumentId::new();
+        let refunds = chunk(user_id, doc_id, "Refunds are issued within 14 days");
+        let shipping = chunk(user_id, doc_id, "Orders ship the next business day");
+
+        let store = LocalVectorStore::new(&root);
+        store
+            .upsert_chunks(
+                &[refunds.clone(), shipping.clone()],
+                &[vec![0.0, 1.0, 0.0], vec![1.0, 0.0, 0.0]],
+            )
+            .await
+            .unwrap();
+        drop(store);
+
+        // A fresh store reads both halves of the index back from disk
+        let store = LocalVectorStore::new(&root);
+        let filters = SearchFilters::default();
+        let keyword = store
+            .hybrid_search("refunds", &[1.0, 0.0, 0.0], user_id, &filters, 5, 0.0)
+            .await
+            .unwrap();
+        assert_eq!(keyword.len(), 1);
+        assert_eq!(keyword[0].chunk_id, refunds.id);
+
+        let semantic = store
+            .hybrid_search("refunds", &[1.0, 0.0, 0.0], user_id, &filters, 5, 1.0)
+            .await
+            .unwrap();
+        assert_eq!(semantic[0].chunk_id, shipping.id);
+
+        store.drop_index().await.unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejects_mixed_dimensions_in_one_batch() {
+        let root = std::env::temp_dir().join(format!("cortex-local-{}", uuid::Uuid::new_v4()));
+        let user_id = UserId::new();
+        let doc_id = DocumentId::new();
+        let refunds = chunk(user_id, doc_id, "Refunds are issued within 14 days");
+        let shipping = chunk(user_id, doc_id, "Orders ship the next business day");
+
+        // Into an empty index, so only the batch itself can disagree
+        let store = LocalVectorStore::new(&root);
+        let result = store
+            .upsert_chunks(&[refunds, shipping], &[vec![1.0, 0.0], vec![1.0, 0.0, 0.0]])
+            .await;
+        assert!(matches!(result, Err(VectorStoreError::InvalidInput(_))));
+
+        store.drop_index().await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_reopen_ignores_partial_log_entry() {
+        let root = std::env::temp_dir().join(format!("cortex-local-{}", uuid::Uuid::new_v4()));
+        let user_id = UserId::new();
+        let refunds = chunk(
+            user_id,
+            DocumentId::new(),
+            "Refunds are issued within 14 days",
+        );
+        let shipping = chunk(
+            user_id,
+            DocumentId::new(),
+            "Orders ship the next business day",
+        );
+
+        let store = LocalVectorStore::new(&root);
+        store
+            .upsert_chunks(std::slice::from_ref(&refunds), &[vec![1.0, 0.0]])
+            .await
+            .unwrap();
+        drop(store);
+
+        // A write cut off halfway through its entry
+        let log = root.join(DEFAULT_INDEX).join(VECTORS_LOG);
+        let mut file = std::fs::OpenOptions::new().append(true).open(&log).unwrap();
+        file.write_all(&[200, 0, 0, 0, 1, 2, 3]).unwrap();
+        drop(file);
+
+        let store = LocalVectorStore::new(&root);
+        store
+            .upsert_chunks(std::slice::from_ref(&shipping), &[vec![0.0, 1.0]])
+            .await
+            .unwrap();
+        drop(store);
+
+        let store = LocalVectorStore::new(&root);
+        let filters = SearchFilters::default();
+        let mut found: Vec<ChunkId> = store
+            .hybrid_search("refunds", &[1.0, 1.0], user_id, &filters, 5, 1.0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.chunk_id)
+            .collect();
+        found.sort_by_key(|id| id.0);
+        let mut expected = vec![refunds.id, shipping.id];
+        expected.sort_by_key(|id| id.0);
+        assert_eq!(found, expected);
+
+        store.drop_index().await.unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_filters_apply_to_both_retrievers() {
+        let root = std::env::temp_dir().join(format!("cortex-local-{}", uuid::Uuid::new_v4()));
+        let user_id = UserId::new();
+        let mut old = chunk(user_id, DocumentId::new(), "Refunds take 30 days");
+        old.updated_at = "2023-01-01T00:00:00Z".parse().unwrap();
+        old.metadata = serde_json::json!({ "tags": ["archive"] });
+        let new = chunk(user_id, DocumentId::new(), "Refunds take 14 days");
+
+        let store = LocalVectorStore::new(&root);
+        store
+            .upsert_chunks(
+                &[old.clone(), new.clone()],
+                &[vec![1.0, 0.0], vec![0.0, 1.0]],
+            )
+            .await
+            .unwrap();
+
+        let filter: FilterExpr = serde_json::from_value(serde_json::json!({
+            "or": [
+                { "updated_at": { "to": "2024-01-01T00:00:00Z" } },
+                { "not": { "metadata": { "key": "tags", "value": "archive" } } }
+            ]
+        }))
+        .unwrap();
+        let cases = [
+            (filter, vec![old.id, new.id]),
+            (
+                FilterExpr::ExcludeDocumentIds(vec![old.document_id]),
+                vec![new.id],
+            ),
+            (
+                FilterExpr::Title("handbook".to_string()),
+                vec![old.id, new.id],
+            ),
+            (FilterExpr::Title("policies".to_string()), vec![]),
+        ];
+        for (filter, expected) in cases {
+            let filters = SearchFilters {
+                filter: Some(filter.clone()),
+                ..Default::default()
+            };
+            for alpha in [0.0, 1.0] {
+                let mut found: Vec<ChunkId> = store
+                    .hybrid_search("refunds", &[1.0, 1.0], user_id, &filters, 5, alpha)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|r| r.chunk_id)
+                    .collect();
+                let mut expected = expected.clone();
+                found.sort_by_key(|id| id.0);
+                expected.sort_by_key(|id| id.0);
+                assert_eq!(found, expected, "{filter:?} with alpha {alpha}");
+            }
+        }
+
+        store.drop_index().await.unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,359 @@
+use async_trait::async_trait;
+use cortex_common::types::*;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::facets::{FacetCounter, FacetCounts, FacetRequest};
+use crate::filter::{self, FilterFields};
+use crate::hybrid;
+use crate::models::{Chunk, SearchFilters, SearchResult};
+use crate::vector::{VectorStore, VectorStoreError, DEFAULT_INDEX};
+
+type Indexes = HashMap<String, HashMap<ChunkId, StoredChunk>>;
+
+/// An in-process vector store with brute-force search. Nothing is persisted,
+/// so it is meant for development and tests rather than production.
+#[derive(Clone)]
+pub struct MemoryVectorStore {
+    indexes: Arc<RwLock<Indexes>>,
+    index: String,
+}
+
+struct StoredChunk {
+    chunk: Chunk,
+    vector: Vec<f32>,
+    /// Terms of the text, title and section title, for keyword search.
+    terms: Vec<String>,
+    metadata_fields: Vec<String>,
+}
+
+impl StoredChunk {
+    fn filter_fields(&self) -> FilterFields<'_> {
+        FilterFields {
+            document_id: self.chunk.document_id,
+            document_title: &self.chunk.document_title,
+            section_title: self.chunk.section_title.as_deref(),
+            indexed_at: self.chunk.indexed_at,
+            updated_at: self.chunk.updated_at,
+            metadata_fields: &self.metadata_fields,
+        }
+    }
+}
+
+impl Default for MemoryVectorStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryVectorStore {
+    pub fn new() -> Self {
+        Self {
+            indexes: Arc::default(),
+            index: DEFAULT_INDEX.to_string(),
+        }
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Indexes> {
+        self.indexes.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Indexes> {
+        self.indexes.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn retain(&self, keep: impl Fn(&Chunk) -> bool) {
+        if let Some(chunks) = self.write().get_mut(&self.index) {
+            chunks.retain(|_, stored| keep(&stored.chunk));
+        }
+    }
+}
+
+#[async_trait]
+impl VectorStore for MemoryVectorStore {
+    fn with_index(&self, name: &str) -> Arc<dyn VectorStore> {
+        Arc::new(Self {
+            indexes: self.indexes.clone(),
+            index: name.to_string(),
+        })
+    }
+
+    fn index_name(&self) -> &str {
+        &self.index
+    }
+
+    async fn ensure_index(&self) -> Result<(), VectorStoreError> {
+        self.write().entry(self.index.clone()).or_default();
+        Ok(())
+    }
+
+    async fn drop_index(&self) -> Result<(), VectorStoreError> {
+        self.write().remove(&self.index);
+        Ok(())
+    }
+
+    async fn upsert_chunks(
+        &self,
+        chunks: &[Chunk],
+        vectors: &[Vec<f32>],
+    ) -> Result<(), VectorStoreError> {
+        if chunks.len() != vectors.len() {
+            return Err(VectorStoreError::InvalidInput(format!(
+                "{} chunks but {} vectors",
+                chunks.len(),
+                vectors.len()
+            )));
+        }
+
+        let mut indexes = self.write();
+        let index = indexes.entry(self.index.clone()).or_default();
+        for (chunk, vector) in chunks.iter().zip(vectors) {
+            let searchable = [
+                chunk.text.as_str(),
+                &chunk.document_title,
+                chunk.section_title.as_deref().unwrap_or_default(),
+            ]
+            .join(" ");
+            index.insert(
+                chunk.id,
+                StoredChunk {
+                    chunk: chunk.clone(),
+                    vector: vector.clone(),
+                    terms: hybrid::tokenize(&searchable),
+                    metadata_fields: filter::metadata_fields(&chunk.metadata),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    async fn hybrid_search(
+        &self,
+        query: &str,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+        alpha: f32,
+    ) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let indexes = self.read();
+        let candidates = candidates(indexes.get(&self.index), user_id, filters);
+
+        // Like Weaviate, each side only contributes its own top `limit` hits
+        let keyword = if alpha < 1.0 {
+            keyword_scores(&candidates, query, limit)
+        } else {
+            Vec::new()
+        };
+        let semantic = if alpha > 0.0 {
+            vector_scores(&candidates, vector, limit)
+        } else {
+            Vec::new()
+        };
+
+        let results = hybrid::relative_score_fusion(&keyword, &semantic, alpha)
+            .into_iter()
+            .take(limit)
+            .map(|(i, score)| search_result(&candidates[i].chunk, score))
+            .collect();
+        Ok(results)
+    }
+
+    async fn keyword_search(
+        &self,
+        query: &str,
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let indexes = self.read();
+        let candidates = candidates(indexes.get(&self.index), user_id, filters);
+        Ok(keyword_scores(&candidates, query, limit)
+            .into_iter()
+            .map(|(i, score)| search_result(&candidates[i].chunk, score))
+            .collect())
+    }
+
+    async fn vector_search(
+        &self,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let indexes = self.read();
+        let candidates = candidates(indexes.get(&self.index), user_id, filters);
+        Ok(vector_scores(&candidates, vector, limit)
+            .into_iter()
+            .map(|(i, score)| search_result(&candidates[i].chunk, score))
+            .collect())
+    }
+
+    async fn facet_counts(
+        &self,
+        query: &str,
+        user_id: UserId,
+        filters: &SearchFilters,
+        request: &FacetRequest,
+    ) -> Result<FacetCounts, VectorStoreError> {
+        let terms = hybrid::tokenize(query);
+        let indexes = self.read();
+        let mut counter = FacetCounter::new(request);
+        for stored in candidates(indexes.get(&self.index), user_id, filters) {
+            if terms.is_empty() || terms.iter().any(|t| stored.terms.contains(t)) {
+                counter.add_chunk(
+                    &stored.chunk.source_type.to_string(),
+                    &stored.filter_fields(),
+                );
+            }
+        }
+        Ok(counter.finish())
+    }
+
+    async fn chunk_ids_by_document(
+        &self,
+        document_id: DocumentId,
+    ) -> Result<Vec<ChunkId>, VectorStoreError> {
+        Ok(self
+            .read()
+            .get(&self.index)
+            .map(|chunks| {
+                chunks
+                    .values()
+                    .filter(|stored| stored.chunk.document_id == document_id)
+                    .map(|stored| stored.chunk.id)
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn delete_chunks_by_ids(&self, ids: &[ChunkId]) -> Result<(), VectorStoreError> {
+        if let Some(chunks) = self.write().get_mut(&self.index) {
+            for id in ids {
+                chunks.remove(id);
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_chunks_by_documents(
+        &self,
+        document_ids: &[DocumentId],
+    ) -> Result<(), VectorStoreError> {
+        self.retain(|chunk| !document_ids.contains(&chunk.document_id));
+        Ok(())
+    }
+
+    async fn delete_chunks_by_user(&self, user_id: UserId) -> Result<(), VectorStoreError> {
+        self.retain(|chunk| chunk.user_id != user_id);
+        Ok(())
+    }
+
+    async fn chunk_counts_by_document(
+        &self,
+        user_id: UserId,
+    ) -> Result<HashMap<DocumentId, usize>, VectorStoreError> {
+        let mut counts = HashMap::new();
+        if let Some(chunks) = self.read().get(&self.index) {
+            for stored in chunks.values().filter(|s| s.chunk.user_id == user_id) {
+                *counts.entry(stored.chunk.document_id).or_default() += 1;
+            }
+        }
+        Ok(counts)
+    }
+
+    async fn chunk_vectors(
+        &self,
+        ids: &[ChunkId],
+    ) -> Result<HashMap<ChunkId, Vec<f32>>, VectorStoreError> {
+        let indexes = self.read();
+        let Some(index) = indexes.get(&self.index) else {
+            return Ok(HashMap::new());
+        };
+        Ok(ids
+            .iter()
+            .filter_map(|id| Some((*id, index.get(id)?.vector.clone())))
+            .collect())
+    }
+
+    async fn chunk_owners(&self) -> Result<Vec<UserId>, VectorStoreError> {
+        let mut owners: Vec<UserId> = self
+            .read()
+            .get(&self.index)
+            .map(|chunks| chunks.values().map(|s| s.chunk.user_id).collect())
+            .unwrap_or_default();
+        owners.sort_by_key(|id| id.0);
+        owners.dedup();
+        Ok(owners)
+    }
+
+    async fn health_check(&self) -> Result<bool, VectorStoreError> {
+        Ok(true)
+    }
+}
+
+/// The user's chunks that pass the filters.
+fn candidates<'a>(
+    index: Option<&'a HashMap<ChunkId, StoredChunk>>,
+    user_id: UserId,
+    filters: &SearchFilters,
+) -> Vec<&'a StoredChunk> {
+    let Some(index) = index else {
+        return Vec::new();
+    };
+    index
+        .values()
+        .filter(|stored| {
+            let chunk = &stored.chunk;
+            chunk.user_id == user_id
+                && filters
+                    .source_type
+                    .as_ref()
+                    .is_none_or(|t| chunk.source_type.to_string() == *t)
+                && filters.chunk_kind.is_none_or(|k| chunk.kind == k)
+                && filters
+                    .filter
+                    .as_ref()
+                    .is_none_or(|f| f.matches(&stored.filter_fields()))
+        })
+        .collect()
+}
+
+/// The `limit` best BM25 matches among `candidates`, by position.
+fn keyword_scores(candidates: &[&StoredChunk], query: &str, limit: usize) -> Vec<(usize, f32)> {
+    let terms: Vec<Vec<String>> = candidates.iter().map(|c| c.terms.clone()).collect();
+    let scores = hybrid::bm25_scores(query, &terms);
+    hybrid::top_k(
+        scores
+            .into_iter()
+            .enumerate()
+            .filter(|(_, score)| *score > 0.0),
+        limit,
+    )
+}
+
+/// The `limit` candidates most similar to `vector`, by position.
+fn vector_scores(candidates: &[&StoredChunk], vector: &[f32], limit: usize) -> Vec<(usize, f32)> {
+    hybrid::top_k(
+        candidates
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (i, hybrid::cosine_similarity(vector, &c.vector))),
+        limit,
+    )
+}
+
+fn search_result(chunk: &Chunk, score: f32) -> SearchResult {
+    SearchResult {
+        chunk_id: chunk.id,
+        document_id: chunk.document_id,
+        text: chunk.text.clone(),
+        score,
+        document_title: chunk.document_title.clone(),
+        source_type: chunk.source_type,
+        source_url: chunk.source_url.clone(),
+        section_title: chunk.section_title.clone(),
+        kind: chunk.kind,
+        parent_id: chunk.parent_id,
+    }
+}
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS parent_chunks (
+    id              UUID PRIMARY KEY,
+    document_id     UUID NOT NULL REFERENCES documents(id) ON DELETE CASCADE,
+    user_id         UUID NOT NULL,
+    chunk_index     INTEGER NOT NULL,
+    text            TEXT NOT NULL,
+    section_title   TEXT,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_parent_chunks_document ON parent_chunks(document_id);
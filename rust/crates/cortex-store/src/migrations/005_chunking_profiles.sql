@@ -0,0 +1,2 @@
+ALTER TABLE documents ADD COLUMN IF NOT EXISTS chunking_profile JSONB;
+ALTER TABLE connectors ADD COLUMN IF NOT EXISTS chunking_profile JSONB;
@@ -0,0 +1,19 @@
+ALTER TABLE documents ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'indexed';
+ALTER TABLE documents ADD COLUMN IF NOT EXISTS error_message TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_documents_status ON documents(status) WHERE status <> 'indexed';
+
+-- Pending vector-store writes. A row exists from the moment a document is
+-- marked 'indexing' until its chunks are confirmed in the vector store, and
+-- holds everything needed to retry the ingest.
+CREATE TABLE IF NOT EXISTS ingest_outbox (
+    document_id     UUID PRIMARY KEY REFERENCES documents(id) ON DELETE CASCADE,
+    user_id         UUID NOT NULL,
+    payload         JSONB NOT NULL,
+    attempts        INTEGER NOT NULL DEFAULT 0,
+    last_error      TEXT,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ingest_outbox_updated ON ingest_outbox(updated_at);
@@ -0,0 +1,2 @@
+-- Structured output of jobs that produce a report (e.g. reconciliation)
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS result JSONB;
@@ -0,0 +1,8 @@
+-- The vector-store class and embedding model each user's chunks are indexed
+-- with. Users without a row use the shared default class and model.
+CREATE TABLE IF NOT EXISTS search_indexes (
+    user_id         UUID PRIMARY KEY,
+    class_name      TEXT NOT NULL,
+    embedding_model TEXT,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,27 @@
+-- Original document text, so documents can be re-chunked and re-embedded
+-- without going back to the source. Shared by documents with identical content.
+CREATE TABLE IF NOT EXISTS document_contents (
+    content_hash    TEXT PRIMARY KEY,
+    content         TEXT NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Mirror of the child chunks held in the vector store, so chunk text and its
+-- position in the document can be read without querying Weaviate.
+CREATE TABLE IF NOT EXISTS chunks (
+    id              UUID PRIMARY KEY,
+    document_id     UUID NOT NULL REFERENCES documents(id) ON DELETE CASCADE,
+    user_id         UUID NOT NULL,
+    parent_id       UUID,
+    chunk_index     INTEGER NOT NULL,
+    text            TEXT NOT NULL,
+    section_title   TEXT,
+    kind            TEXT NOT NULL DEFAULT 'text',
+    context_header  TEXT,
+    -- Byte offsets into the retained document content, where they could be located
+    start_offset    INTEGER,
+    end_offset      INTEGER,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_chunks_document ON chunks(document_id, chunk_index);
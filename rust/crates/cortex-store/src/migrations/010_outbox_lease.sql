@@ -0,0 +1,4 @@
+-- Until when the ingest holding an outbox entry is presumed alive. The
+-- reconciler only claims entries whose lease has run out; entries written
+-- before the lease existed fall back to their last update.
+ALTER TABLE ingest_outbox ADD COLUMN IF NOT EXISTS claimed_until TIMESTAMPTZ;
@@ -1,7 +1,10 @@
 use chrono::{DateTime, Utc};
+use cortex_common::chunking::{ChunkingProfile, ProfileSelector};
 use cortex_common::types::*;
 use serde::{Deserialize, Serialize};
 
+use crate::filter::FilterExpr;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Document {
     pub id: DocumentId,
@@ -14,6 +17,10 @@ pub struct Document {
     pub chunk_count: i32,
     pub mime_type: Option<String>,
     pub metadata: serde_json::Value,
+    /// The chunking profile the current chunks were produced with.
+    pub chunking_profile: Option<ChunkingProfile>,
+    pub status: DocumentStatus,
+    pub error_message: Option<String>,
     pub indexed_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -27,6 +34,8 @@ pub struct Connector {
     pub last_sync_at: Option<DateTime<Utc>>,
     pub sync_cursor: Option<String>,
     pub error_message: Option<String>,
+    /// Overrides the source-type default chunking profile for this connector.
+    pub chunking_profile: Option<ProfileSelector>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -41,6 +50,8 @@ pub struct Job {
     pub total_items: i32,
     pub processed_items: i32,
     pub error_message: Option<String>,
+    /// Report produced by the job, if it has one.
+    pub result: Option<serde_json::Value>,
     pub started_at: Option<DateTime<Utc>>,
     pub completed_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
@@ -58,7 +69,48 @@ pub struct Chunk {
     pub source_url: Option<String>,
     pub chunk_index: i32,
     pub section_title: Option<String>,
+    pub kind: ChunkKind,
+    /// The parent section this chunk was split from, if any.
+    pub parent_id: Option<ChunkId>,
+    /// Contextual header that was embedded together with `text`, if any.
+    pub context_header: Option<String>,
     pub metadata: serde_json::Value,
+    /// The document's timestamps, copied onto each chunk for date filters.
+    pub indexed_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Byte range of `text` in the document content, if it could be located.
+    /// Kept in Postgres only.
+    #[serde(default)]
+    pub start_offset: Option<i32>,
+    #[serde(default)]
+    pub end_offset: Option<i32>,
+}
+
+/// A child chunk as mirrored in Postgres.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkRecord {
+    pub id: ChunkId,
+    pub document_id: DocumentId,
+    pub parent_id: Option<ChunkId>,
+    pub chunk_index: i32,
+    pub text: String,
+    pub section_title: Option<String>,
+    pub kind: ChunkKind,
+    pub context_header: Option<String>,
+    pub start_offset: Option<i32>,
+    pub end_offset: Option<i32>,
+}
+
+/// A parent section stored in Postgres. Its child chunks are what gets
+/// embedded; the parent text is returned as retrieval context.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParentChunk {
+    pub id: ChunkId,
+    pub document_id: DocumentId,
+    pub user_id: UserId,
+    pub chunk_index: i32,
+    pub text: String,
+    pub section_title: Option<String>,
 }
 
 /// A search result returned from Weaviate.
@@ -72,6 +124,57 @@ pub struct SearchResult {
     pub source_type: SourceType,
     pub source_url: Option<String>,
     pub section_title: Option<String>,
+    pub kind: ChunkKind,
+    pub parent_id: Option<ChunkId>,
+}
+
+/// Optional restrictions applied to a search on top of the user scope.
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+    pub source_type: Option<String>,
+    pub chunk_kind: Option<ChunkKind>,
+    pub filter: Option<FilterExpr>,
+}
+
+/// Restricts which of a user's documents are listed or deleted.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentFilter {
+    pub source_type: Option<SourceType>,
+    /// Case-insensitive substring of the title.
+    pub title: Option<String>,
+    pub document_ids: Option<Vec<DocumentId>>,
+}
+
+impl DocumentFilter {
+    pub fn is_empty(&self) -> bool {
+        self.source_type.is_none() && self.title.is_none() && self.document_ids.is_none()
+    }
+}
+
+/// Order of a document listing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentSort {
+    /// Most recently updated first.
+    #[default]
+    UpdatedDesc,
+    UpdatedAsc,
+    TitleAsc,
+    TitleDesc,
+    /// Largest documents first.
+    ChunkCountDesc,
+}
+
+impl DocumentSort {
+    pub(crate) fn order_by(self) -> &'static str {
+        match self {
+            DocumentSort::UpdatedDesc => "updated_at DESC",
+            DocumentSort::UpdatedAsc => "updated_at ASC",
+            DocumentSort::TitleAsc => "title ASC",
+            DocumentSort::TitleDesc => "title DESC",
+            DocumentSort::ChunkCountDesc => "chunk_count DESC",
+        }
+    }
 }
 
 /// Parameters for creating a new document record.
@@ -86,6 +189,48 @@ pub struct CreateDocument {
     pub chunk_count: i32,
     pub mime_type: Option<String>,
     pub metadata: serde_json::Value,
+    pub chunking_profile: Option<ChunkingProfile>,
+}
+
+/// A pending vector-store write recorded in the ingest outbox.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub document_id: DocumentId,
+    pub user_id: UserId,
+    /// Everything needed to re-run the ingest, as written by the pipeline.
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The vector-store index a user's chunks live in, and the embedding model
+/// they were embedded with. Queries must be embedded with the same model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchIndex {
+    pub class_name: String,
+    /// `None` means the ML service's default model.
+    pub embedding_model: Option<String>,
+}
+
+impl Default for SearchIndex {
+    fn default() -> Self {
+        Self {
+            class_name: crate::vector::DEFAULT_INDEX.to_string(),
+            embedding_model: None,
+        }
+    }
+}
+
+/// A document rebuilt into a shadow index, waiting for the index swap.
+#[derive(Debug, Clone)]
+pub struct ReindexedDocument {
+    pub document_id: DocumentId,
+    pub chunking_profile: Option<ChunkingProfile>,
+    pub parents: Vec<ParentChunk>,
+    pub chunks: Vec<Chunk>,
+    pub chunk_count: i32,
 }
 
 /// Parameters for creating a new job.
@@ -0,0 +1,692 @@
+use ::pgvector::Vector;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use cortex_common::types::*;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+use crate::facets::{FacetCounter, FacetCounts, FacetRequest};
+use crate::filter::{self, DateRange, FilterExpr};
+use crate::hybrid;
+use crate::models::{Chunk, SearchFilters, SearchResult};
+use crate::vector::{self, VectorStore, VectorStoreError, DEFAULT_INDEX};
+
+/// Text search configuration used for keyword scoring.
+const TEXT_SEARCH_CONFIG: &str = "english";
+
+/// Text search configuration for title and section filters, which match
+/// words as written rather than stemmed.
+const FILTER_SEARCH_CONFIG: &str = "simple";
+
+/// pgvector's default and largest `hnsw.ef_search`.
+const HNSW_EF_SEARCH: usize = 40;
+const HNSW_MAX_EF_SEARCH: usize = 1000;
+
+/// The text keyword search and facet counts match against.
+const SEARCHABLE_TEXT: &str = "document_title || ' ' || coalesce(section_title, '') || ' ' || text";
+
+const RESULT_COLUMNS: &str = "id, document_id, text, document_title, source_type, source_url, \
+                              section_title, chunk_kind, parent_id";
+
+/// A vector store on Postgres with the pgvector extension, so a deployment
+/// needs no separate vector database.
+///
+/// Each index is its own table with an HNSW index over the embeddings and a
+/// `tsvector` over the searchable text. Needs pgvector 0.8 or later for
+/// iterative index scans. Keyword and vector search run as two
+/// queries and are fused in Rust the same way Weaviate fuses hybrid results.
+#[derive(Clone)]
+pub struct PgVectorStore {
+    pool: PgPool,
+    index: String,
+    /// Tables and dimensions whose HNSW index is known to exist, shared by
+    /// every index of the store.
+    vector_indexes: Arc<Mutex<HashSet<(String, usize)>>>,
+}
+
+impl PgVectorStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            index: DEFAULT_INDEX.to_string(),
+            vector_indexes: Arc::default(),
+        }
+    }
+
+    /// The table backing this index.
+    fn table(&self) -> Result<String, VectorStoreError> {
+        table_name(&self.index)
+    }
+
+    /// Make sure the HNSW index exists for vectors of this size. Embeddings
+    /// are stored without a fixed dimension, so the index is built over a cast
+    /// and only once the dimension is known. Creating it locks out writes,
+    /// so it's only attempted the first time a table sees a dimension.
+    async fn ensure_vector_index(&self, table: &str, dimensions: usize) -> Result<(), sqlx::Error> {
+        let key = (table.to_string(), dimensions);
+        if self.vector_indexes.lock().unwrap().contains(&key) {
+            return Ok(());
+        }
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS {table}_embedding ON {table} \
+             USING hnsw ((embedding::vector({dimensions})) vector_cosine_ops)"
+        ))
+        .execute(&self.pool)
+        .await?;
+        self.vector_indexes.lock().unwrap().insert(key);
+        Ok(())
+    }
+
+    async fn vector_hits(
+        &self,
+        table: &str,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<(SearchResult, f32)>, sqlx::Error> {
+        // The cast must match the index expression for the HNSW index to be used
+        let dimensions = vector.len();
+        let distance = format!("embedding::vector({dimensions}) <=> ");
+        let mut sql = QueryBuilder::<Postgres>::new(format!(
+            "WITH hits AS MATERIALIZED (SELECT {RESULT_COLUMNS}, 1 - ({distance}"
+        ));
+        sql.push_bind(Vector::from(vector.to_vec())).push(format!(
+            "::vector({dimensions})) AS score FROM {table} WHERE "
+        ));
+        push_scope(&mut sql, user_id, filters);
+        sql.push(format!(" ORDER BY {distance}"))
+            .push_bind(Vector::from(vector.to_vec()))
+            .push(format!("::vector({dimensions}) LIMIT "))
+            .push_bind(limit as i64)
+            // A relaxed scan may return neighbors slightly out of order
+            .push(") SELECT * FROM hits ORDER BY score DESC");
+
+        // The HNSW scan finds `ef_search` neighbors before the user and
+        // filters are applied; scan on until `limit` of them pass
+        let mut tx = self.pool.begin().await?;
+        let ef_search = limit.clamp(HNSW_EF_SEARCH, HNSW_MAX_EF_SEARCH);
+        sqlx::query(&format!("SET LOCAL hnsw.ef_search = {ef_search}"))
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("SET LOCAL hnsw.iterative_scan = relaxed_order")
+            .execute(&mut *tx)
+            .await?;
+        let rows = sql.build().fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(rows.iter().map(scored_result_from_row).collect())
+    }
+
+    async fn keyword_hits(
+        &self,
+        table: &str,
+        query: &str,
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<(SearchResult, f32)>, sqlx::Error> {
+        // Any term may match, like BM25; terms are alphanumeric so they are
+        // safe to join into tsquery syntax
+        let terms = hybrid::tokenize(query);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut sql = QueryBuilder::<Postgres>::new(format!(
+            "SELECT {RESULT_COLUMNS}, ts_rank_cd(search_vector, query)::float8 AS score \
+             FROM {table}, to_tsquery('{TEXT_SEARCH_CONFIG}', "
+        ));
+        sql.push_bind(terms.join(" | "))
+            .push(") query WHERE search_vector @@ query AND ");
+        push_scope(&mut sql, user_id, filters);
+        sql.push(" ORDER BY score DESC LIMIT ")
+            .push_bind(limit as i64);
+
+        let rows = sql.build().fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(scored_result_from_row).collect())
+    }
+
+    /// Chunks per value of `column` among those in scope that contain any
+    /// of `terms`, or all of them without terms. Words are matched as
+    /// written, not stemmed, like the other backends count them.
+    async fn grouped_counts<T>(
+        &self,
+        table: &str,
+        column: &str,
+        terms: &[String],
+        user_id: UserId,
+        filters: &SearchFilters,
+    ) -> Result<Vec<(T, i64)>, sqlx::Error>
+    where
+        T: for<'r> sqlx::Decode<'r, Postgres> + sqlx::Type<Postgres> + Send + Unpin,
+    {
+        let mut sql = QueryBuilder::<Postgres>::new(format!(
+            "SELECT {column} AS value, COUNT(*) AS count FROM {table} WHERE "
+        ));
+        if !terms.is_empty() {
+            sql.push(format!(
+                "to_tsvector('{FILTER_SEARCH_CONFIG}', {SEARCHABLE_TEXT}) \
+                 @@ to_tsquery('{FILTER_SEARCH_CONFIG}', "
+            ))
+            .push_bind(terms.join(" | "))
+            .push(") AND ");
+        }
+        push_scope(&mut sql, user_id, filters);
+        sql.push(" GROUP BY 1");
+        sql.build_query_as().fetch_all(&self.pool).await
+    }
+}
+
+#[async_trait]
+impl VectorStore for PgVectorStore {
+    fn with_index(&self, name: &str) -> Arc<dyn VectorStore> {
+        Arc::new(Self {
+            pool: self.pool.clone(),
+            index: name.to_string(),
+            vector_indexes: self.vector_indexes.clone(),
+        })
+    }
+
+    fn index_name(&self) -> &str {
+        &self.index
+    }
+
+    async fn ensure_index(&self) -> Result<(), VectorStoreError> {
+        let table = self.table()?;
+        sqlx::query("CREATE EXTENSION IF NOT EXISTS vector")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(&format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {table} (
+                id              UUID PRIMARY KEY,
+                document_id     UUID NOT NULL,
+                user_id         UUID NOT NULL,
+                text            TEXT NOT NULL,
+                source_type     TEXT NOT NULL,
+                document_title  TEXT NOT NULL,
+                source_url      TEXT,
+                chunk_index     INTEGER NOT NULL,
+                section_title   TEXT,
+                chunk_kind      TEXT NOT NULL,
+                parent_id       UUID,
+                context_header  TEXT,
+                metadata        JSONB NOT NULL DEFAULT '{{}}',
+                metadata_fields TEXT[] NOT NULL DEFAULT '{{}}',
+                indexed_at      TIMESTAMPTZ,
+                updated_at      TIMESTAMPTZ,
+                embedding       vector NOT NULL,
+                search_vector   tsvector GENERATED ALWAYS AS (
+                    to_tsvector('{TEXT_SEARCH_CONFIG}',
+                        document_title || ' ' || coalesce(section_title, '') || ' ' || text)
+                ) STORED
+            )
+            "#
+        ))
+        .execute(&self.pool)
+        .await?;
+
+        for statement in [
+            // Filter columns added after the first release
+            format!(
+                "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS metadata_fields TEXT[] NOT NULL DEFAULT '{{}}'"
+            ),
+            format!("ALTER TABLE {table} ADD COLUMN IF NOT EXISTS indexed_at TIMESTAMPTZ"),
+            format!("ALTER TABLE {table} ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ"),
+            format!("CREATE INDEX IF NOT EXISTS {table}_user ON {table}(user_id)"),
+            format!("CREATE INDEX IF NOT EXISTS {table}_document ON {table}(document_id)"),
+            format!(
+                "CREATE INDEX IF NOT EXISTS {table}_search ON {table} USING gin(search_vector)"
+            ),
+            format!(
+                "CREATE INDEX IF NOT EXISTS {table}_metadata ON {table} USING gin(metadata_fields)"
+            ),
+        ] {
+            sqlx::query(&statement).execute(&self.pool).await?;
+        }
+
+        tracing::info!(index = %self.index, %table, "pgvector index ready");
+        Ok(())
+    }
+
+    async fn drop_index(&self) -> Result<(), VectorStoreError> {
+        let table = self.table()?;
+        sqlx::query(&format!("DROP TABLE IF EXISTS {table}"))
+            .execute(&self.pool)
+            .await?;
+        self.vector_indexes
+            .lock()
+            .unwrap()
+            .retain(|(indexed, _)| *indexed != table);
+        Ok(())
+    }
+
+    async fn upsert_chunks(
+        &self,
+        chunks: &[Chunk],
+        vectors: &[Vec<f32>],
+    ) -> Result<(), VectorStoreError> {
+        if chunks.len() != vectors.len() {
+            return Err(VectorStoreError::InvalidInput(format!(
+                "{} chunks but {} vectors",
+                chunks.len(),
+                vectors.len()
+            )));
+        }
+        let Some(dimensions) = vectors.first().map(Vec::len) else {
+            return Ok(());
+        };
+
+        let table = self.table()?;
+        self.ensure_vector_index(&table, dimensions).await?;
+
+        let insert = format!(
+            r#"
+            INSERT INTO {table} (id, document_id, user_id, text, source_type, document_title,
+                                 source_url, chunk_index, section_title, chunk_kind, parent_id,
+                                 context_header, metadata, metadata_fields, indexed_at,
+                                 updated_at, embedding)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+            ON CONFLICT (id) DO UPDATE SET
+                text = EXCLUDED.text,
+                document_title = EXCLUDED.document_title,
+                source_url = EXCLUDED.source_url,
+                chunk_index = EXCLUDED.chunk_index,
+                section_title = EXCLUDED.section_title,
+                chunk_kind = EXCLUDED.chunk_kind,
+                parent_id = EXCLUDED.parent_id,
+                context_header = EXCLUDED.context_header,
+                metadata = EXCLUDED.metadata,
+                metadata_fields = EXCLUDED.metadata_fields,
+                indexed_at = EXCLUDED.indexed_at,
+                updated_at = EXCLUDED.updated_at,
+                embedding = EXCLUDED.embedding
+            "#
+        );
+
+        // All or nothing, like a Weaviate batch that reports no errors
+        let mut tx = self.pool.begin().await?;
+        for (chunk, vector) in chunks.iter().zip(vectors) {
+            if vector.len() != dimensions {
+                return Err(VectorStoreError::InvalidInput(format!(
+                    "mixed vector sizes {dimensions} and {}",
+                    vector.len()
+                )));
+            }
+            sqlx::query(&insert)
+                .bind(chunk.id.0)
+                .bind(chunk.document_id.0)
+                .bind(chunk.user_id.0)
+                .bind(&chunk.text)
+                .bind(chunk.source_type.to_string())
+                .bind(&chunk.document_title)
+                .bind(&chunk.source_url)
+                .bind(chunk.chunk_index)
+                .bind(&chunk.section_title)
+                .bind(chunk.kind.to_string())
+                .bind(chunk.parent_id.map(|id| id.0))
+                .bind(&chunk.context_header)
+                .bind(&chunk.metadata)
+                .bind(filter::metadata_fields(&chunk.metadata))
+                .bind(chunk.indexed_at)
+                .bind(chunk.updated_at)
+                .bind(Vector::from(vector.clone()))
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn hybrid_search(
+        &self,
+        query: &str,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+        alpha: f32,
+    ) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let table = self.table()?;
+        let keyword = if alpha < 1.0 {
+            self.keyword_hits(&table, query, user_id, filters, limit)
+                .await?
+        } else {
+            Vec::new()
+        };
+        let semantic = if alpha > 0.0 {
+            self.vector_hits(&table, vector, user_id, filters, limit)
+                .await?
+        } else {
+            Vec::new()
+        };
+
+        let mut results: HashMap<ChunkId, SearchResult> = HashMap::new();
+        let mut scores = |hits: Vec<(SearchResult, f32)>| -> Vec<(ChunkId, f32)> {
+            hits.into_iter()
+                .map(|(result, score)| {
+                    let id = result.chunk_id;
+                    results.insert(id, result);
+                    (id, score)
+                })
+                .collect()
+        };
+        let keyword = scores(keyword);
+        let semantic = scores(semantic);
+
+        Ok(hybrid::relative_score_fusion(&keyword, &semantic, alpha)
+            .into_iter()
+            .take(limit)
+            .filter_map(|(id, score)| {
+                let mut result = results.remove(&id)?;
+                result.score = score;
+                Some(result)
+            })
+            .collect())
+    }
+
+    async fn keyword_search(
+        &self,
+        query: &str,
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let table = self.table()?;
+        let hits = self
+            .keyword_hits(&table, query, user_id, filters, limit)
+            .await?;
+        Ok(hits.into_iter().map(|(result, _)| result).collect())
+    }
+
+    async fn vector_search(
+        &self,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let table = self.table()?;
+        let hits = self
+            .vector_hits(&table, vector, user_id, filters, limit)
+            .await?;
+        Ok(hits.into_iter().map(|(result, _)| result).collect())
+    }
+
+    async fn facet_counts(
+        &self,
+        query: &str,
+        user_id: UserId,
+        filters: &SearchFilters,
+        request: &FacetRequest,
+    ) -> Result<FacetCounts, VectorStoreError> {
+        let table = self.table()?;
+        let terms = hybrid::tokenize(query);
+        let mut counter = FacetCounter::new(request);
+
+        if request.source_type {
+            let counts: Vec<(String, i64)> = self
+                .grouped_counts(&table, "source_type", &terms, user_id, filters)
+                .await?;
+            for (source_type, count) in counts {
+                counter.add_source_type(&source_type, count as usize);
+            }
+        }
+        if request.document {
+            let counts: Vec<(Uuid, i64)> = self
+                .grouped_counts(&table, "document_id", &terms, user_id, filters)
+                .await?;
+            for (document_id, count) in counts {
+                counter.add_document(DocumentId(document_id), count as usize);
+            }
+        }
+        // Timestamps are per document, so there are few distinct values to
+        // bucket
+        if request.indexed_at.is_some() {
+            let counts: Vec<(Option<DateTime<Utc>>, i64)> = self
+                .grouped_counts(&table, "indexed_at", &terms, user_id, filters)
+                .await?;
+            for (indexed_at, count) in counts {
+                if let Some(indexed_at) = indexed_at {
+                    counter.add_indexed_at(indexed_at, count as usize);
+                }
+            }
+        }
+        if !request.metadata.is_empty() {
+            let counts: Vec<(String, i64)> = self
+                .grouped_counts(&table, "unnest(metadata_fields)", &terms, user_id, filters)
+                .await?;
+            for (field, count) in counts {
+                counter.add_metadata_field(&field, count as usize);
+            }
+        }
+
+        Ok(counter.finish())
+    }
+
+    async fn chunk_ids_by_document(
+        &self,
+        document_id: DocumentId,
+    ) -> Result<Vec<ChunkId>, VectorStoreError> {
+        let table = self.table()?;
+        let ids: Vec<Uuid> =
+            sqlx::query_scalar(&format!("SELECT id FROM {table} WHERE document_id = $1"))
+                .bind(document_id.0)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(ids.into_iter().map(ChunkId).collect())
+    }
+
+    async fn delete_chunks_by_ids(&self, ids: &[ChunkId]) -> Result<(), VectorStoreError> {
+        let table = self.table()?;
+        let ids: Vec<Uuid> = ids.iter().map(|id| id.0).collect();
+        sqlx::query(&format!("DELETE FROM {table} WHERE id = ANY($1)"))
+            .bind(&ids)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_chunks_by_documents(
+        &self,
+        document_ids: &[DocumentId],
+    ) -> Result<(), VectorStoreError> {
+        let table = self.table()?;
+        let ids: Vec<Uuid> = document_ids.iter().map(|id| id.0).collect();
+        sqlx::query(&format!("DELETE FROM {table} WHERE document_id = ANY($1)"))
+            .bind(&ids)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_chunks_by_user(&self, user_id: UserId) -> Result<(), VectorStoreError> {
+        let table = self.table()?;
+        sqlx::query(&format!("DELETE FROM {table} WHERE user_id = $1"))
+            .bind(user_id.0)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn chunk_counts_by_document(
+        &self,
+        user_id: UserId,
+    ) -> Result<HashMap<DocumentId, usize>, VectorStoreError> {
+        let table = self.table()?;
+        let rows = sqlx::query(&format!(
+            "SELECT document_id, COUNT(*) AS count FROM {table} WHERE user_id = $1 GROUP BY document_id"
+        ))
+        .bind(user_id.0)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| {
+                let count: i64 = r.get("count");
+                (DocumentId(r.get("document_id")), count as usize)
+            })
+            .collect())
+    }
+
+    async fn chunk_vectors(
+        &self,
+        ids: &[ChunkId],
+    ) -> Result<HashMap<ChunkId, Vec<f32>>, VectorStoreError> {
+        let table = self.table()?;
+        let ids: Vec<Uuid> = ids.iter().map(|id| id.0).collect();
+        let rows: Vec<(Uuid, Vector)> = sqlx::query_as(&format!(
+            "SELECT id, embedding FROM {table} WHERE id = ANY($1)"
+        ))
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(id, embedding)| (ChunkId(id), embedding.to_vec()))
+            .collect())
+    }
+
+    async fn chunk_owners(&self) -> Result<Vec<UserId>, VectorStoreError> {
+        let table = self.table()?;
+        let ids: Vec<Uuid> = sqlx::query_scalar(&format!("SELECT DISTINCT user_id FROM {table}"))
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(ids.into_iter().map(UserId).collect())
+    }
+
+    async fn health_check(&self) -> Result<bool, VectorStoreError> {
+        Ok(sqlx::query_scalar::<_, i32>("SELECT 1")
+            .fetch_one(&self.pool)
+            .await
+            .is_ok())
+    }
+}
+
+/// Index names become part of a table name, so only ASCII letters, digits
+/// and underscores are accepted. Shadow indexes (`Chunk_<uuid>`) fit well
+/// within Postgres's 63-character identifier limit.
+fn table_name(index: &str) -> Result<String, VectorStoreError> {
+    vector::check_index_name(index)?;
+    Ok(format!("vector_chunks_{}", index.to_ascii_lowercase()))
+}
+
+/// The conditions every search applies: the user scope, the simple filters
+/// and the filter expression.
+fn push_scope(sql: &mut QueryBuilder<'_, Postgres>, user_id: UserId, filters: &SearchFilters) {
+    sql.push("user_id = ").push_bind(user_id.0);
+    if let Some(source_type) = &filters.source_type {
+        sql.push(" AND source_type = ")
+            .push_bind(source_type.clone());
+    }
+    if let Some(kind) = filters.chunk_kind {
+        sql.push(" AND chunk_kind = ").push_bind(kind.to_string());
+    }
+    if let Some(filter) = &filters.filter {
+        sql.push(" AND ");
+        push_filter(sql, filter);
+    }
+}
+
+fn push_filter(sql: &mut QueryBuilder<'_, Postgres>, expr: &FilterExpr) {
+    match expr {
+        FilterExpr::And(operands) | FilterExpr::Or(operands) => {
+            let separator = if matches!(expr, FilterExpr::And(_)) {
+                " AND "
+            } else {
+                " OR "
+            };
+            sql.push("(");
+            for (i, operand) in operands.iter().enumerate() {
+                if i > 0 {
+                    sql.push(separator);
+                }
+                push_filter(sql, operand);
+            }
+            sql.push(")");
+        }
+        // Rows written before the timestamp columns existed compare as NULL;
+        // count them as not matching, so negations include them
+        FilterExpr::Not(operand) => {
+            sql.push("NOT coalesce(");
+            push_filter(sql, operand);
+            sql.push(", false)");
+        }
+        FilterExpr::IndexedAt(range) => push_date_range(sql, "indexed_at", range),
+        FilterExpr::UpdatedAt(range) => push_date_range(sql, "updated_at", range),
+        FilterExpr::DocumentIds(ids) => {
+            let ids: Vec<Uuid> = ids.iter().map(|id| id.0).collect();
+            sql.push("document_id = ANY(").push_bind(ids).push(")");
+        }
+        FilterExpr::ExcludeDocumentIds(ids) => {
+            let ids: Vec<Uuid> = ids.iter().map(|id| id.0).collect();
+            sql.push("document_id <> ALL(").push_bind(ids).push(")");
+        }
+        // Words are alphanumeric, so they are safe to join into tsquery syntax
+        FilterExpr::Title(value) | FilterExpr::Section(value) => {
+            let column = match expr {
+                FilterExpr::Title(_) => "document_title",
+                _ => "coalesce(section_title, '')",
+            };
+            sql.push(format!(
+                "to_tsvector('{FILTER_SEARCH_CONFIG}', {column}) @@ \
+                 to_tsquery('{FILTER_SEARCH_CONFIG}', "
+            ))
+            .push_bind(hybrid::tokenize(value).join(" & "))
+            .push(")");
+        }
+        FilterExpr::Metadata(condition) => {
+            sql.push("metadata_fields @> ARRAY[")
+                .push_bind(condition.field())
+                .push("]::text[]");
+        }
+    }
+}
+
+fn push_date_range(sql: &mut QueryBuilder<'_, Postgres>, column: &str, range: &DateRange) {
+    sql.push("(true");
+    if let Some(from) = range.from {
+        sql.push(format!(" AND {column} >= ")).push_bind(from);
+    }
+    if let Some(to) = range.to {
+        sql.push(format!(" AND {column} < ")).push_bind(to);
+    }
+    sql.push(")");
+}
+
+fn scored_result_from_row(row: &sqlx::postgres::PgRow) -> (SearchResult, f32) {
+    let source_type: String = row.get("source_type");
+    let kind: String = row.get("chunk_kind");
+    let score: f32 = row.get::<f64, _>("score") as f32;
+
+    let result = SearchResult {
+        chunk_id: ChunkId(row.get("id")),
+        document_id: DocumentId(row.get("document_id")),
+        text: row.get("text"),
+        score,
+        document_title: row.get("document_title"),
+        source_type: source_type.parse().unwrap_or(SourceType::PdfUpload),
+        source_url: row.get("source_url"),
+        section_title: row.get("section_title"),
+        kind: kind.parse().unwrap_or_default(),
+        parent_id: row.get::<Option<Uuid>, _>("parent_id").map(ChunkId),
+    };
+    (result, score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_name_rejects_unsafe_index_names() {
+        assert_eq!(table_name("Chunk").unwrap(), "vector_chunks_chunk");
+        assert!(table_name("Chunk_0f8fad5bd9cb469fa16570867728950e").is_ok());
+        assert!(table_name("Chunk; DROP TABLE documents").is_err());
+        assert!(table_name("").is_err());
+    }
+}
@@ -6,6 +6,11 @@ use uuid::Uuid;
 
 use crate::models::*;
 
+/// How long an ingest holds its outbox entry before the reconciler may take
+/// it over, presuming the ingest crashed. It covers a retry's parse and embed
+/// as well as the vector write.
+const OUTBOX_LEASE_SECS: i64 = 300;
+
 #[derive(Clone)]
 pub struct PostgresStore {
     pool: PgPool,
@@ -25,6 +30,13 @@ impl PostgresStore {
             include_str!("migrations/001_init.sql"),
             include_str!("migrations/002_connectors.sql"),
             include_str!("migrations/003_jobs.sql"),
+            include_str!("migrations/004_parent_chunks.sql"),
+            include_str!("migrations/005_chunking_profiles.sql"),
+            include_str!("migrations/006_ingest_outbox.sql"),
+            include_str!("migrations/007_job_results.sql"),
+            include_str!("migrations/008_reindex.sql"),
+            include_str!("migrations/009_chunks.sql"),
+            include_str!("migrations/010_outbox_lease.sql"),
         ];
 
         for (i, sql) in migrations.iter().enumerate() {
@@ -41,42 +53,18 @@ impl PostgresStore {
 
     // ── Documents ──
 
+    /// Insert a document, or update the existing row for the same source.
+    /// Returns the persisted id, which is the existing one on re-ingest.
     pub async fn create_document(&self, doc: &CreateDocument) -> Result<DocumentId, sqlx::Error> {
-        let id = Uuid::new_v4();
-        sqlx::query(
-            r#"
-            INSERT INTO documents (id, user_id, source_type, source_id, title, source_url, content_hash, chunk_count, mime_type, metadata)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
-            ON CONFLICT (user_id, source_type, source_id)
-            DO UPDATE SET
-                title = EXCLUDED.title,
-                content_hash = EXCLUDED.content_hash,
-                chunk_count = EXCLUDED.chunk_count,
-                metadata = EXCLUDED.metadata,
-                updated_at = NOW()
-            "#,
-        )
-        .bind(id)
-        .bind(doc.user_id.0)
-        .bind(doc.source_type.to_string())
-        .bind(&doc.source_id)
-        .bind(&doc.title)
-        .bind(&doc.source_url)
-        .bind(&doc.content_hash)
-        .bind(doc.chunk_count)
-        .bind(&doc.mime_type)
-        .bind(&doc.metadata)
-        .execute(&self.pool)
-        .await?;
-
-        Ok(DocumentId(id))
+        upsert_document(&self.pool, doc, DocumentStatus::Indexed).await
     }
 
     pub async fn get_document(&self, id: DocumentId) -> Result<Option<Document>, sqlx::Error> {
         let row = sqlx::query(
             r#"
             SELECT id, user_id, source_type, source_id, title, source_url,
-                   content_hash, chunk_count, mime_type, metadata, indexed_at, updated_at
+                   content_hash, chunk_count, mime_type, metadata, chunking_profile,
+                   status, error_message, indexed_at, updated_at
             FROM documents WHERE id = $1
             "#,
         )
@@ -87,33 +75,62 @@ impl PostgresStore {
         Ok(row.map(|r| document_from_row(&r)))
     }
 
+    /// A page of a user's documents matching `filter`.
     pub async fn list_documents(
         &self,
         user_id: UserId,
-        source_type: Option<&str>,
+        filter: &DocumentFilter,
+        sort: DocumentSort,
         limit: i64,
         offset: i64,
     ) -> Result<Vec<Document>, sqlx::Error> {
-        let rows = sqlx::query(
+        // The ORDER BY comes from a fixed set of columns, never from input
+        let query = format!(
             r#"
             SELECT id, user_id, source_type, source_id, title, source_url,
-                   content_hash, chunk_count, mime_type, metadata, indexed_at, updated_at
+                   content_hash, chunk_count, mime_type, metadata, chunking_profile,
+                   status, error_message, indexed_at, updated_at
             FROM documents
-            WHERE user_id = $1 AND ($2::text IS NULL OR source_type = $2)
-            ORDER BY updated_at DESC
-            LIMIT $3 OFFSET $4
+            WHERE {DOCUMENT_FILTER}
+            ORDER BY {}, id
+            LIMIT $5 OFFSET $6
             "#,
-        )
-        .bind(user_id.0)
-        .bind(source_type)
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(&self.pool)
-        .await?;
+            sort.order_by(),
+        );
+        let rows = bind_document_filter(sqlx::query(&query), user_id, filter)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await?;
 
         Ok(rows.iter().map(document_from_row).collect())
     }
 
+    pub async fn count_documents(
+        &self,
+        user_id: UserId,
+        filter: &DocumentFilter,
+    ) -> Result<i64, sqlx::Error> {
+        let query = format!("SELECT COUNT(*) FROM documents WHERE {DOCUMENT_FILTER}");
+        let row = bind_document_filter(sqlx::query(&query), user_id, filter)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row.get(0))
+    }
+
+    /// Ids of every document of a user matching `filter`.
+    pub async fn list_document_ids(
+        &self,
+        user_id: UserId,
+        filter: &DocumentFilter,
+    ) -> Result<Vec<DocumentId>, sqlx::Error> {
+        let query = format!("SELECT id FROM documents WHERE {DOCUMENT_FILTER}");
+        let rows = bind_document_filter(sqlx::query(&query), user_id, filter)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(|r| DocumentId(r.get("id"))).collect())
+    }
+
     pub async fn delete_document(&self, id: DocumentId) -> Result<bool, sqlx::Error> {
         let result = sqlx::query("DELETE FROM documents WHERE id = $1")
             .bind(id.0)
@@ -122,17 +139,99 @@ impl PostgresStore {
         Ok(result.rows_affected() > 0)
     }
 
+    /// Delete a user's documents by id, with their parent sections and chunk
+    /// records. Returns the content hash of each deleted document.
+    pub async fn delete_documents(
+        &self,
+        user_id: UserId,
+        ids: &[DocumentId],
+    ) -> Result<Vec<String>, sqlx::Error> {
+        let ids: Vec<Uuid> = ids.iter().map(|id| id.0).collect();
+        sqlx::query_scalar(
+            "DELETE FROM documents WHERE user_id = $1 AND id = ANY($2) RETURNING content_hash",
+        )
+        .bind(user_id.0)
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Every document a user owns, regardless of status.
+    pub async fn list_all_documents(&self, user_id: UserId) -> Result<Vec<Document>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user_id, source_type, source_id, title, source_url,
+                   content_hash, chunk_count, mime_type, metadata, chunking_profile,
+                   status, error_message, indexed_at, updated_at
+            FROM documents WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id.0)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(document_from_row).collect())
+    }
+
+    /// Users that own at least one document.
+    pub async fn list_document_owners(&self) -> Result<Vec<UserId>, sqlx::Error> {
+        let ids: Vec<Uuid> = sqlx::query_scalar("SELECT DISTINCT user_id FROM documents")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(ids.into_iter().map(UserId).collect())
+    }
+
+    /// Flag documents whose index needs rebuilding. They stop counting as
+    /// indexed, so the next upload or sync of the same content re-indexes them.
+    pub async fn mark_needs_reindex(
+        &self,
+        ids: &[DocumentId],
+        reason: &str,
+    ) -> Result<(), sqlx::Error> {
+        let ids: Vec<Uuid> = ids.iter().map(|id| id.0).collect();
+        sqlx::query(
+            "UPDATE documents SET status = 'failed', error_message = $2, updated_at = NOW() WHERE id = ANY($1)",
+        )
+        .bind(&ids)
+        .bind(reason)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The content hash of the version recorded for a source document.
+    pub async fn current_content_hash(
+        &self,
+        user_id: UserId,
+        source_type: &str,
+        source_id: &str,
+    ) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT content_hash FROM documents WHERE user_id = $1 AND source_type = $2 AND source_id = $3",
+        )
+        .bind(user_id.0)
+        .bind(source_type)
+        .bind(source_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Whether this exact version of a source document is already indexed.
     pub async fn has_content_hash(
         &self,
         user_id: UserId,
         source_type: &str,
+        source_id: &str,
         content_hash: &str,
     ) -> Result<bool, sqlx::Error> {
         let row = sqlx::query(
-            "SELECT EXISTS(SELECT 1 FROM documents WHERE user_id = $1 AND source_type = $2 AND content_hash = $3) as exists",
+            "SELECT EXISTS(SELECT 1 FROM documents WHERE user_id = $1 AND source_type = $2 AND source_id = $3 AND content_hash = $4 AND status = 'indexed') as exists",
         )
         .bind(user_id.0)
         .bind(source_type)
+        .bind(source_id)
         .bind(content_hash)
         .fetch_one(&self.pool)
         .await?;
@@ -140,6 +239,374 @@ impl PostgresStore {
         Ok(row.get::<bool, _>("exists"))
     }
 
+    // ── Two-phase indexing ──
+
+    /// Phase one: upsert the document as `indexing` and record `payload` in the
+    /// ingest outbox, in one transaction, leasing the entry to this ingest.
+    /// A different payload (a new version) starts with a fresh retry count;
+    /// the reconciler re-running the same payload keeps counting. Returns the
+    /// persisted document id.
+    pub async fn begin_indexing(
+        &self,
+        doc: &CreateDocument,
+        payload: &serde_json::Value,
+    ) -> Result<DocumentId, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let id = upsert_document(&mut *tx, doc, DocumentStatus::Indexing).await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO ingest_outbox (document_id, user_id, payload, claimed_until)
+            VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))
+            ON CONFLICT (document_id)
+            DO UPDATE SET payload = EXCLUDED.payload, claimed_until = EXCLUDED.claimed_until,
+                attempts = CASE WHEN ingest_outbox.payload = EXCLUDED.payload
+                    THEN ingest_outbox.attempts ELSE 0 END,
+                last_error = CASE WHEN ingest_outbox.payload = EXCLUDED.payload
+                    THEN ingest_outbox.last_error END,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(id.0)
+        .bind(doc.user_id.0)
+        .bind(payload)
+        .bind(OUTBOX_LEASE_SECS as f64)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    /// Phase two: the vector write is confirmed, so mark the document
+    /// `indexed` and clear its outbox entry.
+    pub async fn finish_indexing(
+        &self,
+        id: DocumentId,
+        chunk_count: i32,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE documents
+            SET status = 'indexed', chunk_count = $2, error_message = NULL, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id.0)
+        .bind(chunk_count)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM ingest_outbox WHERE document_id = $1")
+            .bind(id.0)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await
+    }
+
+    /// Record a failed ingest. The outbox entry stays for the reconciler.
+    pub async fn mark_indexing_failed(
+        &self,
+        id: DocumentId,
+        error: &str,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "UPDATE documents SET status = 'failed', error_message = $2, updated_at = NOW() WHERE id = $1",
+        )
+        .bind(id.0)
+        .bind(error)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE ingest_outbox
+            SET last_error = $2, updated_at = NOW()
+            WHERE document_id = $1
+            "#,
+        )
+        .bind(id.0)
+        .bind(error)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await
+    }
+
+    /// Claim the oldest outbox entry whose lease has run out and that has been
+    /// retried fewer than `max_attempts` times, leasing it to the caller and
+    /// counting the retry. Counting on claim means a retry that crashes or
+    /// fails before recording an error still counts. Entries another caller
+    /// is claiming are skipped rather than waited for.
+    pub async fn claim_outbox_entry(
+        &self,
+        max_attempts: i32,
+    ) -> Result<Option<OutboxEntry>, sqlx::Error> {
+        let row = sqlx::query(
+            r#"
+            UPDATE ingest_outbox
+            SET claimed_until = NOW() + make_interval(secs => $1), attempts = attempts + 1
+            WHERE document_id = (
+                SELECT document_id
+                FROM ingest_outbox
+                WHERE COALESCE(claimed_until, updated_at + make_interval(secs => $1)) < NOW()
+                    AND attempts < $2
+                ORDER BY updated_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING document_id, user_id, payload, attempts, last_error, created_at, updated_at
+            "#,
+        )
+        .bind(OUTBOX_LEASE_SECS as f64)
+        .bind(max_attempts)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(outbox_entry_from_row))
+    }
+
+    // ── Document content ──
+
+    /// Keep the original text of a document version for later reindexing.
+    pub async fn put_document_content(
+        &self,
+        content_hash: &str,
+        content: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO document_contents (content_hash, content) VALUES ($1, $2) ON CONFLICT (content_hash) DO NOTHING",
+        )
+        .bind(content_hash)
+        .bind(content)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_document_content(
+        &self,
+        content_hash: &str,
+    ) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar("SELECT content FROM document_contents WHERE content_hash = $1")
+            .bind(content_hash)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Whether any document, of any user, is at this version of its content.
+    pub async fn content_hash_in_use(&self, content_hash: &str) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM documents WHERE content_hash = $1)")
+            .bind(content_hash)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// Delete retained content no document refers to any more. Returns
+    /// whether it was deleted.
+    pub async fn delete_unreferenced_content(
+        &self,
+        content_hash: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM document_contents
+            WHERE content_hash = $1
+                AND NOT EXISTS (SELECT 1 FROM documents WHERE content_hash = $1)
+            "#,
+        )
+        .bind(content_hash)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // ── Search indexes ──
+
+    /// The index a user's chunks currently live in.
+    pub async fn get_search_index(&self, user_id: UserId) -> Result<SearchIndex, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT class_name, embedding_model FROM search_indexes WHERE user_id = $1",
+        )
+        .bind(user_id.0)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row
+            .map(|r| SearchIndex {
+                class_name: r.get("class_name"),
+                embedding_model: r.get("embedding_model"),
+            })
+            .unwrap_or_default())
+    }
+
+    /// Point a user at a rebuilt index and replace the parent sections, chunk
+    /// records and chunk counts of every rebuilt document, in one transaction.
+    pub async fn commit_reindex(
+        &self,
+        user_id: UserId,
+        index: &SearchIndex,
+        documents: &[ReindexedDocument],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        for doc in documents {
+            replace_chunks(&mut tx, doc.document_id, &doc.parents, &doc.chunks).await?;
+
+            sqlx::query(
+                "UPDATE documents SET chunk_count = $2, chunking_profile = $3 WHERE id = $1",
+            )
+            .bind(doc.document_id.0)
+            .bind(doc.chunk_count)
+            .bind(
+                doc.chunking_profile
+                    .as_ref()
+                    .and_then(|p| serde_json::to_value(p).ok()),
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO search_indexes (user_id, class_name, embedding_model)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id)
+            DO UPDATE SET
+                class_name = EXCLUDED.class_name,
+                embedding_model = EXCLUDED.embedding_model,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(user_id.0)
+        .bind(&index.class_name)
+        .bind(&index.embedding_model)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await
+    }
+
+    // ── Connectors ──
+
+    pub async fn get_connector(&self, id: ConnectorId) -> Result<Option<Connector>, sqlx::Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, user_id, source_type, status, last_sync_at, sync_cursor,
+                   error_message, chunking_profile, created_at, updated_at
+            FROM connectors WHERE id = $1
+            "#,
+        )
+        .bind(id.0)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| connector_from_row(&r)))
+    }
+
+    // ── Chunks ──
+
+    /// Replace all parent sections and chunk records of a document.
+    pub async fn replace_document_chunks(
+        &self,
+        document_id: DocumentId,
+        parents: &[ParentChunk],
+        chunks: &[Chunk],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        replace_chunks(&mut tx, document_id, parents, chunks).await?;
+        tx.commit().await
+    }
+
+    /// A document's chunk records, in document order.
+    pub async fn get_document_chunks(
+        &self,
+        document_id: DocumentId,
+    ) -> Result<Vec<ChunkRecord>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, document_id, parent_id, chunk_index, text, section_title, kind,
+                   context_header, start_offset, end_offset
+            FROM chunks WHERE document_id = $1
+            ORDER BY chunk_index
+            "#,
+        )
+        .bind(document_id.0)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(chunk_record_from_row).collect())
+    }
+
+    pub async fn get_chunks(&self, ids: &[ChunkId]) -> Result<Vec<ChunkRecord>, sqlx::Error> {
+        let ids: Vec<Uuid> = ids.iter().map(|id| id.0).collect();
+        let rows = sqlx::query(
+            r#"
+            SELECT id, document_id, parent_id, chunk_index, text, section_title, kind,
+                   context_header, start_offset, end_offset
+            FROM chunks WHERE id = ANY($1)
+            "#,
+        )
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(chunk_record_from_row).collect())
+    }
+
+    /// Chunks whose index falls in one of the inclusive `(document, first,
+    /// last)` ranges, ordered by document and index.
+    pub async fn get_chunk_ranges(
+        &self,
+        ranges: &[(DocumentId, i32, i32)],
+    ) -> Result<Vec<ChunkRecord>, sqlx::Error> {
+        let document_ids: Vec<Uuid> = ranges.iter().map(|r| r.0 .0).collect();
+        let firsts: Vec<i32> = ranges.iter().map(|r| r.1).collect();
+        let lasts: Vec<i32> = ranges.iter().map(|r| r.2).collect();
+        let rows = sqlx::query(
+            r#"
+            SELECT c.id, c.document_id, c.parent_id, c.chunk_index, c.text, c.section_title,
+                   c.kind, c.context_header, c.start_offset, c.end_offset
+            FROM chunks c
+            JOIN unnest($1::uuid[], $2::int[], $3::int[]) AS r(document_id, lo, hi)
+              ON c.document_id = r.document_id AND c.chunk_index BETWEEN r.lo AND r.hi
+            ORDER BY c.document_id, c.chunk_index
+            "#,
+        )
+        .bind(&document_ids)
+        .bind(&firsts)
+        .bind(&lasts)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(chunk_record_from_row).collect())
+    }
+
+    pub async fn get_parent_chunks(
+        &self,
+        ids: &[ChunkId],
+    ) -> Result<Vec<ParentChunk>, sqlx::Error> {
+        let ids: Vec<Uuid> = ids.iter().map(|id| id.0).collect();
+        let rows = sqlx::query(
+            r#"
+            SELECT id, document_id, user_id, chunk_index, text, section_title
+            FROM parent_chunks WHERE id = ANY($1)
+            "#,
+        )
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(parent_chunk_from_row).collect())
+    }
+
     // ── Jobs ──
 
     pub async fn create_job(&self, job: &CreateJob) -> Result<JobId, sqlx::Error> {
@@ -210,11 +677,25 @@ impl PostgresStore {
         Ok(())
     }
 
+    pub async fn set_job_result(
+        &self,
+        id: JobId,
+        result: &serde_json::Value,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE jobs SET result = $2 WHERE id = $1")
+            .bind(id.0)
+            .bind(result)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn get_job(&self, id: JobId) -> Result<Option<Job>, sqlx::Error> {
         let row = sqlx::query(
             r#"
             SELECT id, user_id, connector_id, job_type, status,
-                   total_items, processed_items, error_message,
+                   total_items, processed_items, error_message, result,
                    started_at, completed_at, created_at
             FROM jobs WHERE id = $1
             "#,
@@ -227,6 +708,141 @@ impl PostgresStore {
     }
 }
 
+/// Parameters `$1`–`$4`, bound by `bind_document_filter`.
+const DOCUMENT_FILTER: &str = r#"user_id = $1
+    AND ($2::text IS NULL OR source_type = $2)
+    AND ($3::text IS NULL OR title ILIKE '%' || $3 || '%')
+    AND ($4::uuid[] IS NULL OR id = ANY($4))"#;
+
+fn bind_document_filter<'q>(
+    query: sqlx::query::Query<'q, sqlx::Postgres, sqlx::postgres::PgArguments>,
+    user_id: UserId,
+    filter: &DocumentFilter,
+) -> sqlx::query::Query<'q, sqlx::Postgres, sqlx::postgres::PgArguments> {
+    query
+        .bind(user_id.0)
+        .bind(filter.source_type.map(|t| t.to_string()))
+        .bind(filter.title.as_deref().map(escape_like))
+        .bind(
+            filter
+                .document_ids
+                .as_ref()
+                .map(|ids| ids.iter().map(|id| id.0).collect::<Vec<Uuid>>()),
+        )
+}
+
+/// Escape LIKE wildcards so a title search matches its input literally.
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+async fn replace_chunks(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    document_id: DocumentId,
+    parents: &[ParentChunk],
+    chunks: &[Chunk],
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM chunks WHERE document_id = $1")
+        .bind(document_id.0)
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query("DELETE FROM parent_chunks WHERE document_id = $1")
+        .bind(document_id.0)
+        .execute(&mut **tx)
+        .await?;
+
+    for parent in parents {
+        sqlx::query(
+            r#"
+            INSERT INTO parent_chunks (id, document_id, user_id, chunk_index, text, section_title)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(parent.id.0)
+        .bind(document_id.0)
+        .bind(parent.user_id.0)
+        .bind(parent.chunk_index)
+        .bind(&parent.text)
+        .bind(&parent.section_title)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    for chunk in chunks {
+        sqlx::query(
+            r#"
+            INSERT INTO chunks (id, document_id, user_id, parent_id, chunk_index, text, section_title, kind, context_header, start_offset, end_offset)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            "#,
+        )
+        .bind(chunk.id.0)
+        .bind(document_id.0)
+        .bind(chunk.user_id.0)
+        .bind(chunk.parent_id.map(|p| p.0))
+        .bind(chunk.chunk_index)
+        .bind(&chunk.text)
+        .bind(&chunk.section_title)
+        .bind(chunk.kind.to_string())
+        .bind(&chunk.context_header)
+        .bind(chunk.start_offset)
+        .bind(chunk.end_offset)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn upsert_document<'e, E>(
+    executor: E,
+    doc: &CreateDocument,
+    status: DocumentStatus,
+) -> Result<DocumentId, sqlx::Error>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    // The id is only used for new rows; RETURNING yields the persisted one
+    let id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO documents (id, user_id, source_type, source_id, title, source_url, content_hash, chunk_count, mime_type, metadata, chunking_profile, status)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        ON CONFLICT (user_id, source_type, source_id)
+        DO UPDATE SET
+            title = EXCLUDED.title,
+            content_hash = EXCLUDED.content_hash,
+            chunk_count = EXCLUDED.chunk_count,
+            metadata = EXCLUDED.metadata,
+            chunking_profile = EXCLUDED.chunking_profile,
+            status = EXCLUDED.status,
+            error_message = NULL,
+            updated_at = NOW()
+        RETURNING id
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(doc.user_id.0)
+    .bind(doc.source_type.to_string())
+    .bind(&doc.source_id)
+    .bind(&doc.title)
+    .bind(&doc.source_url)
+    .bind(&doc.content_hash)
+    .bind(doc.chunk_count)
+    .bind(&doc.mime_type)
+    .bind(&doc.metadata)
+    .bind(
+        doc.chunking_profile
+            .as_ref()
+            .and_then(|p| serde_json::to_value(p).ok()),
+    )
+    .bind(status.to_string())
+    .fetch_one(executor)
+    .await?;
+
+    Ok(DocumentId(id))
+}
+
 fn document_from_row(row: &sqlx::postgres::PgRow) -> Document {
     Document {
         id: DocumentId(row.get("id")),
@@ -242,11 +858,81 @@ fn document_from_row(row: &sqlx::postgres::PgRow) -> Document {
         chunk_count: row.get("chunk_count"),
         mime_type: row.get("mime_type"),
         metadata: row.get("metadata"),
+        chunking_profile: row
+            .get::<Option<serde_json::Value>, _>("chunking_profile")
+            .and_then(|v| serde_json::from_value(v).ok()),
+        status: row
+            .get::<String, _>("status")
+            .parse()
+            .unwrap_or(DocumentStatus::Failed),
+        error_message: row.get("error_message"),
         indexed_at: row.get("indexed_at"),
         updated_at: row.get("updated_at"),
     }
 }
 
+fn outbox_entry_from_row(row: &sqlx::postgres::PgRow) -> OutboxEntry {
+    OutboxEntry {
+        document_id: DocumentId(row.get("document_id")),
+        user_id: UserId(row.get("user_id")),
+        payload: row.get("payload"),
+        attempts: row.get("attempts"),
+        last_error: row.get("last_error"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn connector_from_row(row: &sqlx::postgres::PgRow) -> Connector {
+    let status_str: String = row.get("status");
+
+    Connector {
+        id: ConnectorId(row.get("id")),
+        user_id: UserId(row.get("user_id")),
+        source_type: row
+            .get::<String, _>("source_type")
+            .parse()
+            .unwrap_or(SourceType::PdfUpload),
+        status: serde_json::from_str(&format!("\"{}\"", status_str))
+            .unwrap_or(ConnectorStatus::Error),
+        last_sync_at: row.get("last_sync_at"),
+        sync_cursor: row.get("sync_cursor"),
+        error_message: row.get("error_message"),
+        chunking_profile: row
+            .get::<Option<serde_json::Value>, _>("chunking_profile")
+            .and_then(|v| serde_json::from_value(v).ok()),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn parent_chunk_from_row(row: &sqlx::postgres::PgRow) -> ParentChunk {
+    ParentChunk {
+        id: ChunkId(row.get("id")),
+        document_id: DocumentId(row.get("document_id")),
+        user_id: UserId(row.get("user_id")),
+        chunk_index: row.get("chunk_index"),
+        text: row.get("text"),
+        section_title: row.get("section_title"),
+    }
+}
+
+fn chunk_record_from_row(row: &sqlx::postgres::PgRow) -> ChunkRecord {
+    let kind: String = row.get("kind");
+    ChunkRecord {
+        id: ChunkId(row.get("id")),
+        document_id: DocumentId(row.get("document_id")),
+        parent_id: row.get::<Option<Uuid>, _>("parent_id").map(ChunkId),
+        chunk_index: row.get("chunk_index"),
+        text: row.get("text"),
+        section_title: row.get("section_title"),
+        kind: kind.parse().unwrap_or_default(),
+        context_header: row.get("context_header"),
+        start_offset: row.get("start_offset"),
+        end_offset: row.get("end_offset"),
+    }
+}
+
 fn job_from_row(row: &sqlx::postgres::PgRow) -> Job {
     let job_type_str: String = row.get("job_type");
     let status_str: String = row.get("status");
@@ -261,6 +947,7 @@ fn job_from_row(row: &sqlx::postgres::PgRow) -> Job {
         total_items: row.get("total_items"),
         processed_items: row.get("processed_items"),
         error_message: row.get("error_message"),
+        result: row.get("result"),
         started_at: row.get("started_at"),
         completed_at: row.get("completed_at"),
         created_at: row.get("created_at"),
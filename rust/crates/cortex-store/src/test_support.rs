@@ -0,0 +1,101 @@
+//! Fixtures shared by the store's unit and integration tests, and a
+//! conformance check every `VectorStore` backend should pass.
+
+use cortex_common::types::*;
+
+use crate::models::{Chunk, SearchFilters};
+use crate::vector::VectorStore;
+
+/// A plain text chunk of a "Handbook" document.
+pub fn chunk(user_id: UserId, document_id: DocumentId, text: &str) -> Chunk {
+    Chunk {
+        id: ChunkId::new(),
+        document_id,
+        user_id,
+        text: text.to_string(),
+        source_type: SourceType::PdfUpload,
+        document_title: "Handbook".to_string(),
+        source_url: None,
+        chunk_index: 0,
+        section_title: None,
+        kind: ChunkKind::Text,
+        parent_id: None,
+        context_header: None,
+        metadata: serde_json::json!({}),
+        indexed_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+        start_offset: None,
+        end_offset: None,
+    }
+}
+
+/// Behavior every backend shares: hybrid search follows alpha and is scoped
+/// to the user and index, and deletes remove chunks from both retrievers.
+/// `store` should be a fresh index; it is dropped at the end.
+pub async fn check_conformance(store: &dyn VectorStore) {
+    let user_id = UserId::new();
+    let doc_id = DocumentId::new();
+    let refunds = chunk(user_id, doc_id, "Refunds are issued within 14 days");
+    let shipping = chunk(user_id, doc_id, "Orders ship the next business day");
+    let other_user = chunk(UserId::new(), DocumentId::new(), "Refunds for other users");
+    store.ensure_index().await.unwrap();
+    store
+        .upsert_chunks(
+            &[refunds.clone(), shipping.clone(), other_user],
+            &[
+                vec![0.0, 1.0, 0.0],
+                vec![1.0, 0.0, 0.0],
+                vec![0.0, 1.0, 0.0],
+            ],
+        )
+        .await
+        .unwrap();
+
+    let other_index = store.with_index(&format!("Chunk_{}", uuid::Uuid::new_v4().simple()));
+    other_index.ensure_index().await.unwrap();
+    other_index
+        .upsert_chunks(&[chunk(user_id, doc_id, "Refunds")], &[vec![0.0, 1.0, 0.0]])
+        .await
+        .unwrap();
+
+    let filters = SearchFilters::default();
+    let search =
+        |alpha| store.hybrid_search("refunds", &[1.0, 0.0, 0.0], user_id, &filters, 5, alpha);
+
+    // Keyword-only finds the refunds chunk even with a vector pointing elsewhere
+    let keyword = search(0.0).await.unwrap();
+    assert_eq!(keyword.len(), 1);
+    assert_eq!(keyword[0].chunk_id, refunds.id);
+
+    let semantic = search(1.0).await.unwrap();
+    assert_eq!(semantic[0].chunk_id, shipping.id);
+
+    let hybrid = search(0.5).await.unwrap();
+    assert_eq!(hybrid.len(), 2);
+
+    let stranger = store
+        .hybrid_search("refunds", &[1.0, 0.0, 0.0], UserId::new(), &filters, 5, 0.5)
+        .await
+        .unwrap();
+    assert!(stranger.is_empty());
+
+    store.delete_chunks_by_documents(&[doc_id]).await.unwrap();
+    assert!(store
+        .chunk_ids_by_document(doc_id)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(search(0.0).await.unwrap().is_empty());
+    assert!(search(1.0).await.unwrap().is_empty());
+    assert_eq!(
+        other_index
+            .chunk_ids_by_document(doc_id)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    other_index.drop_index().await.unwrap();
+    store.drop_index().await.unwrap();
+}
@@ -0,0 +1,167 @@
+use async_trait::async_trait;
+use cortex_common::config::{AppConfig, VectorStoreBackend};
+use cortex_common::types::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::facets::{FacetCounts, FacetRequest};
+use crate::local::LocalVectorStore;
+use crate::memory::MemoryVectorStore;
+use crate::models::{Chunk, SearchFilters, SearchResult};
+use crate::pgvector::PgVectorStore;
+use crate::postgres::PostgresStore;
+use crate::weaviate::{WeaviateError, WeaviateStore};
+
+/// The shared index chunks live in until a user's index is rebuilt.
+pub const DEFAULT_INDEX: &str = "Chunk";
+
+/// Used by the local backend when no path is configured.
+const DEFAULT_LOCAL_DIR: &str = "data/vectors";
+
+/// Storage and hybrid search of chunk embeddings.
+///
+/// A handle targets one named index (a Weaviate class, for example); users
+/// whose index was rebuilt are served from their own, see `SearchIndex`.
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// A handle on the same backend that reads and writes another index.
+    fn with_index(&self, name: &str) -> Arc<dyn VectorStore>;
+
+    fn index_name(&self) -> &str;
+
+    /// Create the index if it doesn't exist.
+    async fn ensure_index(&self) -> Result<(), VectorStoreError>;
+
+    /// Drop the index and every chunk in it.
+    async fn drop_index(&self) -> Result<(), VectorStoreError>;
+
+    /// Insert or replace chunks, one vector per chunk.
+    async fn upsert_chunks(
+        &self,
+        chunks: &[Chunk],
+        vectors: &[Vec<f32>],
+    ) -> Result<(), VectorStoreError>;
+
+    /// Hybrid keyword and vector search over one user's chunks. `alpha`
+    /// weights the two: 1.0 is pure vector search, 0.0 pure keyword search.
+    async fn hybrid_search(
+        &self,
+        query: &str,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+        alpha: f32,
+    ) -> Result<Vec<SearchResult>, VectorStoreError>;
+
+    /// Keyword search alone, best first, scored by the backend's ranking
+    /// function (BM25, or `ts_rank_cd` for pgvector). Lets callers fuse the
+    /// two retrievers themselves.
+    async fn keyword_search(
+        &self,
+        query: &str,
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, VectorStoreError>;
+
+    /// Vector search alone, best first, scored by cosine similarity.
+    async fn vector_search(
+        &self,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, VectorStoreError>;
+
+    /// Facet counts over one user's chunks that pass `filters` and contain
+    /// any term of `query`, or all of them if it has no terms. Vector search
+    /// matches every chunk, so the keyword match stands in for the matched
+    /// set.
+    async fn facet_counts(
+        &self,
+        query: &str,
+        user_id: UserId,
+        filters: &SearchFilters,
+        request: &FacetRequest,
+    ) -> Result<FacetCounts, VectorStoreError>;
+
+    /// Ids of all chunks currently stored for a document.
+    async fn chunk_ids_by_document(
+        &self,
+        document_id: DocumentId,
+    ) -> Result<Vec<ChunkId>, VectorStoreError>;
+
+    async fn delete_chunks_by_ids(&self, ids: &[ChunkId]) -> Result<(), VectorStoreError>;
+
+    async fn delete_chunks_by_documents(
+        &self,
+        document_ids: &[DocumentId],
+    ) -> Result<(), VectorStoreError>;
+
+    async fn delete_chunks_by_user(&self, user_id: UserId) -> Result<(), VectorStoreError>;
+
+    /// Number of chunks stored per document for one user.
+    async fn chunk_counts_by_document(
+        &self,
+        user_id: UserId,
+    ) -> Result<HashMap<DocumentId, usize>, VectorStoreError>;
+
+    /// Stored embeddings of the given chunks; chunks that don't exist are
+    /// left out.
+    async fn chunk_vectors(
+        &self,
+        ids: &[ChunkId],
+    ) -> Result<HashMap<ChunkId, Vec<f32>>, VectorStoreError>;
+
+    /// Users that own at least one chunk in this index.
+    async fn chunk_owners(&self) -> Result<Vec<UserId>, VectorStoreError>;
+
+    async fn health_check(&self) -> Result<bool, VectorStoreError>;
+}
+
+/// Build the configured vector store, targeting the default index.
+pub fn from_config(config: &AppConfig, postgres: &PostgresStore) -> Arc<dyn VectorStore> {
+    match config.vector_store.backend {
+        VectorStoreBackend::Weaviate => Arc::new(WeaviateStore::new(&config.weaviate_url)),
+        VectorStoreBackend::Pgvector => Arc::new(PgVectorStore::new(postgres.pool().clone())),
+        VectorStoreBackend::Memory => Arc::new(MemoryVectorStore::new()),
+        VectorStoreBackend::Local => Arc::new(LocalVectorStore::new(
+            config
+                .vector_store
+                .path
+                .as_deref()
+                .unwrap_or(DEFAULT_LOCAL_DIR),
+        )),
+    }
+}
+
+/// Index names end up in table names and paths, so only ASCII letters,
+/// digits and underscores are allowed.
+pub(crate) fn check_index_name(index: &str) -> Result<(), VectorStoreError> {
+    let valid = !index.is_empty()
+        && index.len() <= 48
+        && index.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(VectorStoreError::InvalidInput(format!(
+            "invalid index name: {index:?}"
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VectorStoreError {
+    #[error(transparent)]
+    Weaviate(#[from] WeaviateError),
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("search index error: {0}")]
+    Tantivy(#[from] tantivy::TantivyError),
+    #[error("vector index file error: {0}")]
+    Persistence(#[from] bincode::Error),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+}
@@ -0,0 +1,562 @@
+//! Typed builder for the GraphQL queries sent to Weaviate.
+//!
+//! Values are rendered as GraphQL literals instead of being spliced in with
+//! `format!`. Strings are escaped, filters become input objects with enum
+//! operators, and floats with no literal form (NaN, infinity) are rejected.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use std::fmt::Write;
+
+use super::WeaviateError;
+
+/// A GraphQL input value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Boolean(bool),
+    /// Rendered unquoted, like the `operator` of a filter.
+    Enum(&'static str),
+    List(Vec<Input>),
+    Object(Vec<(&'static str, Input)>),
+}
+
+impl Input {
+    fn render(&self, out: &mut String) -> Result<(), WeaviateError> {
+        match self {
+            // JSON string escapes are a subset of GraphQL's
+            Input::String(s) => out.push_str(&serde_json::Value::from(s.as_str()).to_string()),
+            Input::Int(i) => out.push_str(&i.to_string()),
+            Input::Float(f) => {
+                if !f.is_finite() {
+                    return Err(WeaviateError::InvalidQuery(format!(
+                        "{f} has no GraphQL literal"
+                    )));
+                }
+                // Debug keeps a fractional part, so the literal stays a Float
+                let _ = write!(out, "{f:?}");
+            }
+            Input::Boolean(b) => out.push_str(if *b { "true" } else { "false" }),
+            Input::Enum(name) => out.push_str(name),
+            Input::List(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    item.render(out)?;
+                }
+                out.push(']');
+            }
+            Input::Object(fields) => {
+                out.push('{');
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    out.push_str(name);
+                    out.push_str(": ");
+                    value.render(out)?;
+                }
+                out.push('}');
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Comparison operators of Weaviate's filter language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    GreaterThan,
+    GreaterThanEqual,
+    LessThan,
+    LessThanEqual,
+    Like,
+    ContainsAny,
+    ContainsAll,
+}
+
+impl Operator {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Operator::Equal => "Equal",
+            Operator::NotEqual => "NotEqual",
+            Operator::GreaterThan => "GreaterThan",
+            Operator::GreaterThanEqual => "GreaterThanEqual",
+            Operator::LessThan => "LessThan",
+            Operator::LessThanEqual => "LessThanEqual",
+            Operator::Like => "Like",
+            Operator::ContainsAny => "ContainsAny",
+            Operator::ContainsAll => "ContainsAll",
+        }
+    }
+}
+
+/// The value a filter condition compares against. The variant picks the
+/// `value*` field Weaviate expects.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterValue {
+    Text(String),
+    TextArray(Vec<String>),
+    Int(i64),
+    Number(f64),
+    Boolean(bool),
+    Date(DateTime<Utc>),
+}
+
+impl FilterValue {
+    fn field(&self) -> &'static str {
+        match self {
+            FilterValue::Text(_) => "valueText",
+            FilterValue::TextArray(_) => "valueTextArray",
+            FilterValue::Int(_) => "valueInt",
+            FilterValue::Number(_) => "valueNumber",
+            FilterValue::Boolean(_) => "valueBoolean",
+            FilterValue::Date(_) => "valueDate",
+        }
+    }
+
+    fn to_input(&self) -> Input {
+        match self {
+            FilterValue::Text(s) => Input::String(s.clone()),
+            FilterValue::TextArray(items) => {
+                Input::List(items.iter().cloned().map(Input::String).collect())
+            }
+            FilterValue::Int(i) => Input::Int(*i),
+            FilterValue::Number(n) => Input::Float(*n),
+            FilterValue::Boolean(b) => Input::Boolean(*b),
+            FilterValue::Date(d) => Input::String(rfc3339(d)),
+        }
+    }
+
+    fn to_json(&self) -> Result<serde_json::Value, WeaviateError> {
+        Ok(match self {
+            FilterValue::Text(s) => json!(s),
+            FilterValue::TextArray(items) => json!(items),
+            FilterValue::Int(i) => json!(i),
+            FilterValue::Number(n) if !n.is_finite() => {
+                return Err(WeaviateError::InvalidQuery(format!(
+                    "{n} is not a valid filter value"
+                )))
+            }
+            FilterValue::Number(n) => json!(n),
+            FilterValue::Boolean(b) => json!(b),
+            FilterValue::Date(d) => json!(rfc3339(d)),
+        })
+    }
+}
+
+impl From<&str> for FilterValue {
+    fn from(value: &str) -> Self {
+        FilterValue::Text(value.to_string())
+    }
+}
+
+impl From<String> for FilterValue {
+    fn from(value: String) -> Self {
+        FilterValue::Text(value)
+    }
+}
+
+impl From<Vec<String>> for FilterValue {
+    fn from(value: Vec<String>) -> Self {
+        FilterValue::TextArray(value)
+    }
+}
+
+impl From<i64> for FilterValue {
+    fn from(value: i64) -> Self {
+        FilterValue::Int(value)
+    }
+}
+
+impl From<f64> for FilterValue {
+    fn from(value: f64) -> Self {
+        FilterValue::Number(value)
+    }
+}
+
+impl From<bool> for FilterValue {
+    fn from(value: bool) -> Self {
+        FilterValue::Boolean(value)
+    }
+}
+
+impl From<DateTime<Utc>> for FilterValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        FilterValue::Date(value)
+    }
+}
+
+/// A `where` filter, usable both in GraphQL queries and REST batch deletes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Condition {
+        property: String,
+        operator: Operator,
+        value: FilterValue,
+    },
+}
+
+impl Filter {
+    pub fn condition(property: &str, operator: Operator, value: impl Into<FilterValue>) -> Self {
+        Filter::Condition {
+            property: property.to_string(),
+            operator,
+            value: value.into(),
+        }
+    }
+
+    pub fn equal(property: &str, value: impl Into<FilterValue>) -> Self {
+        Self::condition(property, Operator::Equal, value)
+    }
+
+    pub fn contains_any(property: &str, values: impl IntoIterator<Item = String>) -> Self {
+        Self::condition(
+            property,
+            Operator::ContainsAny,
+            values.into_iter().collect::<Vec<_>>(),
+        )
+    }
+
+    /// Conjunction of `filters`, without wrapping a single filter in `And`.
+    pub fn all(mut filters: Vec<Filter>) -> Self {
+        if filters.len() == 1 {
+            return filters.remove(0);
+        }
+        Filter::And(filters)
+    }
+
+    /// Disjunction of `filters`, without wrapping a single filter in `Or`.
+    pub fn any(mut filters: Vec<Filter>) -> Self {
+        if filters.len() == 1 {
+            return filters.remove(0);
+        }
+        Filter::Or(filters)
+    }
+
+    fn to_input(&self) -> Input {
+        match self {
+            Filter::And(operands) | Filter::Or(operands) => Input::Object(vec![
+                ("operator", Input::Enum(self.operator_name())),
+                (
+                    "operands",
+                    Input::List(operands.iter().map(Filter::to_input).collect()),
+                ),
+            ]),
+            Filter::Condition {
+                property,
+                operator,
+                value,
+            } => Input::Object(vec![
+                ("path", Input::List(vec![Input::String(property.clone())])),
+                ("operator", Input::Enum(operator.as_str())),
+                (value.field(), value.to_input()),
+            ]),
+        }
+    }
+
+    /// The REST JSON shape, as taken by batch deletes.
+    pub fn to_json(&self) -> Result<serde_json::Value, WeaviateError> {
+        Ok(match self {
+            Filter::And(operands) | Filter::Or(operands) => json!({
+                "operator": self.operator_name(),
+                "operands": operands
+                    .iter()
+                    .map(Filter::to_json)
+                    .collect::<Result<Vec<_>, _>>()?,
+            }),
+            Filter::Condition {
+                property,
+                operator,
+                value,
+            } => {
+                let mut condition = json!({
+                    "path": [property],
+                    "operator": operator.as_str(),
+                });
+                condition[value.field()] = value.to_json()?;
+                condition
+            }
+        })
+    }
+
+    fn operator_name(&self) -> &'static str {
+        match self {
+            Filter::And(_) => "And",
+            Filter::Or(_) => "Or",
+            Filter::Condition { operator, .. } => operator.as_str(),
+        }
+    }
+
+    fn properties(&self) -> Vec<&str> {
+        match self {
+            Filter::And(operands) | Filter::Or(operands) => {
+                operands.iter().flat_map(Filter::properties).collect()
+            }
+            Filter::Condition { property, .. } => vec![property],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Get,
+    Aggregate,
+}
+
+/// A `Get` or `Aggregate` query against one class.
+#[derive(Debug, Clone)]
+pub struct Query {
+    operation: Operation,
+    class: String,
+    arguments: Vec<(&'static str, Input)>,
+    /// Property names referenced by arguments, checked when building.
+    properties: Vec<String>,
+    selection: String,
+}
+
+impl Query {
+    pub fn get(class: &str) -> Self {
+        Self::new(Operation::Get, class)
+    }
+
+    pub fn aggregate(class: &str) -> Self {
+        Self::new(Operation::Aggregate, class)
+    }
+
+    fn new(operation: Operation, class: &str) -> Self {
+        Self {
+            operation,
+            class: class.to_string(),
+            arguments: Vec::new(),
+            properties: Vec::new(),
+            selection: String::new(),
+        }
+    }
+
+    /// Hybrid BM25 and vector search; `alpha` 1.0 is pure vector search.
+    pub fn hybrid(mut self, query: &str, vector: &[f32], alpha: f32) -> Self {
+        let vector = vector.iter().map(|v| Input::Float(f64::from(*v))).collect();
+        self.arguments.push((
+            "hybrid",
+            Input::Object(vec![
+                ("query", Input::String(query.to_string())),
+                ("vector", Input::List(vector)),
+                ("alpha", Input::Float(f64::from(alpha))),
+            ]),
+        ));
+        self
+    }
+
+    /// BM25 keyword search over the searchable properties.
+    pub fn bm25(mut self, query: &str) -> Self {
+        self.arguments.push((
+            "bm25",
+            Input::Object(vec![("query", Input::String(query.to_string()))]),
+        ));
+        self
+    }
+
+    /// Nearest neighbors of `vector`.
+    pub fn near_vector(mut self, vector: &[f32]) -> Self {
+        let vector = vector.iter().map(|v| Input::Float(f64::from(*v))).collect();
+        self.arguments.push((
+            "nearVector",
+            Input::Object(vec![("vector", Input::List(vector))]),
+        ));
+        self
+    }
+
+    pub fn filter(mut self, filter: &Filter) -> Self {
+        self.properties
+            .extend(filter.properties().into_iter().map(String::from));
+        self.arguments.push(("where", filter.to_input()));
+        self
+    }
+
+    pub fn group_by(mut self, property: &str) -> Self {
+        self.properties.push(property.to_string());
+        self.arguments.push((
+            "groupBy",
+            Input::List(vec![Input::String(property.to_string())]),
+        ));
+        self
+    }
+
+    /// Order results by `property`, smallest first.
+    pub fn sort_ascending(mut self, property: &str) -> Self {
+        self.properties.push(property.to_string());
+        self.arguments.push((
+            "sort",
+            Input::List(vec![Input::Object(vec![
+                (
+                    "path",
+                    Input::List(vec![Input::String(property.to_string())]),
+                ),
+                ("order", Input::Enum("asc")),
+            ])]),
+        ));
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.arguments.push(("limit", Input::Int(limit as i64)));
+        self
+    }
+
+    /// The selection set, written by us and never built from user input.
+    pub fn select(mut self, selection: &str) -> Self {
+        self.selection = selection.to_string();
+        self
+    }
+
+    pub fn build(&self) -> Result<String, WeaviateError> {
+        for name in std::iter::once(&self.class).chain(&self.properties) {
+            if !is_name(name) {
+                return Err(WeaviateError::InvalidQuery(format!(
+                    "{name:?} is not a valid GraphQL name"
+                )));
+            }
+        }
+
+        let operation = match self.operation {
+            Operation::Get => "Get",
+            Operation::Aggregate => "Aggregate",
+        };
+        let mut out = format!("{{ {operation} {{ {}", self.class);
+        if !self.arguments.is_empty() {
+            out.push('(');
+            for (i, (name, value)) in self.arguments.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                out.push_str(name);
+                out.push_str(": ");
+                value.render(&mut out)?;
+            }
+            out.push(')');
+        }
+        let _ = write!(out, " {{ {} }} }} }}", self.selection);
+        Ok(out)
+    }
+}
+
+/// One entry of a GraphQL response's `errors` array.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GraphQlError {
+    pub message: String,
+    /// Where in the response the error occurred, e.g. `["Get", "Chunk"]`.
+    #[serde(default)]
+    pub path: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    data: serde_json::Value,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+/// The `data` of a GraphQL response, or its `errors`. Weaviate answers 200
+/// for failed queries, with `data` set to null for the failed fields.
+pub fn into_data(body: serde_json::Value) -> Result<serde_json::Value, WeaviateError> {
+    let response: Response = serde_json::from_value(body)
+        .map_err(|e| WeaviateError::Query(format!("unexpected GraphQL response: {e}")))?;
+    if !response.errors.is_empty() {
+        return Err(WeaviateError::GraphQl(response.errors));
+    }
+    Ok(response.data)
+}
+
+/// Whether `name` matches GraphQL's `/[_A-Za-z][_0-9A-Za-z]*/`.
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+fn rfc3339(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_renders_escaped_input_objects() {
+        let filter = Filter::all(vec![
+            Filter::equal("userId", "a \"quoted\"\nid \\"),
+            Filter::condition("chunkIndex", Operator::GreaterThan, 3),
+        ]);
+        let query = Query::get("Chunk")
+            .hybrid("refunds", &[0.5, 1.0], 0.75)
+            .filter(&filter)
+            .limit(5)
+            .select("text")
+            .build()
+            .unwrap();
+        assert_eq!(
+            query,
+            r#"{ Get { Chunk(hybrid: {query: "refunds", vector: [0.5, 1.0], alpha: 0.75}, where: {operator: And, operands: [{path: ["userId"], operator: Equal, valueText: "a \"quoted\"\nid \\"}, {path: ["chunkIndex"], operator: GreaterThan, valueInt: 3}]}, limit: 5) { text } } }"#
+        );
+    }
+
+    #[test]
+    fn test_sort_renders_path_and_enum_order() {
+        let query = Query::get("Chunk")
+            .sort_ascending("chunkIndex")
+            .limit(2)
+            .select("chunkIndex")
+            .build()
+            .unwrap();
+        assert_eq!(
+            query,
+            r#"{ Get { Chunk(sort: [{path: ["chunkIndex"], order: asc}], limit: 2) { chunkIndex } } }"#
+        );
+        let injected = Query::get("Chunk").sort_ascending("a b").build();
+        assert!(matches!(injected, Err(WeaviateError::InvalidQuery(_))));
+    }
+
+    #[test]
+    fn test_invalid_values_and_names_are_rejected() {
+        let nan = Query::get("Chunk").hybrid("q", &[f32::NAN], 0.5).build();
+        assert!(matches!(nan, Err(WeaviateError::InvalidQuery(_))));
+        let inf = Query::get("Chunk")
+            .hybrid("q", &[1.0], f32::INFINITY)
+            .build();
+        assert!(matches!(inf, Err(WeaviateError::InvalidQuery(_))));
+
+        let injected = Query::get("Chunk")
+            .filter(&Filter::equal("userId) { text } #", "x"))
+            .build();
+        assert!(matches!(injected, Err(WeaviateError::InvalidQuery(_))));
+    }
+
+    #[test]
+    fn test_errors_array_becomes_typed_error() {
+        let body = json!({
+            "data": { "Get": { "Chunk": null } },
+            "errors": [{ "message": "no such prop", "path": ["Get", "Chunk"] }]
+        });
+        match into_data(body) {
+            Err(WeaviateError::GraphQl(errors)) => {
+                assert_eq!(errors[0].message, "no such prop");
+                assert_eq!(errors[0].path, vec![json!("Get"), json!("Chunk")]);
+            }
+            other => panic!("expected GraphQL errors, got {other:?}"),
+        }
+    }
+}
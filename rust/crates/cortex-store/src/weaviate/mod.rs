@@ -0,0 +1,962 @@
+pub mod graphql;
+
+use async_trait::async_trait;
+use cortex_common::types::*;
+use reqwest::Client;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::facets::{FacetCounter, FacetCounts, FacetRequest};
+use crate::filter::{self, DateRange, FilterExpr};
+use crate::hybrid;
+use crate::models::{Chunk, SearchFilters, SearchResult};
+use crate::vector::{VectorStore, VectorStoreError, DEFAULT_INDEX};
+use graphql::{Filter, GraphQlError, Operator, Query};
+
+#[derive(Clone)]
+pub struct WeaviateStore {
+    client: Client,
+    base_url: String,
+    /// The class chunks are read from and written to.
+    class: String,
+}
+
+/// Weaviate's default `QUERY_MAXIMUM_RESULTS`.
+const MAX_OBJECTS_PER_QUERY: usize = 10_000;
+/// Documents per batch delete when removing several documents.
+const DELETE_DOCUMENTS_PER_BATCH: usize = 100;
+
+impl WeaviateStore {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            class: DEFAULT_INDEX.to_string(),
+        }
+    }
+
+    /// Create the Chunk schema in Weaviate if it doesn't exist, and add any
+    /// properties that were introduced after the class was first created.
+    pub async fn ensure_schema(&self) -> Result<(), WeaviateError> {
+        let url = format!("{}/v1/schema/{}", self.base_url, self.class);
+        let resp = self.client.get(&url).send().await?;
+
+        if resp.status().is_success() {
+            let existing: serde_json::Value = resp.json().await?;
+            let existing_names: Vec<&str> = existing["properties"]
+                .as_array()
+                .map(|props| props.iter().filter_map(|p| p["name"].as_str()).collect())
+                .unwrap_or_default();
+
+            let url = format!("{}/v1/schema/{}/properties", self.base_url, self.class);
+            for property in chunk_properties() {
+                let name = property["name"].as_str().unwrap_or_default();
+                if existing_names.contains(&name) {
+                    continue;
+                }
+
+                let resp = self.client.post(&url).json(&property).send().await?;
+                if !resp.status().is_success() {
+                    let body = resp.text().await.unwrap_or_default();
+                    return Err(WeaviateError::SchemaCreation(body));
+                }
+                tracing::info!(class = %self.class, property = name, "Added property to Weaviate schema");
+            }
+
+            tracing::info!(class = %self.class, "Weaviate schema already exists");
+            return Ok(());
+        }
+
+        let schema = json!({
+            "class": self.class,
+            "description": "A semantically coherent text chunk from an indexed document",
+            "vectorizer": "none",
+            "vectorIndexType": "hnsw",
+            "vectorIndexConfig": {
+                "distance": "cosine",
+                "ef": 256,
+                "efConstruction": 128,
+                "maxConnections": 64
+            },
+            "properties": chunk_properties()
+        });
+
+        let url = format!("{}/v1/schema", self.base_url);
+        let resp = self.client.post(&url).json(&schema).send().await?;
+
+        if !resp.status().is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(WeaviateError::SchemaCreation(body));
+        }
+
+        tracing::info!(class = %self.class, "Created Weaviate schema");
+        Ok(())
+    }
+
+    /// Insert a chunk with its embedding vector.
+    pub async fn upsert_chunk(&self, chunk: &Chunk, vector: &[f32]) -> Result<(), WeaviateError> {
+        let object = chunk_object(&self.class, chunk, vector);
+
+        let url = format!("{}/v1/objects", self.base_url);
+        let resp = self.client.post(&url).json(&object).send().await?;
+
+        if !resp.status().is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(WeaviateError::Insert(body));
+        }
+
+        Ok(())
+    }
+
+    /// Batch insert multiple chunks with their embeddings.
+    async fn batch_upsert_chunks(
+        &self,
+        chunks: &[Chunk],
+        vectors: &[Vec<f32>],
+    ) -> Result<(), WeaviateError> {
+        let objects: Vec<_> = chunks
+            .iter()
+            .zip(vectors.iter())
+            .map(|(chunk, vector)| chunk_object(&self.class, chunk, vector))
+            .collect();
+
+        let batch = json!({ "objects": objects });
+        let url = format!("{}/v1/batch/objects", self.base_url);
+        let resp = self.client.post(&url).json(&batch).send().await?;
+
+        if !resp.status().is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(WeaviateError::Insert(body));
+        }
+
+        // The batch endpoint answers 200 even when individual objects fail
+        let results: Vec<serde_json::Value> = resp.json().await?;
+        let failed: Vec<&Vec<serde_json::Value>> = results
+            .iter()
+            .filter_map(|r| r["result"]["errors"]["error"].as_array())
+            .collect();
+
+        if !failed.is_empty() {
+            let messages: Vec<&str> = failed
+                .iter()
+                .flat_map(|errors| errors.iter())
+                .filter_map(|e| e["message"].as_str())
+                .collect();
+            return Err(WeaviateError::Insert(format!(
+                "{} of {} objects failed: {}",
+                failed.len(),
+                chunks.len(),
+                messages.join("; ")
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Hybrid search combining BM25 keyword search and vector similarity.
+    async fn hybrid_search(
+        &self,
+        query: &str,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+        alpha: f32,
+    ) -> Result<Vec<SearchResult>, WeaviateError> {
+        let query = Query::get(&self.class).hybrid(query, vector, alpha);
+        self.search(query, user_id, filters, limit, Score::Score)
+            .await
+    }
+
+    /// BM25 search alone.
+    async fn keyword_search(
+        &self,
+        query: &str,
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, WeaviateError> {
+        let query = Query::get(&self.class).bm25(query);
+        self.search(query, user_id, filters, limit, Score::Score)
+            .await
+    }
+
+    /// Vector search alone.
+    async fn vector_search(
+        &self,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, WeaviateError> {
+        let query = Query::get(&self.class).near_vector(vector);
+        self.search(query, user_id, filters, limit, Score::Distance)
+            .await
+    }
+
+    /// Run a search query over one user's chunks and read back the results.
+    async fn search(
+        &self,
+        query: Query,
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+        score: Score,
+    ) -> Result<Vec<SearchResult>, WeaviateError> {
+        let query = query
+            .filter(&scope_filter(user_id, filters))
+            .limit(limit)
+            .select(&format!(
+                "text documentId documentTitle sourceType sourceUrl sectionTitle \
+                 chunkIndex chunkKind parentId _additional {{ id {} }}",
+                score.field()
+            ));
+
+        let data = self.graphql(&query).await?;
+        let chunks = data["Get"][self.class.as_str()]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        let results = chunks
+            .into_iter()
+            .filter_map(|c| {
+                let chunk_id = c["_additional"]["id"].as_str()?.parse().ok()?;
+                let doc_id = c["documentId"].as_str()?.parse().ok()?;
+                let score = score.read(&c["_additional"]);
+
+                Some(SearchResult {
+                    chunk_id: ChunkId(chunk_id),
+                    document_id: DocumentId(doc_id),
+                    text: c["text"].as_str().unwrap_or_default().to_string(),
+                    score,
+                    document_title: c["documentTitle"].as_str().unwrap_or_default().to_string(),
+                    source_type: c["sourceType"]
+                        .as_str()
+                        .unwrap_or("pdf_upload")
+                        .parse()
+                        .unwrap_or(SourceType::PdfUpload),
+                    source_url: c["sourceUrl"].as_str().map(String::from),
+                    section_title: c["sectionTitle"].as_str().map(String::from),
+                    kind: c["chunkKind"]
+                        .as_str()
+                        .and_then(|k| k.parse().ok())
+                        .unwrap_or_default(),
+                    parent_id: c["parentId"]
+                        .as_str()
+                        .and_then(|s| s.parse().ok())
+                        .map(ChunkId),
+                })
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Facet counts from `Aggregate` queries grouped by each facet's
+    /// property, over the chunks in scope that contain a query term.
+    async fn facet_counts(
+        &self,
+        query: &str,
+        user_id: UserId,
+        filters: &SearchFilters,
+        request: &FacetRequest,
+    ) -> Result<FacetCounts, WeaviateError> {
+        let mut operands = vec![scope_filter(user_id, filters)];
+        let terms = hybrid::tokenize(query);
+        if !terms.is_empty() {
+            let any_term = ["text", "documentTitle", "sectionTitle"]
+                .map(|property| Filter::contains_any(property, terms.clone()));
+            operands.push(Filter::any(any_term.to_vec()));
+        }
+        let filter = Filter::all(operands);
+        let mut counter = FacetCounter::new(request);
+
+        if request.source_type {
+            let groups = self.count_grouped_by("sourceType", Some(&filter)).await?;
+            for (source_type, count) in groups {
+                counter.add_source_type(&source_type, count);
+            }
+        }
+        if request.document {
+            let groups = self.count_grouped_by("documentId", Some(&filter)).await?;
+            for (id, count) in groups {
+                if let Ok(id) = id.parse() {
+                    counter.add_document(DocumentId(id), count);
+                }
+            }
+        }
+        // Timestamps are per document, so there are few distinct values to
+        // bucket
+        if request.indexed_at.is_some() {
+            let groups = self.count_grouped_by("indexedAt", Some(&filter)).await?;
+            for (indexed_at, count) in groups {
+                if let Ok(indexed_at) = chrono::DateTime::parse_from_rfc3339(&indexed_at) {
+                    counter.add_indexed_at(indexed_at.to_utc(), count);
+                }
+            }
+        }
+        if !request.metadata.is_empty() {
+            let groups = self
+                .count_grouped_by("metadataFields", Some(&filter))
+                .await?;
+            for (field, count) in groups {
+                counter.add_metadata_field(&field, count);
+            }
+        }
+
+        Ok(counter.finish())
+    }
+
+    /// Delete specific chunks by id.
+    async fn delete_chunks_by_ids(&self, ids: &[ChunkId]) -> Result<(), WeaviateError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let ids = ids.iter().map(|id| id.0.to_string());
+        self.batch_delete(&Filter::contains_any("id", ids)).await
+    }
+
+    /// Delete all chunks of several documents.
+    async fn delete_chunks_by_documents(
+        &self,
+        document_ids: &[DocumentId],
+    ) -> Result<(), WeaviateError> {
+        // Keep each filter small; a batch delete also caps how many objects it removes
+        for batch in document_ids.chunks(DELETE_DOCUMENTS_PER_BATCH) {
+            let ids = batch.iter().map(|id| id.0.to_string());
+            self.batch_delete(&Filter::contains_any("documentId", ids))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Delete all chunks belonging to a user.
+    async fn delete_chunks_by_user(&self, user_id: UserId) -> Result<(), WeaviateError> {
+        self.batch_delete(&Filter::equal("userId", user_id.0.to_string()))
+            .await
+    }
+
+    /// Drop the class and every object in it.
+    async fn delete_class(&self) -> Result<(), WeaviateError> {
+        let url = format!("{}/v1/schema/{}", self.base_url, self.class);
+        let resp = self.client.delete(&url).send().await?;
+
+        if !resp.status().is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(WeaviateError::Delete(body));
+        }
+
+        Ok(())
+    }
+
+    /// Ids of all chunks currently stored for a document.
+    async fn chunk_ids_by_document(
+        &self,
+        document_id: DocumentId,
+    ) -> Result<Vec<ChunkId>, WeaviateError> {
+        // Weaviate's `after` cursor can't be combined with a filter, so page
+        // by chunk index instead. Versions of a document share indexes, so
+        // pages overlap at their boundary and ids are deduplicated
+        let mut ids = Vec::new();
+        let mut seen = HashSet::new();
+        let mut from = (Operator::GreaterThanEqual, 0);
+        loop {
+            let query = Query::get(&self.class)
+                .filter(&Filter::all(vec![
+                    Filter::equal("documentId", document_id.0.to_string()),
+                    Filter::condition("chunkIndex", from.0, from.1),
+                ]))
+                .sort_ascending("chunkIndex")
+                .limit(MAX_OBJECTS_PER_QUERY)
+                .select("chunkIndex _additional { id }");
+
+            let data = self.graphql(&query).await?;
+            let page = data["Get"][self.class.as_str()]
+                .as_array()
+                .cloned()
+                .unwrap_or_default();
+            let mut last_index = from.1;
+            let mut added = false;
+            for chunk in &page {
+                if let Some(id) = chunk["_additional"]["id"]
+                    .as_str()
+                    .and_then(|id| id.parse().ok())
+                {
+                    if seen.insert(id) {
+                        ids.push(ChunkId(id));
+                        added = true;
+                    }
+                }
+                last_index = last_index.max(chunk["chunkIndex"].as_i64().unwrap_or_default());
+            }
+
+            if page.len() < MAX_OBJECTS_PER_QUERY {
+                return Ok(ids);
+            }
+            // A full page of one index already seen can only move past it
+            from = if added {
+                (Operator::GreaterThanEqual, last_index)
+            } else {
+                (Operator::GreaterThan, last_index)
+            };
+        }
+    }
+
+    /// Stored vectors of the given chunks.
+    async fn chunk_vectors(
+        &self,
+        ids: &[ChunkId],
+    ) -> Result<HashMap<ChunkId, Vec<f32>>, WeaviateError> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let query = Query::get(&self.class)
+            .filter(&Filter::contains_any(
+                "id",
+                ids.iter().map(|id| id.0.to_string()),
+            ))
+            .limit(ids.len())
+            .select("_additional { id vector }");
+
+        let data = self.graphql(&query).await?;
+        let vectors = data["Get"][self.class.as_str()]
+            .as_array()
+            .map(|chunks| {
+                chunks
+                    .iter()
+                    .filter_map(|c| {
+                        let additional = &c["_additional"];
+                        let id = additional["id"].as_str()?.parse().ok()?;
+                        let vector = serde_json::from_value(additional["vector"].clone()).ok()?;
+                        Some((ChunkId(id), vector))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(vectors)
+    }
+
+    /// Number of chunks stored per document for one user.
+    async fn chunk_counts_by_document(
+        &self,
+        user_id: UserId,
+    ) -> Result<HashMap<DocumentId, usize>, WeaviateError> {
+        let filter = Filter::equal("userId", user_id.0.to_string());
+        let groups = self
+            .count_all_grouped_by("documentId", Some(&filter))
+            .await?;
+        Ok(groups
+            .into_iter()
+            .filter_map(|(id, count)| Some((DocumentId(id.parse().ok()?), count)))
+            .collect())
+    }
+
+    /// Users that own at least one chunk.
+    async fn chunk_owners(&self) -> Result<Vec<UserId>, WeaviateError> {
+        let groups = self.count_all_grouped_by("userId", None).await?;
+        Ok(groups
+            .into_keys()
+            .filter_map(|id| Some(UserId(id.parse().ok()?)))
+            .collect())
+    }
+
+    /// Like `count_grouped_by`, for callers that need every group. Weaviate
+    /// can't page an aggregation, so reaching the group limit is an error
+    /// rather than a partial count.
+    async fn count_all_grouped_by(
+        &self,
+        property: &str,
+        filter: Option<&Filter>,
+    ) -> Result<HashMap<String, usize>, WeaviateError> {
+        let groups = self.count_grouped_by(property, filter).await?;
+        if groups.len() >= MAX_OBJECTS_PER_QUERY {
+            return Err(WeaviateError::Query(format!(
+                "{property} has at least {MAX_OBJECTS_PER_QUERY} values, more than one aggregation returns"
+            )));
+        }
+        Ok(groups)
+    }
+
+    async fn count_grouped_by(
+        &self,
+        property: &str,
+        filter: Option<&Filter>,
+    ) -> Result<HashMap<String, usize>, WeaviateError> {
+        let mut query = Query::aggregate(&self.class);
+        if let Some(filter) = filter {
+            query = query.filter(filter);
+        }
+        let query = query
+            .group_by(property)
+            .limit(MAX_OBJECTS_PER_QUERY)
+            .select("groupedBy { value } meta { count }");
+
+        let data = self.graphql(&query).await?;
+        let groups = data["Aggregate"][self.class.as_str()]
+            .as_array()
+            .map(|groups| {
+                groups
+                    .iter()
+                    .filter_map(|g| {
+                        let value = g["groupedBy"]["value"].as_str()?.to_string();
+                        let count = g["meta"]["count"].as_u64()? as usize;
+                        Some((value, count))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(groups)
+    }
+
+    /// Run a query and return its `data`, failing on GraphQL `errors`.
+    async fn graphql(&self, query: &Query) -> Result<serde_json::Value, WeaviateError> {
+        let graphql = query.build()?;
+        let url = format!("{}/v1/graphql", self.base_url);
+        let resp = self
+            .client
+            .post(&url)
+            .json(&json!({ "query": graphql }))
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(WeaviateError::Query(body));
+        }
+
+        graphql::into_data(resp.json().await?)
+    }
+
+    async fn batch_delete(&self, filter: &Filter) -> Result<(), WeaviateError> {
+        let batch_delete = json!({
+            "match": {
+                "class": self.class,
+                "where": filter.to_json()?
+            }
+        });
+
+        let url = format!("{}/v1/batch/objects", self.base_url);
+        let resp = self.client.delete(&url).json(&batch_delete).send().await?;
+
+        if !resp.status().is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(WeaviateError::Delete(body));
+        }
+
+        Ok(())
+    }
+
+    /// Check if Weaviate is healthy.
+    async fn health_check(&self) -> Result<bool, WeaviateError> {
+        let url = format!("{}/v1/.well-known/ready", self.base_url);
+        let resp = self.client.get(&url).send().await?;
+        Ok(resp.status().is_success())
+    }
+}
+
+#[async_trait]
+impl VectorStore for WeaviateStore {
+    fn with_index(&self, name: &str) -> Arc<dyn VectorStore> {
+        Arc::new(Self {
+            client: self.client.clone(),
+            base_url: self.base_url.clone(),
+            class: name.to_string(),
+        })
+    }
+
+    fn index_name(&self) -> &str {
+        &self.class
+    }
+
+    async fn ensure_index(&self) -> Result<(), VectorStoreError> {
+        Ok(self.ensure_schema().await?)
+    }
+
+    async fn drop_index(&self) -> Result<(), VectorStoreError> {
+        Ok(self.delete_class().await?)
+    }
+
+    async fn upsert_chunks(
+        &self,
+        chunks: &[Chunk],
+        vectors: &[Vec<f32>],
+    ) -> Result<(), VectorStoreError> {
+        Ok(self.batch_upsert_chunks(chunks, vectors).await?)
+    }
+
+    async fn hybrid_search(
+        &self,
+        query: &str,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+        alpha: f32,
+    ) -> Result<Vec<SearchResult>, VectorStoreError> {
+        Ok(
+            WeaviateStore::hybrid_search(self, query, vector, user_id, filters, limit, alpha)
+                .await?,
+        )
+    }
+
+    async fn keyword_search(
+        &self,
+        query: &str,
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, VectorStoreError> {
+        Ok(WeaviateStore::keyword_search(self, query, user_id, filters, limit).await?)
+    }
+
+    async fn vector_search(
+        &self,
+        vector: &[f32],
+        user_id: UserId,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, VectorStoreError> {
+        Ok(WeaviateStore::vector_search(self, vector, user_id, filters, limit).await?)
+    }
+
+    async fn facet_counts(
+        &self,
+        query: &str,
+        user_id: UserId,
+        filters: &SearchFilters,
+        request: &FacetRequest,
+    ) -> Result<FacetCounts, VectorStoreError> {
+        Ok(WeaviateStore::facet_counts(self, query, user_id, filters, request).await?)
+    }
+
+    async fn chunk_ids_by_document(
+        &self,
+        document_id: DocumentId,
+    ) -> Result<Vec<ChunkId>, VectorStoreError> {
+        Ok(WeaviateStore::chunk_ids_by_document(self, document_id).await?)
+    }
+
+    async fn delete_chunks_by_ids(&self, ids: &[ChunkId]) -> Result<(), VectorStoreError> {
+        Ok(WeaviateStore::delete_chunks_by_ids(self, ids).await?)
+    }
+
+    async fn delete_chunks_by_documents(
+        &self,
+        document_ids: &[DocumentId],
+    ) -> Result<(), VectorStoreError> {
+        Ok(WeaviateStore::delete_chunks_by_documents(self, document_ids).await?)
+    }
+
+    async fn delete_chunks_by_user(&self, user_id: UserId) -> Result<(), VectorStoreError> {
+        Ok(WeaviateStore::delete_chunks_by_user(self, user_id).await?)
+    }
+
+    async fn chunk_counts_by_document(
+        &self,
+        user_id: UserId,
+    ) -> Result<HashMap<DocumentId, usize>, VectorStoreError> {
+        Ok(WeaviateStore::chunk_counts_by_document(self, user_id).await?)
+    }
+
+    async fn chunk_vectors(
+        &self,
+        ids: &[ChunkId],
+    ) -> Result<HashMap<ChunkId, Vec<f32>>, VectorStoreError> {
+        Ok(WeaviateStore::chunk_vectors(self, ids).await?)
+    }
+
+    async fn chunk_owners(&self) -> Result<Vec<UserId>, VectorStoreError> {
+        Ok(WeaviateStore::chunk_owners(self).await?)
+    }
+
+    async fn health_check(&self) -> Result<bool, VectorStoreError> {
+        Ok(WeaviateStore::health_check(self).await?)
+    }
+}
+
+/// Property definitions of the Chunk class.
+fn chunk_properties() -> Vec<serde_json::Value> {
+    vec![
+        json!({
+            "name": "text",
+            "dataType": ["text"],
+            "tokenization": "word",
+            "indexFilterable": true,
+            "indexSearchable": true
+        }),
+        json!({
+            "name": "documentId",
+            "dataType": ["text"],
+            "tokenization": "field",
+            "indexFilterable": true,
+            "indexSearchable": false
+        }),
+        json!({
+            "name": "userId",
+            "dataType": ["text"],
+            "tokenization": "field",
+            "indexFilterable": true,
+            "indexSearchable": false
+        }),
+        json!({
+            "name": "sourceType",
+            "dataType": ["text"],
+            "tokenization": "field",
+            "indexFilterable": true
+        }),
+        json!({
+            "name": "documentTitle",
+            "dataType": ["text"],
+            "tokenization": "word",
+            "indexSearchable": true
+        }),
+        json!({
+            "name": "sourceUrl",
+            "dataType": ["text"],
+            "tokenization": "field",
+            "indexFilterable": false,
+            "indexSearchable": false
+        }),
+        json!({
+            "name": "chunkIndex",
+            "dataType": ["int"]
+        }),
+        json!({
+            "name": "sectionTitle",
+            "dataType": ["text"],
+            "tokenization": "word",
+            "indexSearchable": true
+        }),
+        json!({
+            "name": "chunkKind",
+            "dataType": ["text"],
+            "tokenization": "field",
+            "indexFilterable": true,
+            "indexSearchable": false
+        }),
+        json!({
+            "name": "parentId",
+            "dataType": ["text"],
+            "tokenization": "field",
+            "indexFilterable": true,
+            "indexSearchable": false
+        }),
+        json!({
+            "name": "contextHeader",
+            "dataType": ["text"],
+            "tokenization": "word",
+            "indexFilterable": false,
+            "indexSearchable": false
+        }),
+        json!({
+            "name": "metadata",
+            "dataType": ["text"],
+            "tokenization": "field",
+            "indexSearchable": false
+        }),
+        json!({
+            "name": "metadataFields",
+            "description": "Top-level metadata entries as [key, value] pairs, for filtering",
+            "dataType": ["text[]"],
+            "tokenization": "field",
+            "indexFilterable": true,
+            "indexSearchable": false
+        }),
+        json!({
+            "name": "indexedAt",
+            "dataType": ["date"],
+            "indexFilterable": true
+        }),
+        json!({
+            "name": "updatedAt",
+            "dataType": ["date"],
+            "indexFilterable": true
+        }),
+    ]
+}
+
+/// Where a search reports each result's score.
+#[derive(Clone, Copy)]
+enum Score {
+    /// `score`, a string: BM25 or the fused hybrid score.
+    Score,
+    /// `distance`, the cosine distance of a vector search.
+    Distance,
+}
+
+impl Score {
+    fn field(self) -> &'static str {
+        match self {
+            Score::Score => "score",
+            Score::Distance => "distance",
+        }
+    }
+
+    /// The score from a result's `_additional`; distances become cosine
+    /// similarity so higher is better for every search.
+    fn read(self, additional: &serde_json::Value) -> f32 {
+        match self {
+            Score::Score => additional["score"]
+                .as_str()
+                .and_then(|s| s.parse::<f32>().ok())
+                .unwrap_or(0.0),
+            Score::Distance => additional["distance"]
+                .as_f64()
+                .map_or(0.0, |d| 1.0 - d as f32),
+        }
+    }
+}
+
+/// The chunks a search may return: the user's, narrowed by `filters`.
+fn scope_filter(user_id: UserId, filters: &SearchFilters) -> Filter {
+    let mut operands = vec![Filter::equal("userId", user_id.0.to_string())];
+    if let Some(source) = &filters.source_type {
+        operands.push(Filter::equal("sourceType", source.as_str()));
+    }
+    if let Some(kind) = filters.chunk_kind {
+        // Chunks indexed before chunkKind existed have none and are text.
+        // NotEqual also matches a missing value, so text means "not a table".
+        operands.push(match kind {
+            ChunkKind::Text => Filter::condition(
+                "chunkKind",
+                Operator::NotEqual,
+                ChunkKind::Table.to_string(),
+            ),
+            ChunkKind::Table => Filter::equal("chunkKind", kind.to_string()),
+        });
+    }
+    if let Some(filter) = &filters.filter {
+        operands.push(where_filter(filter, false));
+    }
+    Filter::all(operands)
+}
+
+/// Translate a search filter into Weaviate's filter language. There is no
+/// `Not` operator, so negations are pushed down to the conditions.
+fn where_filter(expr: &FilterExpr, negate: bool) -> Filter {
+    let equal = if negate {
+        Operator::NotEqual
+    } else {
+        Operator::Equal
+    };
+
+    match expr {
+        FilterExpr::And(operands) | FilterExpr::Or(operands) => {
+            let operands = operands.iter().map(|f| where_filter(f, negate)).collect();
+            if matches!(expr, FilterExpr::And(_)) != negate {
+                Filter::all(operands)
+            } else {
+                Filter::any(operands)
+            }
+        }
+        FilterExpr::Not(operand) => where_filter(operand, !negate),
+        FilterExpr::IndexedAt(range) => date_filter("indexedAt", range, negate),
+        FilterExpr::UpdatedAt(range) => date_filter("updatedAt", range, negate),
+        FilterExpr::DocumentIds(ids) | FilterExpr::ExcludeDocumentIds(ids) => {
+            let ids = ids.iter().map(|id| id.0.to_string());
+            if matches!(expr, FilterExpr::DocumentIds(_)) != negate {
+                Filter::contains_any("documentId", ids)
+            } else {
+                Filter::all(
+                    ids.map(|id| Filter::condition("documentId", Operator::NotEqual, id))
+                        .collect(),
+                )
+            }
+        }
+        // Both properties use word tokenization, so each word is a token
+        FilterExpr::Title(value) | FilterExpr::Section(value) => {
+            let property = match expr {
+                FilterExpr::Title(_) => "documentTitle",
+                _ => "sectionTitle",
+            };
+            let words = hybrid::tokenize(value)
+                .into_iter()
+                .map(|word| Filter::condition(property, equal, word))
+                .collect();
+            if negate {
+                Filter::any(words)
+            } else {
+                Filter::all(words)
+            }
+        }
+        FilterExpr::Metadata(condition) => {
+            Filter::condition("metadataFields", equal, condition.field())
+        }
+    }
+}
+
+/// `from <= property < to`, or its complement.
+fn date_filter(property: &str, range: &DateRange, negate: bool) -> Filter {
+    let (after, before) = if negate {
+        (Operator::LessThan, Operator::GreaterThanEqual)
+    } else {
+        (Operator::GreaterThanEqual, Operator::LessThan)
+    };
+    let mut bounds = Vec::new();
+    if let Some(from) = range.from {
+        bounds.push(Filter::condition(property, after, from));
+    }
+    if let Some(to) = range.to {
+        bounds.push(Filter::condition(property, before, to));
+    }
+    if negate {
+        Filter::any(bounds)
+    } else {
+        Filter::all(bounds)
+    }
+}
+
+/// Build the Weaviate object for a chunk and its embedding.
+fn chunk_object(class: &str, chunk: &Chunk, vector: &[f32]) -> serde_json::Value {
+    json!({
+        "class": class,
+        "id": chunk.id.0.to_string(),
+        "vector": vector,
+        "properties": {
+            "text": chunk.text,
+            "documentId": chunk.document_id.0.to_string(),
+            "userId": chunk.user_id.0.to_string(),
+            "sourceType": chunk.source_type.to_string(),
+            "documentTitle": chunk.document_title,
+            "sourceUrl": chunk.source_url,
+            "chunkIndex": chunk.chunk_index,
+            "sectionTitle": chunk.section_title,
+            "chunkKind": chunk.kind.to_string(),
+            "parentId": chunk.parent_id.map(|id| id.0.to_string()),
+            "contextHeader": chunk.context_header,
+            "metadata": serde_json::to_string(&chunk.metadata).unwrap_or_default(),
+            "metadataFields": filter::metadata_fields(&chunk.metadata),
+            "indexedAt": chunk.indexed_at.to_rfc3339(),
+            "updatedAt": chunk.updated_at.to_rfc3339(),
+        }
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WeaviateError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("schema creation failed: {0}")]
+    SchemaCreation(String),
+    #[error("insert failed: {0}")]
+    Insert(String),
+    #[error("query failed: {0}")]
+    Query(String),
+    #[error("delete failed: {0}")]
+    Delete(String),
+    #[error("invalid query: {0}")]
+    InvalidQuery(String),
+    #[error("GraphQL errors: {}", graphql_messages(.0))]
+    GraphQl(Vec<GraphQlError>),
+}
+
+fn graphql_messages(errors: &[GraphQlError]) -> String {
+    let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+    messages.join("; ")
+}